@@ -0,0 +1,76 @@
+mod specification;
+mod output;
+
+use std::env;
+use std::sync::Arc;
+use anyhow::{bail, Result};
+use stopwatch::Stopwatch;
+use unq_common::backtest::BacktestConfiguration;
+use unq_common::manager::AssetManager;
+use unq_common::web::Precision;
+use unq_common::{get_ini, get_ini_optional, get_ini_string};
+use unq_strategy::runner::run_backtests;
+use crate::output::{print_summary, write_series, SummaryRow};
+use crate::specification::{CliSpecification, OutputFormat};
+
+// The CLI shares the data directories and the backtest settings with the server
+const CONFIG_PATH: &str = "config/unq-server.ini";
+
+fn main() -> Result<()> {
+	let arguments: Vec<String> = env::args().collect();
+	let [_, specification_path] = arguments.as_slice() else {
+		bail!("Usage: unq-cli <.toml/.json specification>");
+	};
+	let specification = CliSpecification::read(specification_path)?;
+	let config = get_ini(CONFIG_PATH)?;
+	let server_section = "server";
+	let ticker_directory = get_ini_string(&config, server_section, "ticker_directory")?;
+	let csv_directory = get_ini_string(&config, server_section, "csv_directory")?;
+	let assets_path = get_ini_string(&config, server_section, "assets")?;
+	let script_directory = get_ini_string(&config, server_section, "script_directory")?;
+	let archive_memory_limit = get_ini_optional::<usize>(&config, server_section, "archive_memory_limit")?
+		.map(|x| x * 1024 * 1024);
+	let backtest_configuration = BacktestConfiguration::from_ini(&config)?;
+	let output_directory = specification.output_directory
+		.clone()
+		.unwrap_or("output".to_string());
+	let formats = specification.formats
+		.clone()
+		.unwrap_or(vec![OutputFormat::Json]);
+	let precision = specification.precision.unwrap_or(Precision::Full);
+	println!("Loading assets");
+	let stopwatch = Stopwatch::start_new();
+	let asset_manager = AssetManager::new(&ticker_directory, &csv_directory, &assets_path, archive_memory_limit)?;
+	let asset_manager_arc = Arc::new(asset_manager);
+	println!("Loaded assets in {} ms", stopwatch.elapsed_ms());
+	let mut rows = Vec::new();
+	let mut failed = 0;
+	for backtest in specification.backtests.iter() {
+		println!("Running backtest \"{}\"", backtest.name);
+		let stopwatch = Stopwatch::start_new();
+		// Keep going if one of the backtests fails so that a single error doesn't ruin an entire batch
+		let result = backtest.request
+			.get_specification(&backtest_configuration, &asset_manager_arc)
+			.and_then(|backtest_specification| run_backtests(backtest_specification, asset_manager_arc.clone(), &script_directory, None))
+			.and_then(|series| {
+				write_series(&series, &backtest.name, &output_directory, &formats, precision)?;
+				Ok(series)
+			});
+		match result {
+			Ok(series) => {
+				let seconds = stopwatch.elapsed().as_secs_f64();
+				rows.push(SummaryRow::new(&backtest.name, &series, seconds));
+			},
+			Err(error) => {
+				println!("Backtest \"{}\" failed: {error}", backtest.name);
+				failed += 1;
+			}
+		}
+	}
+	println!();
+	print_summary(&rows);
+	if failed > 0 {
+		bail!("{failed} out of {} backtests failed", specification.backtests.len());
+	}
+	Ok(())
+}
@@ -0,0 +1,108 @@
+use std::fs;
+use std::fs::File;
+use std::path::Path;
+use anyhow::{anyhow, Context, Result};
+use unq_common::backtest::BacktestSeries;
+use unq_common::export::ExportFormat;
+use unq_common::web::{with_precision, Precision};
+use unq_common::PathDisplay;
+use crate::specification::OutputFormat;
+
+// Metrics of the best result that are displayed in the summary table, along with their column titles
+const SUMMARY_METRICS: [(&str, &str); 6] = [
+	("totalReturn", "Return"),
+	("compoundAnnualGrowthRate", "CAGR"),
+	("sharpeRatio", "Sharpe"),
+	("sortinoRatio", "Sortino"),
+	("maxDrawdown", "Max DD"),
+	("trades", "Trades")
+];
+
+pub struct SummaryRow {
+	name: String,
+	runs: usize,
+	metrics: Vec<f64>,
+	seconds: f64
+}
+
+impl SummaryRow {
+	pub fn new(name: &str, series: &BacktestSeries, seconds: f64) -> SummaryRow {
+		let best_metrics = series.get_best_result().get_metrics();
+		let metrics = SUMMARY_METRICS
+			.iter()
+			.map(|(key, _)| best_metrics
+				.iter()
+				.find(|(name, _)| name == key)
+				.map(|(_, value)| *value)
+				.unwrap_or(f64::NAN))
+			.collect();
+		SummaryRow {
+			name: name.to_string(),
+			runs: series.get_results().len(),
+			metrics,
+			seconds
+		}
+	}
+}
+
+pub fn write_series(series: &BacktestSeries, name: &String, output_directory: &String, formats: &[OutputFormat], precision: Precision) -> Result<()> {
+	let directory = Path::new(output_directory);
+	fs::create_dir_all(directory)
+		.with_context(|| anyhow!("Failed to create output directory \"{output_directory}\""))?;
+	for format in formats {
+		let export_format = match format {
+			OutputFormat::Json => {
+				let path = directory.join(format!("{name}.json"));
+				let json = with_precision(precision, || serde_json::to_string_pretty(series))?;
+				fs::write(&path, json)
+					.with_context(|| anyhow!("Failed to write \"{}\"", path.to_string()))?;
+				continue;
+			},
+			OutputFormat::Csv => ExportFormat::Csv,
+			OutputFormat::Parquet => ExportFormat::Parquet
+		};
+		let best_result = series.get_best_result();
+		let tables = [
+			("sweep", series.get_sweep_table()?),
+			("equity-curve", best_result.get_equity_curve_table()?),
+			("trades", best_result.get_trades_table()?)
+		];
+		for (suffix, table) in tables {
+			let path = directory.join(format!("{name}-{suffix}.{}", export_format.get_extension()));
+			let file = File::create(&path)
+				.with_context(|| anyhow!("Failed to create \"{}\"", path.to_string()))?;
+			table.write(export_format, file)
+				.with_context(|| anyhow!("Failed to write \"{}\"", path.to_string()))?;
+		}
+	}
+	Ok(())
+}
+
+pub fn print_summary(rows: &[SummaryRow]) {
+	let name_width = rows
+		.iter()
+		.map(|x| x.name.len())
+		.max()
+		.unwrap_or(0)
+		.max(4);
+	let mut header = format!("{:<name_width$} {:>6}", "Name", "Runs");
+	for (_, title) in SUMMARY_METRICS {
+		header += format!(" {title:>10}").as_str();
+	}
+	header += format!(" {:>10}", "Time").as_str();
+	println!("{header}");
+	println!("{}", "-".repeat(header.len()));
+	for row in rows {
+		let mut line = format!("{:<name_width$} {:>6}", row.name, row.runs);
+		for ((key, _), value) in SUMMARY_METRICS.iter().zip(row.metrics.iter()) {
+			let cell = match *key {
+				"trades" => format!("{value:.0}"),
+				"totalReturn" | "compoundAnnualGrowthRate" | "maxDrawdown" => format!("{:.2}%", value * 100.0),
+				_ => format!("{value:.2}")
+			};
+			line += format!(" {cell:>10}").as_str();
+		}
+		line += format!(" {:>9.1}s", row.seconds).as_str();
+		println!("{line}");
+	}
+}
@@ -0,0 +1,98 @@
+use std::fs;
+use std::path::Path;
+use anyhow::{anyhow, bail, Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use unq_common::api::RunBacktestRequest;
+use unq_common::web::Precision;
+
+#[derive(Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+	Json,
+	// CSV and Parquet output consists of the sweep table and the equity curve and trades of the best result
+	Csv,
+	Parquet
+}
+
+/*
+Batch of backtests loaded from a .toml or .json file, e.g.:
+
+outputDirectory = "output"
+formats = ["json", "csv", "parquet"]
+precision = "full"
+
+[[backtests]]
+name = "trend"
+strategy = "script"
+symbols = ["ES", "NQ"]
+from = { date = "2010-01-01T00:00:00" }
+to = { specialKeyword = "last" }
+timeFrame = "daily"
+parameters = [
+	{ name = "script", stringValue = "trend" },
+	{ name = "positions", stringValue = "fixed" },
+	{ name = "period", value = 10, limit = 50, increment = 10 }
+]
+
+The members of each backtest other than the name are identical to those of the /backtest request of the server.
+*/
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CliSpecification {
+	// Directory the results are written to, defaults to "output"
+	pub output_directory: Option<String>,
+	// Defaults to JSON only
+	pub formats: Option<Vec<OutputFormat>>,
+	// Precision of the values in JSON output, "compact", "full" or a number of significant digits, defaults to "full"
+	pub precision: Option<Precision>,
+	pub backtests: Vec<CliBacktest>
+}
+
+#[derive(Deserialize)]
+pub struct CliBacktest {
+	// Used as the file name of the output files
+	pub name: String,
+	#[serde(flatten)]
+	pub request: RunBacktestRequest
+}
+
+impl CliSpecification {
+	pub fn read(path: &String) -> Result<CliSpecification> {
+		let content = fs::read_to_string(path)
+			.with_context(|| anyhow!("Failed to read specification from \"{path}\""))?;
+		let extension = Path::new(path)
+			.extension()
+			.and_then(|x| x.to_str());
+		let specification: CliSpecification = match extension {
+			Some("toml") => toml::from_str(content.as_str())
+				.with_context(|| anyhow!("Failed to parse specification \"{path}\""))?,
+			Some("json") => serde_json::from_str(content.as_str())
+				.with_context(|| anyhow!("Failed to parse specification \"{path}\""))?,
+			_ => bail!("Specification must be a .toml or .json file")
+		};
+		specification.validate()?;
+		Ok(specification)
+	}
+
+	fn validate(&self) -> Result<()> {
+		if self.backtests.is_empty() {
+			bail!("Specification doesn't contain any backtests");
+		}
+		// The names are used as file names so they are subject to the same restrictions as script names
+		let pattern = Regex::new("^[A-Za-z0-9 _-]+$")?;
+		for backtest in self.backtests.iter() {
+			if !pattern.is_match(backtest.name.as_str()) {
+				bail!("Invalid characters in backtest name \"{}\"", backtest.name);
+			}
+			let duplicates = self.backtests
+				.iter()
+				.filter(|x| x.name == backtest.name)
+				.count();
+			if duplicates > 1 {
+				bail!("Backtest name \"{}\" is not unique", backtest.name);
+			}
+		}
+		Ok(())
+	}
+}
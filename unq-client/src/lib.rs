@@ -0,0 +1,183 @@
+/*
+Typed client for the HTTP API of unq-server.
+Requests and responses use the structures from unq_common::api, the same ones the server and its OpenAPI document
+(served at /openapi.json) are based on, so changes to the wire format are caught at compile time.
+*/
+use std::collections::HashMap;
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::RequestBuilder;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use unq_common::api::{BacktestDiff, CancelJobRequest, CorrelationData, DiffResultsRequest, ExportBacktestRequest, ExportCorrelationRequest, ExportHistoryRequest, GetCorrelationRequest, GetHistoryRequest, GetJobStatusRequest, JobStatusResult, LoadResultRequest, OhlcRecordWeb, QueryRequest, QueryResult, ReloadResult, ReplayBacktestRequest, ReplayBacktestResult, Response, RunBacktestRequest, StoredBacktest, StoredBacktestSummary, SubmitJobResult};
+use unq_common::backtest::BacktestSeries;
+use unq_common::web::Precision;
+
+pub struct Client {
+	// e.g. "http://127.0.0.1:8000", without a trailing slash
+	base_url: String,
+	http_client: reqwest::Client,
+	// Requested precision of WebF64 values, the server defaults to compact values
+	precision: Option<Precision>,
+	// Only required if the server has been configured with API keys
+	api_key: Option<String>
+}
+
+impl Client {
+	pub fn new(base_url: &str) -> Client {
+		Client {
+			base_url: base_url.trim_end_matches('/').to_string(),
+			http_client: reqwest::Client::new(),
+			precision: None,
+			api_key: None
+		}
+	}
+
+	pub fn with_precision(mut self, precision: Precision) -> Client {
+		self.precision = Some(precision);
+		self
+	}
+
+	pub fn with_api_key(mut self, api_key: &str) -> Client {
+		self.api_key = Some(api_key.to_string());
+		self
+	}
+
+	pub async fn get_history(&self, request: &GetHistoryRequest) -> Result<HashMap<String, Vec<OhlcRecordWeb>>> {
+		self.post("/history", request).await
+	}
+
+	pub async fn get_correlation(&self, request: &GetCorrelationRequest) -> Result<CorrelationData> {
+		self.post("/correlation", request).await
+	}
+
+	pub async fn run_backtest(&self, request: &RunBacktestRequest) -> Result<BacktestSeries> {
+		self.post("/backtest", request).await
+	}
+
+	pub async fn replay_backtest(&self, request: &ReplayBacktestRequest) -> Result<ReplayBacktestResult> {
+		self.post("/backtest/replay", request).await
+	}
+
+	pub async fn submit_job(&self, request: &RunBacktestRequest) -> Result<SubmitJobResult> {
+		self.post("/jobs/submit", request).await
+	}
+
+	pub async fn get_job_status(&self, request: &GetJobStatusRequest) -> Result<JobStatusResult> {
+		self.post("/jobs/status", request).await
+	}
+
+	pub async fn cancel_job(&self, request: &CancelJobRequest) -> Result<()> {
+		let _: Option<Value> = self.post("/jobs/cancel", request).await?;
+		Ok(())
+	}
+
+	pub async fn list_results(&self) -> Result<Vec<StoredBacktestSummary>> {
+		let builder = self.get_builder("/results/list");
+		self.send(builder).await
+	}
+
+	pub async fn load_result(&self, request: &LoadResultRequest) -> Result<StoredBacktest> {
+		self.post("/results/load", request).await
+	}
+
+	pub async fn diff_results(&self, request: &DiffResultsRequest) -> Result<BacktestDiff> {
+		self.post("/results/diff", request).await
+	}
+
+	pub async fn run_query(&self, request: &QueryRequest) -> Result<Vec<QueryResult>> {
+		self.post("/query", request).await
+	}
+
+	// Requires an administrator key if the server has been configured with API keys
+	pub async fn reload(&self) -> Result<ReloadResult> {
+		let builder = self.get_builder("/admin/reload");
+		self.send(builder).await
+	}
+
+	// The export functions return the contents of the CSV or Parquet file
+	pub async fn export_history(&self, request: &ExportHistoryRequest) -> Result<Vec<u8>> {
+		self.export("/export/history", request).await
+	}
+
+	pub async fn export_correlation(&self, request: &ExportCorrelationRequest) -> Result<Vec<u8>> {
+		self.export("/export/correlation", request).await
+	}
+
+	pub async fn export_backtest(&self, request: &ExportBacktestRequest) -> Result<Vec<u8>> {
+		self.export("/export/backtest", request).await
+	}
+
+	fn get_url(&self, path: &str) -> String {
+		match self.precision {
+			Some(precision) => format!("{}{path}?precision={precision}", self.base_url),
+			None => format!("{}{path}", self.base_url)
+		}
+	}
+
+	fn get_builder(&self, path: &str) -> RequestBuilder {
+		let builder = self.http_client.post(self.get_url(path));
+		match &self.api_key {
+			Some(api_key) => builder.bearer_auth(api_key),
+			None => builder
+		}
+	}
+
+	async fn post<A, B>(&self, path: &str, request: &A) -> Result<B>
+	where
+		A: Serialize,
+		B: DeserializeOwned
+	{
+		let builder = self.get_builder(path).json(request);
+		self.send(builder).await
+	}
+
+	async fn send<T: DeserializeOwned>(&self, builder: RequestBuilder) -> Result<T> {
+		let response = builder
+			.send()
+			.await
+			.with_context(|| anyhow!("Failed to send request to {}", self.base_url))?;
+		// Authentication and rate limit errors are wrapped in JSON, too
+		let status = response.status();
+		let response: Response<T> = response.json()
+			.await
+			.with_context(|| anyhow!("Failed to deserialize response with status {status}"))?;
+		get_result(response)
+	}
+
+	// Successful exports are returned as files, errors are still wrapped in JSON
+	async fn export<A: Serialize>(&self, path: &str, request: &A) -> Result<Vec<u8>> {
+		let response = self.get_builder(path)
+			.json(request)
+			.send()
+			.await
+			.with_context(|| anyhow!("Failed to send request to {}", self.base_url))?;
+		let is_json = response.headers()
+			.get(CONTENT_TYPE)
+			.and_then(|x| x.to_str().ok())
+			.is_some_and(|x| x.starts_with("application/json"));
+		if is_json {
+			let response: Response<Value> = response.json().await?;
+			get_result(response)?;
+			bail!("Server returned JSON rather than a file");
+		}
+		let bytes = response
+			.error_for_status()?
+			.bytes()
+			.await?;
+		Ok(bytes.to_vec())
+	}
+}
+
+fn get_result<T: DeserializeOwned>(response: Response<T>) -> Result<T> {
+	if let Some(error) = response.error {
+		bail!("Server error: {error}");
+	}
+	// Unit results are serialized as null
+	match response.result {
+		Some(result) => Ok(result),
+		None => serde_json::from_value(Value::Null)
+			.with_context(|| anyhow!("Response did not contain a result"))
+	}
+}
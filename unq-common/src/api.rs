@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+use crate::backtest::{BacktestConfiguration, BacktestSeries, SimplifiedBacktestResult};
+use crate::datetime::RelativeDateTime;
+use crate::export::{ExportFormat, Table};
+use crate::manager::{ArchiveSnapshot, AssetManager};
+use crate::manifest::{BacktestManifest, BacktestSpecification};
+use crate::ohlc::{BarSpecification, OhlcArchive, OhlcRecord, TimeFrame};
+use crate::resample::{Alignment, ResamplePeriod};
+use crate::strategy::{StrategyParameter, StrategyParameters};
+use crate::web::WebF64;
+
+/*
+Request and response structures of the HTTP API.
+They are shared by the server, the OpenAPI document and the Rust client so that all of them agree on the wire format.
+*/
+
+// Envelope of all JSON responses, exactly one of the two members is set
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct Response<T> {
+	pub result: Option<T>,
+	pub error: Option<String>
+}
+
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GetHistoryRequest {
+	pub symbols: Vec<String>,
+	pub from: RelativeDateTime,
+	pub to: RelativeDateTime,
+	// Minutes, 1440 for daily data
+	pub time_frame: u16,
+	// Bars aggregated from tick data by unq-parser, if specified the time frame is only used to resolve relative dates
+	pub bars: Option<BarSpecification>,
+	// Resample the records to minute, hour, day, week or month buckets, if specified the time frame is only used to resolve relative dates
+	pub period: Option<ResamplePeriod>,
+	// How records are grouped when they are resampled to a larger time frame, defaults to clock alignment
+	pub alignment: Option<Alignment>
+}
+
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+pub struct GetCorrelationRequest {
+	pub symbols: Vec<String>,
+	pub from: RelativeDateTime,
+	pub to: RelativeDateTime
+}
+
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RunBacktestRequest {
+	pub strategy: String,
+	pub symbols: Vec<String>,
+	pub from: RelativeDateTime,
+	pub to: RelativeDateTime,
+	pub parameters: Vec<StrategyParameter>,
+	pub time_frame: TimeFrame,
+	// Symbol or time series name of a reference asset, overrides the benchmark from the configuration file
+	pub benchmark: Option<String>,
+	// Annual minimum acceptable return for the Sortino ratio, overrides the configuration file
+	pub minimum_acceptable_return: Option<f64>
+}
+
+impl RunBacktestRequest {
+	// Resolves relative dates and applies the overrides to the backtest configuration
+	pub fn get_specification(&self, backtest_configuration: &BacktestConfiguration, asset_manager: &AssetManager) -> Result<BacktestSpecification> {
+		let archives = self.symbols
+			.iter()
+			.map(|symbol| asset_manager.get_archive(symbol))
+			.collect::<Result<Vec<Arc<OhlcArchive>>>>()?;
+		let from = self.from.resolve(&self.to, &self.time_frame, &archives)?;
+		let to = self.to.resolve(&self.from, &self.time_frame, &archives)?;
+		let specification = BacktestSpecification {
+			strategy: self.strategy.clone(),
+			symbols: self.symbols.clone(),
+			parameters: StrategyParameters::from_vec(self.parameters.clone()),
+			from,
+			to,
+			time_frame: self.time_frame.clone(),
+			configuration: backtest_configuration.with_overrides(&self.benchmark, self.minimum_acceptable_return)
+		};
+		Ok(specification)
+	}
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ReplayBacktestRequest {
+	// Either the ID of a stored backtest or a manifest returned in a previous BacktestSeries
+	pub id: Option<u64>,
+	pub manifest: Option<BacktestManifest>
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ReplayBacktestResult {
+	pub series: BacktestSeries,
+	// Inputs that no longer match the manifest, e.g. modified archives or scripts
+	pub warnings: Vec<String>
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct SubmitJobResult {
+	pub id: u64
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct GetJobStatusRequest {
+	pub id: u64,
+	// Number of partial results the client has already received
+	pub offset: Option<usize>
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct CancelJobRequest {
+	pub id: u64
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum JobStatus {
+	Queued,
+	Running,
+	Completed,
+	Failed,
+	Cancelled
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JobStatusResult {
+	pub id: u64,
+	pub status: JobStatus,
+	pub completed: usize,
+	pub total: usize,
+	// Partial results starting at the offset specified in the request
+	pub results: Vec<SimplifiedBacktestResult>,
+	// Only available once the job has been completed
+	pub series: Option<BacktestSeries>,
+	pub error: Option<String>
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct LoadResultRequest {
+	pub id: u64
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct DiffResultsRequest {
+	pub id1: u64,
+	pub id2: u64
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct QueryRequest {
+	// Program in the Unquantified prompt language
+	pub query: String
+}
+
+// One result per call in the program that requested data from the server
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum QueryResult {
+	History {
+		data: HashMap<String, Vec<OhlcRecordWeb>>
+	},
+	Correlation {
+		data: CorrelationData
+	},
+	Backtest {
+		series: Box<BacktestSeries>
+	}
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ExportHistoryRequest {
+	#[serde(flatten)]
+	pub request: GetHistoryRequest,
+	pub format: ExportFormat
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ExportCorrelationRequest {
+	#[serde(flatten)]
+	pub request: GetCorrelationRequest,
+	pub format: ExportFormat
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ExportBacktestRequest {
+	// Either the ID of a stored backtest or a new backtest to run
+	pub id: Option<u64>,
+	pub request: Option<RunBacktestRequest>,
+	pub table: BacktestTable,
+	pub format: ExportFormat
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum BacktestTable {
+	// Daily equity curve of the best result
+	EquityCurve,
+	// Closed positions of the best result
+	Trades,
+	// Metrics of all parameter combinations
+	Sweep
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OhlcRecordWeb {
+	pub symbol: String,
+	pub time: NaiveDateTime,
+	pub open: WebF64,
+	pub high: WebF64,
+	pub low: WebF64,
+	pub close: WebF64,
+	pub volume: u32,
+	pub open_interest: Option<u32>
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct CorrelationData {
+	pub symbols: Vec<String>,
+	pub from: NaiveDateTime,
+	pub to: NaiveDateTime,
+	// Coefficients are only rounded if the client requested a number of significant digits
+	pub correlation: Vec<Vec<WebF64>>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredBacktest {
+	pub id: u64,
+	pub time: NaiveDateTime,
+	// Name of the user who ran the backtest, None for results stored before owners were recorded
+	pub owner: Option<String>,
+	// The manifest of the series contains the parameters and the hashes of the inputs
+	pub series: BacktestSeries
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredBacktestSummary {
+	pub id: u64,
+	pub time: NaiveDateTime,
+	pub owner: Option<String>,
+	pub strategy: String,
+	pub symbols: Vec<String>,
+	pub from: NaiveDateTime,
+	pub to: NaiveDateTime,
+	pub script_hash: Option<String>,
+	pub metrics: Vec<Metric>
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Metric {
+	pub name: String,
+	pub value: WebF64
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BacktestDiff {
+	pub id1: u64,
+	pub id2: u64,
+	/*
+	Differences in the specifications of the backtests.
+	Keys of strategy parameters are prefixed with "parameters.", configuration keys with "configuration.".
+	*/
+	pub parameters: Vec<ValueDifference>,
+	pub script_changed: bool,
+	pub assets_changed: bool,
+	// Archives that were only used by one of the runs or whose contents differed
+	pub archives: Vec<ArchiveDifference>,
+	pub metrics: Vec<MetricDifference>
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ValueDifference {
+	pub key: String,
+	pub value1: Value,
+	pub value2: Value
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveDifference {
+	pub symbol: String,
+	pub hash1: Option<String>,
+	pub hash2: Option<String>
+}
+
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricDifference {
+	pub name: String,
+	pub value1: WebF64,
+	pub value2: WebF64,
+	pub delta: WebF64
+}
+
+// Differences between the archives and assets before and after a reload, symbols are sorted
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReloadResult {
+	pub added: Vec<String>,
+	pub removed: Vec<String>,
+	// Archives whose contents changed, modification times alone are not taken into account
+	pub modified: Vec<String>,
+	pub assets_changed: bool
+}
+
+impl OhlcRecordWeb {
+	pub fn new(record: &OhlcRecord) -> OhlcRecordWeb {
+		OhlcRecordWeb {
+			symbol: record.symbol.clone(),
+			time: record.time,
+			open: WebF64::new(record.open),
+			high: WebF64::new(record.high),
+			low: WebF64::new(record.low),
+			close: WebF64::new(record.close),
+			volume: record.volume,
+			open_interest: record.open_interest
+		}
+	}
+}
+
+impl CorrelationData {
+	// Matrix with one row per symbol, the first column contains the symbol of the row
+	pub fn get_table(&self) -> Result<Table> {
+		let mut table = Table::new(self.symbols.len());
+		table.add_text("symbol", self.symbols.clone())?;
+		for (index, symbol) in self.symbols.iter().enumerate() {
+			let values = self.correlation
+				.iter()
+				.map(|row| row[index].get())
+				.collect();
+			table.add_float(symbol, values)?;
+		}
+		Ok(table)
+	}
+}
+
+impl ReloadResult {
+	pub fn new(previous: &AssetManager, current: &AssetManager) -> ReloadResult {
+		let previous_snapshots = previous.get_snapshots();
+		let current_snapshots = current.get_snapshots();
+		let get_symbols = |snapshots: &HashMap<String, ArchiveSnapshot>, filter: &dyn Fn(&ArchiveSnapshot) -> bool| {
+			let mut symbols: Vec<String> = snapshots
+				.values()
+				.filter(|x| filter(x))
+				.map(|x| x.symbol.clone())
+				.collect();
+			symbols.sort();
+			symbols
+		};
+		let added = get_symbols(current_snapshots, &|x| !previous_snapshots.contains_key(&x.symbol));
+		let removed = get_symbols(previous_snapshots, &|x| !current_snapshots.contains_key(&x.symbol));
+		let modified = get_symbols(current_snapshots, &|x| previous_snapshots
+			.get(&x.symbol)
+			.is_some_and(|previous| previous.hash != x.hash));
+		ReloadResult {
+			added,
+			removed,
+			modified,
+			assets_changed: previous.get_assets_hash() != current.get_assets_hash()
+		}
+	}
+}
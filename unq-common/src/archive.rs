@@ -0,0 +1,290 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use anyhow::{anyhow, bail, Context, Result};
+use rkyv::{AlignedVec, Archive, Deserialize, Infallible, Serialize};
+use crate::get_hash;
+use crate::ohlc::{ArchivedRawOhlcArchive, RawBarSeries, RawIntradaySeries, RawOhlcArchive, RawOhlcRecord};
+
+/*
+Layout of compressed (.zrk) and memory-mapped (.rk) archives:
+
+- 8 bytes: magic "UNQARCH\0"
+- 2 bytes: format version, little-endian
+- 2 bytes: reserved, zero
+- 4 bytes: length of the header, little-endian
+- rkyv-serialized ArchiveHeader, padded with zeroes to a multiple of 16 bytes
+- payload: rkyv-serialized RawOhlcArchive, zstd-compressed in .zrk files
+
+The payload starts at a multiple of 16 so that the archived types are properly aligned in memory-mapped files.
+Legacy archives (format version 0) only consist of the payload.
+Format version 2 added bars aggregated from tick data to the payload, version 3 multiple intraday resolutions.
+The header remained the same.
+Archives using older format versions can only be read by unq-parser and must be converted with "unq-parser --migrate".
+*/
+const MAGIC: &[u8; 8] = b"UNQARCH\0";
+const PREFIX_SIZE: usize = 16;
+const PAYLOAD_ALIGNMENT: usize = 16;
+// Used to tell compressed legacy archives apart from uncompressed ones
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+pub const ARCHIVE_FORMAT_VERSION: u16 = 3;
+const LEGACY_FORMAT_VERSION: u16 = 0;
+
+#[derive(Archive, Serialize, Deserialize, Clone)]
+#[archive(check_bytes)]
+pub struct ArchiveHeader {
+	// Version of unq-parser that created the archive
+	pub parser_version: String,
+	pub symbol: String,
+	// Finest intraday resolution, zero if there is no intraday data
+	pub intraday_time_frame: u16,
+	pub daily_records: u64,
+	// Total across all intraday resolutions
+	pub intraday_records: u64,
+	pub compressed: bool,
+	// Hexadecimal SHA-256 digest of the uncompressed payload
+	pub checksum: String
+}
+
+// Payload of format versions 0 and 1
+#[derive(Archive, Deserialize)]
+#[archive(check_bytes)]
+struct RawOhlcArchiveV1 {
+	daily: Vec<RawOhlcRecord>,
+	intraday: Vec<RawOhlcRecord>,
+	intraday_time_frame: u16
+}
+
+// Payload of format version 2
+#[derive(Archive, Deserialize)]
+#[archive(check_bytes)]
+struct RawOhlcArchiveV2 {
+	daily: Vec<RawOhlcRecord>,
+	intraday: Vec<RawOhlcRecord>,
+	intraday_time_frame: u16,
+	bars: Vec<RawBarSeries>
+}
+
+// Archive file whose header has been parsed but whose payload hasn't been decompressed or validated yet
+pub struct ArchiveFile<'a> {
+	pub format_version: u16,
+	// None for legacy archives
+	pub header: Option<ArchiveHeader>,
+	payload: &'a [u8]
+}
+
+impl<'a> ArchiveFile<'a> {
+	pub fn parse(data: &'a [u8]) -> Result<ArchiveFile<'a>> {
+		if !data.starts_with(MAGIC) {
+			let archive_file = ArchiveFile {
+				format_version: LEGACY_FORMAT_VERSION,
+				header: None,
+				payload: data
+			};
+			return Ok(archive_file);
+		}
+		if data.len() < PREFIX_SIZE {
+			bail!("Archive is truncated, the header is incomplete");
+		}
+		let format_version = u16::from_le_bytes([data[8], data[9]]);
+		if format_version > ARCHIVE_FORMAT_VERSION {
+			bail!("Archive uses format version {format_version} but only versions up to {ARCHIVE_FORMAT_VERSION} are supported, please update");
+		}
+		let header_size = u32::from_le_bytes([data[12], data[13], data[14], data[15]]) as usize;
+		let payload_offset = get_payload_offset(header_size);
+		if data.len() < payload_offset {
+			bail!("Archive is truncated, the header is incomplete");
+		}
+		// The header is copied since the file data read into a Vec<u8> isn't necessarily aligned
+		let mut header_bytes = AlignedVec::with_capacity(header_size);
+		header_bytes.extend_from_slice(&data[PREFIX_SIZE..PREFIX_SIZE + header_size]);
+		let archived_header = rkyv::check_archived_root::<ArchiveHeader>(&header_bytes)
+			.map_err(|error| anyhow!("Archive header is corrupt: {error}"))?;
+		let header: ArchiveHeader = archived_header
+			.deserialize(&mut Infallible)
+			.unwrap_or_else(|error| match error {});
+		let archive_file = ArchiveFile {
+			format_version,
+			header: Some(header),
+			payload: &data[payload_offset..]
+		};
+		Ok(archive_file)
+	}
+
+	pub fn is_compressed(&self) -> bool {
+		match &self.header {
+			Some(header) => header.compressed,
+			None => self.payload.starts_with(&ZSTD_MAGIC)
+		}
+	}
+
+	// Offset of the payload within the data the archive file was parsed from
+	pub fn get_payload_offset(&self, data: &[u8]) -> usize {
+		data.len() - self.payload.len()
+	}
+
+	// Decompresses the payload if necessary and verifies the checksum
+	pub fn decode_payload(&self) -> Result<AlignedVec> {
+		let mut payload = AlignedVec::new();
+		if self.is_compressed() {
+			zstd::stream::copy_decode(self.payload, &mut payload)
+				.with_context(|| anyhow!("Failed to decompress archive"))?;
+		} else {
+			payload.extend_from_slice(self.payload);
+		}
+		self.verify_checksum(&payload)?;
+		Ok(payload)
+	}
+
+	pub fn verify_checksum(&self, payload: &[u8]) -> Result<()> {
+		if let Some(header) = &self.header {
+			let checksum = get_hash(payload);
+			if checksum != header.checksum {
+				bail!("Checksum mismatch, archive is corrupt (expected {}, found {checksum})", header.checksum);
+			}
+		}
+		Ok(())
+	}
+
+	pub fn is_outdated(&self) -> bool {
+		self.format_version < ARCHIVE_FORMAT_VERSION
+	}
+
+	// Validates the archived records with bytecheck and compares them to the header
+	pub fn check_payload<'b>(&self, payload: &'b [u8]) -> Result<&'b ArchivedRawOhlcArchive> {
+		if self.is_outdated() {
+			bail!("Archive uses the outdated format version {}, please run \"unq-parser --migrate\"", self.format_version);
+		}
+		let archive = rkyv::check_archived_root::<RawOhlcArchive>(payload)
+			.map_err(|error| anyhow!("Archive is corrupt: {error}"))?;
+		let intraday_records = archive.intraday
+			.iter()
+			.map(|x| x.records.len())
+			.sum();
+		let intraday_time_frame = archive.intraday
+			.iter()
+			.map(|x| x.time_frame)
+			.min()
+			.unwrap_or(0);
+		self.check_header(archive.daily.len(), intraday_records, intraday_time_frame)?;
+		Ok(archive)
+	}
+
+	// Validates and deserializes the payload, including the payloads of older format versions
+	pub fn read_payload(&self, payload: &[u8]) -> Result<RawOhlcArchive> {
+		if !self.is_outdated() {
+			let archive = self.check_payload(payload)?
+				.deserialize(&mut Infallible)
+				.unwrap_or_else(|error| match error {});
+			return Ok(archive);
+		}
+		let (archive, intraday_time_frame) = if self.format_version == 2 {
+			let archive: RawOhlcArchiveV2 = rkyv::check_archived_root::<RawOhlcArchiveV2>(payload)
+				.map_err(|error| anyhow!("Archive is corrupt: {error}"))?
+				.deserialize(&mut Infallible)
+				.unwrap_or_else(|error| match error {});
+			let intraday_time_frame = archive.intraday_time_frame;
+			let archive = RawOhlcArchive {
+				daily: archive.daily,
+				intraday: Self::get_legacy_intraday(archive.intraday, intraday_time_frame),
+				bars: archive.bars
+			};
+			(archive, intraday_time_frame)
+		} else {
+			let archive: RawOhlcArchiveV1 = rkyv::check_archived_root::<RawOhlcArchiveV1>(payload)
+				.map_err(|error| anyhow!("Archive is corrupt: {error}"))?
+				.deserialize(&mut Infallible)
+				.unwrap_or_else(|error| match error {});
+			let intraday_time_frame = archive.intraday_time_frame;
+			let archive = RawOhlcArchive {
+				daily: archive.daily,
+				intraday: Self::get_legacy_intraday(archive.intraday, intraday_time_frame),
+				bars: Vec::new()
+			};
+			(archive, intraday_time_frame)
+		};
+		// Older headers specify the configured intraday time frame even if there is no intraday data
+		self.check_header(archive.daily.len(), archive.get_intraday_record_count(), intraday_time_frame)?;
+		Ok(archive)
+	}
+
+	// Older format versions stored a single intraday resolution
+	fn get_legacy_intraday(records: Vec<RawOhlcRecord>, time_frame: u16) -> Vec<RawIntradaySeries> {
+		if records.is_empty() {
+			return Vec::new();
+		}
+		let series = RawIntradaySeries {
+			time_frame,
+			records
+		};
+		vec![series]
+	}
+
+	fn check_header(&self, daily_records: usize, intraday_records: usize, intraday_time_frame: u16) -> Result<()> {
+		let Some(header) = &self.header else {
+			return Ok(());
+		};
+		let daily_records = daily_records as u64;
+		let intraday_records = intraday_records as u64;
+		if daily_records != header.daily_records || intraday_records != header.intraday_records {
+			bail!(
+				"Archive contains {daily_records} daily and {intraday_records} intraday records but the header specifies {} and {}",
+				header.daily_records,
+				header.intraday_records
+			);
+		}
+		if intraday_time_frame != header.intraday_time_frame {
+			bail!("Intraday time frame of archive does not match header");
+		}
+		Ok(())
+	}
+}
+
+// Reads the header of an archive without reading the payload, None for legacy archives
+pub fn read_archive_header(path: &Path) -> Result<Option<ArchiveHeader>> {
+	let mut file = File::open(path)?;
+	let mut data = Vec::new();
+	(&mut file).take(PREFIX_SIZE as u64).read_to_end(&mut data)?;
+	if data.len() == PREFIX_SIZE && data.starts_with(MAGIC) {
+		// Read the rest of the header, including the padding that precedes the payload
+		let header_size = u32::from_le_bytes([data[12], data[13], data[14], data[15]]) as usize;
+		let remainder = get_payload_offset(header_size) - PREFIX_SIZE;
+		file.take(remainder as u64).read_to_end(&mut data)?;
+	}
+	let archive_file = ArchiveFile::parse(&data)?;
+	Ok(archive_file.header)
+}
+
+pub fn encode_archive(symbol: &str, parser_version: &str, archive: &RawOhlcArchive, compress: bool) -> Result<Vec<u8>> {
+	let payload = rkyv::to_bytes::<_, 1024>(archive)?;
+	let header = ArchiveHeader {
+		parser_version: parser_version.to_string(),
+		symbol: symbol.to_string(),
+		intraday_time_frame: archive.get_intraday_time_frame(),
+		daily_records: archive.daily.len() as u64,
+		intraday_records: archive.get_intraday_record_count() as u64,
+		compressed: compress,
+		checksum: get_hash(&payload)
+	};
+	let header_bytes = rkyv::to_bytes::<_, 256>(&header)?;
+	let header_size = header_bytes.len();
+	let payload_offset = get_payload_offset(header_size);
+	let mut output = Vec::with_capacity(payload_offset + payload.len());
+	output.extend_from_slice(MAGIC);
+	output.extend_from_slice(&ARCHIVE_FORMAT_VERSION.to_le_bytes());
+	output.extend_from_slice(&[0; 2]);
+	output.extend_from_slice(&(header_size as u32).to_le_bytes());
+	output.extend_from_slice(&header_bytes);
+	output.resize(payload_offset, 0);
+	if compress {
+		zstd::stream::copy_encode(payload.as_slice(), &mut output, 1)?;
+	} else {
+		output.extend_from_slice(&payload);
+	}
+	Ok(output)
+}
+
+fn get_payload_offset(header_size: usize) -> usize {
+	(PREFIX_SIZE + header_size).next_multiple_of(PAYLOAD_ALIGNMENT)
+}
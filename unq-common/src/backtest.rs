@@ -1,21 +1,28 @@
-use std::{collections::{BTreeSet, HashMap, VecDeque}};
+use std::{collections::{BTreeMap, BTreeSet, HashMap, VecDeque}};
 use std::cell::RefCell;
 use std::cmp::Ordering;
+use std::ops::Bound;
 use std::rc::Rc;
 use std::sync::Arc;
-use chrono::NaiveDateTime;
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use configparser::ini::Ini;
 use lazy_static::lazy_static;
 use anyhow::{Context, Result, anyhow, bail};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use strum_macros::Display;
 use stopwatch::Stopwatch;
-use crate::{globex::parse_globex_code, manager::{Asset, AssetManager, AssetType}};
+use crate::{get_ini_optional, get_ini_value, globex::parse_globex_code, manager::{Asset, AssetManager, AssetType}};
+use crate::benchmark::{Benchmark, BenchmarkResult};
 use crate::globex::GlobexCode;
+use crate::manifest::BacktestManifest;
 use crate::manager::CsvTimeSeries;
 use crate::OhlcArchive;
 use crate::ohlc::{OhlcRecord, TimeFrame};
-use crate::stats::{mean, standard_deviation_mean};
-use crate::strategy::StrategyParameters;
+use crate::resample::{merge_records, starts_session, Alignment, ResamplePeriod, Resampler};
+use crate::stats::{annualize_ratio, covariance, get_periodic_rate, kurtosis, mean, quantile, skewness, sortino_ratio, sharpe_ratio, standard_deviation_mean};
+use crate::export::Table;
+use crate::strategy::{StrategyParameter, StrategyParameters};
 use crate::web::WebF64;
 
 const FOREX_USD: &str = "USD";
@@ -23,7 +30,15 @@ const FOREX_EUR: &str = "EUR";
 const FOREX_GBP: &str = "GBP";
 const FOREX_JPY: &str = "JPY";
 
-const TRADING_DAYS_PER_YEAR: f64 = 252.0;
+const MINUTES_PER_DAY: u16 = 1440;
+
+pub const TRADING_DAYS_PER_YEAR: f64 = 252.0;
+// Daily returns are evaluated at the 95% confidence level for VaR/CVaR
+const VALUE_AT_RISK_LEVEL: f64 = 0.05;
+// Quantile function of the standard normal distribution for the VaR level above
+const VALUE_AT_RISK_Z_SCORE: f64 = -1.6448536269514722;
+// Number of daily returns in each window of the rolling Sharpe ratio, roughly half a year
+const ROLLING_SHARPE_WINDOW: usize = 126;
 
 lazy_static! {
 	static ref FOREX_MAP: HashMap<String, String> = {
@@ -45,7 +60,7 @@ pub enum PositionSide {
 	Short
 }
 
-#[derive(Clone, PartialEq, Serialize)]
+#[derive(Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub enum EventType {
 	OpenPosition,
@@ -57,6 +72,41 @@ pub enum EventType {
 	Error
 }
 
+/*
+Lifecycle events that occurred during a step of the backtest.
+The backtest queues them and the runner passes them on to the hooks of the strategy after each step.
+*/
+#[derive(Clone, Debug)]
+pub enum StrategyEvent {
+	DayStart,
+	SessionStart,
+	Fill(Fill),
+	Rollover(Rollover),
+	MarginCall
+}
+
+// Contracts bought or sold, including those of rollovers, margin calls and the liquidation at the end of the backtest
+#[derive(Clone, Debug)]
+pub struct Fill {
+	pub position_id: u32,
+	pub symbol: String,
+	pub side: PositionSide,
+	pub count: u32,
+	pub price: f64,
+	// True if the fill opened a position, false if it closed (part of) one
+	pub opening: bool
+}
+
+#[derive(Clone, Debug)]
+pub struct Rollover {
+	// The position in the expiring contract, which has been closed
+	pub previous_position_id: u32,
+	pub previous_symbol: String,
+	// The position in the new contract
+	pub position_id: u32,
+	pub symbol: String
+}
+
 #[derive(Clone)]
 pub struct Backtest {
 	// Point in time when the backtest starts (from <= t < to)
@@ -78,6 +128,10 @@ pub struct Backtest {
 	time_frame: TimeFrame,
 	// Fixed points in time the simulation will iterate over
 	time_sequence: VecDeque<NaiveDateTime>,
+	// Periods of weekly, monthly and N-day time frames, maps the last trading day of each period to its first one
+	periods: BTreeMap<NaiveDateTime, NaiveDateTime>,
+	// Trading sessions, maps the last step of each session to its first one
+	sessions: BTreeMap<NaiveDateTime, NaiveDateTime>,
 	// Sequential ID used to uniquely identify positions
 	next_position_id: u32,
 	// Text-based event log, in ascending order
@@ -94,15 +148,37 @@ pub struct Backtest {
 	fees: f64,
 	// Statistics for profits and losses and bars spent in trades specific to long/short side
 	profit_duration_stats: Vec<ProfitDurationStats>,
+	// Closed positions, in ascending order
+	trades: Vec<Trade>,
+	// Total value of all contracts bought and sold, in USD, used to calculate turnover
+	traded_notional: f64,
+	// Optional reference asset for beta/alpha and benchmark comparisons
+	benchmark: Option<Arc<Benchmark>>,
 	// Interest rate time series for calculating interest
 	fed_funds_rate: Arc<CsvTimeSeries>,
 	// Total interest accumulated
 	interest: f64,
 	// Indicates whether the backtest is still running (terminated = false) or not (terminated = true)
-	terminated: bool
+	terminated: bool,
+	// Lifecycle events that haven't been passed on to the strategy yet
+	strategy_events: Vec<StrategyEvent>,
+	// Trading day of the most recent step, i.e. the date on which its session closes, None before the first step
+	trading_date: Option<NaiveDate>,
+	// Optional listener that is notified of events and equity curve updates while the backtest is running
+	observer: Option<Arc<dyn BacktestObserver>>
 }
 
-#[derive(Clone)]
+/*
+Receives updates from a running backtest, e.g. to stream them to clients before the result is available.
+Observers are shared between backtests running in parallel and must therefore be thread-safe.
+*/
+pub trait BacktestObserver: Send + Sync {
+	fn on_event(&self, event: &BacktestEvent);
+	fn on_daily_stats(&self, daily_stats: &DailyStats);
+}
+
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct BacktestConfiguration {
 	// Initial cash the backtest starts with, in USD
 	pub starting_cash: f64,
@@ -126,7 +202,46 @@ pub struct BacktestConfiguration {
 	// If enabled, cash in the margin account will gain interest based on a fixed formula
 	pub enable_interest: bool,
 	// Enables/disables the event log,
-	pub enable_logging: bool
+	pub enable_logging: bool,
+	// Optional symbol of an asset or name of a time series the strategy is compared against
+	// This is also used to calculate beta and alpha
+	pub benchmark: Option<String>,
+	// Annual minimum acceptable return used as the target of the Sortino ratio, e.g. 0.02 for 2%
+	// If it isn't set, the T-bill rate of each day serves as the target instead
+	pub minimum_acceptable_return: Option<f64>
+}
+
+impl BacktestConfiguration {
+	// Reads the [backtest] section of a configuration file such as unq-server.ini
+	pub fn from_ini(config: &Ini) -> Result<BacktestConfiguration> {
+		let section = "backtest";
+		let configuration = BacktestConfiguration {
+			starting_cash: get_ini_value(config, section, "starting_cash")?,
+			forex_order_fee: get_ini_value(config, section, "forex_order_fee")?,
+			forex_spread: get_ini_value(config, section, "forex_spread")?,
+			futures_spread_ticks: get_ini_value(config, section, "futures_spread_ticks")?,
+			initial_margin_ratio: get_ini_value(config, section, "initial_margin_ratio")?,
+			overnight_margin_ratio: get_ini_value(config, section, "overnight_margin_ratio")?,
+			ruin_ratio: get_ini_value(config, section, "ruin_ratio")?,
+			enable_interest: get_ini_value(config, section, "enable_interest")?,
+			enable_logging: true,
+			benchmark: config.get(section, "benchmark"),
+			minimum_acceptable_return: get_ini_optional(config, section, "minimum_acceptable_return")?
+		};
+		Ok(configuration)
+	}
+
+	// Applies per-request settings that take precedence over the configuration file
+	pub fn with_overrides(&self, benchmark: &Option<String>, minimum_acceptable_return: Option<f64>) -> BacktestConfiguration {
+		let mut configuration = self.clone();
+		if benchmark.is_some() {
+			configuration.benchmark = benchmark.clone();
+		}
+		if minimum_acceptable_return.is_some() {
+			configuration.minimum_acceptable_return = minimum_acceptable_return;
+		}
+		configuration
+	}
 }
 
 #[derive(Clone)]
@@ -165,7 +280,7 @@ pub struct SimplePosition {
 	pub side: PositionSide
 }
 
-#[derive(Clone, Serialize)]
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct BacktestEvent {
 	time: NaiveDateTime,
@@ -173,7 +288,7 @@ pub struct BacktestEvent {
 	message: String
 }
 
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct BacktestResult {
 	starting_cash: WebF64,
@@ -181,6 +296,7 @@ pub struct BacktestResult {
 	events: Vec<BacktestEvent>,
 	equity_curve_daily: Vec<DailyStats>,
 	equity_curve_trades: Vec<EquityCurveData>,
+	trades: Vec<Trade>,
 	fees: WebF64,
 	fees_percent: WebF64,
 	interest: WebF64,
@@ -192,13 +308,37 @@ pub struct BacktestResult {
 	sharpe_ratio: WebF64,
 	sortino_ratio: WebF64,
 	calmar_ratio: WebF64,
+	// Previous calculations based on the average T-bill rate of the from/to range and the standard deviation of negative returns
+	// These are only kept around for comparison
+	legacy_sharpe_ratio: WebF64,
+	legacy_sortino_ratio: WebF64,
 	max_drawdown: WebF64,
+	annualized_volatility: WebF64,
+	skewness: WebF64,
+	kurtosis: WebF64,
+	value_at_risk: WebF64,
+	conditional_value_at_risk: WebF64,
+	parametric_value_at_risk: WebF64,
+	parametric_conditional_value_at_risk: WebF64,
+	ulcer_index: WebF64,
+	// Longest period spent below a previous peak of the equity curve, in days
+	longest_drawdown: i64,
+	// Days it took to recover from the maximum drawdown, None if it never recovered
+	time_to_recovery: Option<i64>,
+	monthly_returns: Vec<PeriodReturn>,
+	annual_returns: Vec<PeriodReturn>,
+	rolling_sharpe_ratio: Vec<DateValue>,
+	exposure: WebF64,
+	turnover: WebF64,
+	beta: WebF64,
+	alpha: WebF64,
+	benchmark: Option<BenchmarkResult>,
 	all_trades: TradeResults,
 	long_trades: TradeResults,
 	short_trades: TradeResults
 }
 
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SimplifiedBacktestResult {
 	parameters: StrategyParameters,
@@ -222,9 +362,12 @@ of BacktestResult objects, it only returns the one with the highest Sortino rati
 to a simplified representation that doesn't require as much memory. This simplified representation is used
 to render a table of parameters and their performance in the web UI.
 */
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct BacktestSeries {
+	// Only set if the series has been stored by the server
+	id: Option<u64>,
+	manifest: BacktestManifest,
 	best_parameters: StrategyParameters,
 	best_result: BacktestResult,
 	results: Vec<SimplifiedBacktestResult>,
@@ -232,7 +375,7 @@ pub struct BacktestSeries {
 	stopwatch: WebF64
 }
 
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct TradeResults {
 	trades: u32,
@@ -243,7 +386,7 @@ pub struct TradeResults {
 	bars_in_trade: WebF64
 }
 
-#[derive(Clone, Serialize)]
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct EquityCurveData {
 	account_value: WebF64,
@@ -251,7 +394,31 @@ pub struct EquityCurveData {
 	drawdown_percent: WebF64
 }
 
-#[derive(Clone, Serialize)]
+/*
+A position or part of a position that has been closed.
+Rollovers of futures contracts are recorded as separate trades.
+*/
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Trade {
+	position_id: u32,
+	// Full name of the contract, e.g. "ESU24"
+	symbol: String,
+	// "long" or "short"
+	side: String,
+	count: u32,
+	entry_time: NaiveDateTime,
+	exit_time: NaiveDateTime,
+	entry_price: WebF64,
+	exit_price: WebF64,
+	// In the currency of the asset, excluding fees
+	profit: WebF64,
+	// Fees paid for closing the position, in USD
+	fees: WebF64,
+	bars_in_trade: u32
+}
+
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct DailyStats {
 	date: NaiveDateTime,
@@ -260,6 +427,22 @@ pub struct DailyStats {
 	overnight_margin: WebF64
 }
 
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PeriodReturn {
+	year: i32,
+	// None for annual returns
+	month: Option<u32>,
+	total_return: WebF64
+}
+
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DateValue {
+	date: NaiveDateTime,
+	value: WebF64
+}
+
 #[derive(Clone)]
 struct ProfitDurationStats {
 	side: PositionSide,
@@ -282,7 +465,9 @@ impl Backtest {
 		if from >= to {
 			bail!("Invalid from/to parameters");
 		}
-		let time_sequence = Self::get_time_sequence(&from, &to, &time_frame, asset_manager.clone())?;
+		let (time_sequence, source_time_frame) = Self::get_time_sequence(&from, &to, &time_frame, asset_manager.clone())?;
+		let periods = Self::get_periods(&time_sequence, &time_frame)?;
+		let sessions = Self::get_sessions(&time_sequence, source_time_frame);
 		let equity_curve_data = EquityCurveData {
 			account_value: WebF64::new(configuration.starting_cash),
 			drawdown: WebF64::new(0.0),
@@ -297,6 +482,10 @@ impl Backtest {
 		let equity_curve_daily = vec![equity_curve_data_daily];
 		let equity_curve_trades = vec![equity_curve_data];
 		let fed_funds_rate = asset_manager.get_time_series("FEDFUNDS")?;
+		let benchmark = match &configuration.benchmark {
+			Some(symbol) => Some(Arc::new(Benchmark::new(symbol, &asset_manager)?)),
+			None => None
+		};
 		let backtest = Backtest {
 			from,
 			to,
@@ -307,6 +496,8 @@ impl Backtest {
 			now: from,
 			time_frame,
 			time_sequence,
+			periods,
+			sessions,
 			next_position_id: 1,
 			events: Vec::new(),
 			equity_curve_daily,
@@ -315,9 +506,15 @@ impl Backtest {
 			max_drawdown: 0.0,
 			fees: 0.0,
 			profit_duration_stats: Vec::new(),
+			trades: Vec::new(),
+			traded_notional: 0.0,
+			benchmark,
 			fed_funds_rate,
 			interest: 0.0,
-			terminated: false
+			terminated: false,
+			strategy_events: Vec::new(),
+			trading_date: None,
+			observer: None
 		};
 		Ok(Rc::new(RefCell::new(backtest)))
 	}
@@ -366,7 +563,26 @@ impl Backtest {
 		let annual_average_return = total_return / years;
 		let compound_annual_growth_rate = return_ratio.powf(1.0 / years) - 1.0;
 		let equity_curve_daily = self.equity_curve_daily.clone();
-		let (sharpe_ratio, sortino_ratio, calmar_ratio) = self.get_ratios(self.max_drawdown, &equity_curve_daily)?;
+		let (sharpe_ratio, sortino_ratio) = self.get_ratios(&equity_curve_daily)?;
+		let (legacy_sharpe_ratio, legacy_sortino_ratio, calmar_ratio) = self.get_legacy_ratios(self.max_drawdown, &equity_curve_daily)?;
+		let daily_returns = Self::get_daily_returns(&equity_curve_daily);
+		let annualized_volatility = TRADING_DAYS_PER_YEAR.sqrt() * Self::standard_deviation_nan(&daily_returns, Self::mean_nan(&daily_returns));
+		let skewness = Self::error_to_nan(skewness(daily_returns.iter()));
+		let kurtosis = Self::error_to_nan(kurtosis(daily_returns.iter()));
+		let (value_at_risk, conditional_value_at_risk) = Self::get_value_at_risk(&daily_returns);
+		let (parametric_value_at_risk, parametric_conditional_value_at_risk) = Self::get_parametric_value_at_risk(&daily_returns);
+		let ulcer_index = Self::get_ulcer_index(&equity_curve_daily);
+		let (longest_drawdown, time_to_recovery) = Self::get_drawdown_durations(&equity_curve_daily);
+		let monthly_returns = Self::get_period_returns(&equity_curve_daily, true);
+		let annual_returns = Self::get_period_returns(&equity_curve_daily, false);
+		let rolling_sharpe_ratio = Self::get_rolling_sharpe_ratio(&equity_curve_daily);
+		let exposure = Self::get_exposure(&equity_curve_daily);
+		let turnover = self.get_turnover(&equity_curve_daily, years);
+		let (beta, alpha) = self.get_beta_alpha(&equity_curve_daily)?;
+		let equity_curve_values = Self::get_equity_curve_values(&equity_curve_daily);
+		let benchmark = self.benchmark
+			.as_ref()
+			.map(|benchmark| benchmark.get_result(&equity_curve_values));
 		let fees_percent = if profit >= 0.0 {
 			self.fees / profit
 		} else {
@@ -381,6 +597,7 @@ impl Backtest {
 			events,
 			equity_curve_daily,
 			equity_curve_trades: self.equity_curve_trades.clone(),
+			trades: self.trades.clone(),
 			fees: WebF64::new(self.fees),
 			fees_percent: WebF64::new(fees_percent),
 			interest: WebF64::new(self.interest),
@@ -392,7 +609,27 @@ impl Backtest {
 			sharpe_ratio: WebF64::new(sharpe_ratio),
 			sortino_ratio: WebF64::new(sortino_ratio),
 			calmar_ratio: WebF64::new(calmar_ratio),
+			legacy_sharpe_ratio: WebF64::new(legacy_sharpe_ratio),
+			legacy_sortino_ratio: WebF64::new(legacy_sortino_ratio),
 			max_drawdown: WebF64::precise(self.max_drawdown),
+			annualized_volatility: WebF64::precise(annualized_volatility),
+			skewness: WebF64::new(skewness),
+			kurtosis: WebF64::new(kurtosis),
+			value_at_risk: WebF64::precise(value_at_risk),
+			conditional_value_at_risk: WebF64::precise(conditional_value_at_risk),
+			parametric_value_at_risk: WebF64::precise(parametric_value_at_risk),
+			parametric_conditional_value_at_risk: WebF64::precise(parametric_conditional_value_at_risk),
+			ulcer_index: WebF64::precise(ulcer_index),
+			longest_drawdown,
+			time_to_recovery,
+			monthly_returns,
+			annual_returns,
+			rolling_sharpe_ratio,
+			exposure: WebF64::precise(exposure),
+			turnover: WebF64::new(turnover),
+			beta: WebF64::new(beta),
+			alpha: WebF64::precise(alpha),
+			benchmark,
 			all_trades,
 			long_trades,
 			short_trades
@@ -443,6 +680,10 @@ impl Backtest {
 
 	pub fn get_records(&self, symbol: &String, bars: usize) -> Result<Vec<OhlcRecord>> {
 		let archive = self.get_symbol_archive(symbol)?;
+		if let Some(period) = self.time_frame.get_period() {
+			let records = self.get_period_records(&archive, &period, bars);
+			return Ok(records);
+		}
 		let source = archive.get_data(&self.time_frame);
 		/*
 		Use .. instead of ..= because the primary use case of this function is filling up buffers with data
@@ -460,7 +701,38 @@ impl Backtest {
 		Ok(records)
 	}
 
+	/*
+	The entire history of a symbol resampled to another period with clock alignment, e.g. daily records for an intraday strategy.
+	This includes records that are in the future from the perspective of the backtest, callers must only use completed records.
+	*/
+	pub fn get_resampled_records(&self, symbol: &String, period: &ResamplePeriod) -> Result<Vec<OhlcRecord>> {
+		let archive = self.get_symbol_archive(symbol)?;
+		let (source_time_frame, source) = match period.get_minutes() {
+			Some(minutes) => {
+				let time_frame = u16::try_from(minutes)
+					.ok()
+					.filter(|x| *x < MINUTES_PER_DAY)
+					.with_context(|| anyhow!("Intraday periods must be shorter than a day"))?;
+				let Some(source) = archive.get_intraday_source(time_frame, &self.from) else {
+					bail!("Unable to find any intraday records of {symbol} that can be resampled to {minutes} minutes");
+				};
+				source
+			},
+			None => (MINUTES_PER_DAY, &archive.daily)
+		};
+		let records = Resampler::new(*period, Alignment::Clock, source_time_frame)
+			.resample(source.get_adjusted_fallback().values());
+		Ok(records)
+	}
+
 	pub fn get_close_values(&self, symbol: &String, bars: usize) -> Result<Vec<f64>> {
+		if self.time_frame.get_period().is_some() {
+			let records = self.get_records(symbol, bars)?
+				.iter()
+				.map(|record| record.close)
+				.collect::<Vec<f64>>();
+			return Ok(records);
+		}
 		let archive = self.get_symbol_archive(symbol)?;
 		let source = archive.get_data(&self.time_frame);
 		let records = source
@@ -474,11 +746,50 @@ impl Backtest {
 	}
 
 	pub fn get_current_record(&self, symbol: &String) -> Result<OhlcRecord> {
-		self.get_record(symbol, self.now, false)
+		let record = self.get_record(symbol, self.now, false)?;
+		self.get_current_period_record(symbol, record)
 	}
 
 	pub fn most_recent_record(&self, symbol: &String) -> Result<OhlcRecord> {
-		self.get_record(symbol, self.now, true)
+		let record = self.latest_record(symbol)?;
+		self.get_current_period_record(symbol, record)
+	}
+
+	/*
+	Indicates whether the strategy is supposed to be executed at the current point in time.
+	Weekly, monthly and N-day time frames only rebalance on the last trading day of each period.
+	There is no point in rebalancing on the last day of the backtest since all positions are closed right afterwards.
+	*/
+	pub fn is_rebalancing_time(&self) -> bool {
+		if self.time_frame.get_period().is_none() {
+			return true;
+		}
+		!self.time_sequence.is_empty() && self.periods.contains_key(&self.now)
+	}
+
+	// Removes the lifecycle events that occurred since the previous call so that they can be passed on to the strategy
+	pub fn take_strategy_events(&mut self) -> Vec<StrategyEvent> {
+		std::mem::take(&mut self.strategy_events)
+	}
+
+	// Indicates whether the current step is the last one of its session, which includes the final step of the backtest
+	pub fn is_session_close(&self) -> bool {
+		self.trading_date.is_some() && self.sessions.contains_key(&self.now)
+	}
+
+	/*
+	Indicates whether the current step is the last one of its month.
+	Sessions belong to the month in which they close so that Globex sessions starting in the evening aren't split up.
+	This is not the case for the final step of the backtest since it is unknown whether there are any more sessions.
+	*/
+	pub fn is_month_end(&self) -> bool {
+		if !self.is_session_close() {
+			return false;
+		}
+		self.sessions
+			.range((Bound::Excluded(self.now), Bound::Unbounded))
+			.next()
+			.is_some_and(|(next, _)| next.year() != self.now.year() || next.month() != self.now.month())
 	}
 
 	pub fn is_available(&self, symbol: &String) -> Result<bool> {
@@ -499,10 +810,17 @@ impl Backtest {
 				event_type,
 				message
 			};
+			if let Some(observer) = &self.observer {
+				observer.on_event(&event);
+			}
 			self.events.push(event);
 		}
 	}
 
+	pub fn set_observer(&mut self, observer: Arc<dyn BacktestObserver>) {
+		self.observer = Some(observer);
+	}
+
 	pub fn disable_logging(&mut self) {
 		self.configuration.enable_logging = false;
 	}
@@ -539,6 +857,18 @@ impl Backtest {
 			self.margin_call_check()?;
 			self.gain_interest()?;
 			self.now = now;
+			// Globex sessions start on the evening before the trading day they belong to
+			let (trading_date, is_session_start) = match self.sessions.range(now..).next() {
+				Some((close, start)) => (close.date(), *start == now),
+				None => (now.date(), false)
+			};
+			if self.trading_date != Some(trading_date) {
+				self.trading_date = Some(trading_date);
+				self.strategy_events.push(StrategyEvent::DayStart);
+			}
+			if is_session_start {
+				self.strategy_events.push(StrategyEvent::SessionStart);
+			}
 			self.update_position_bars();
 			self.rollover_contracts()?;
 			self.update_daily_stats()?;
@@ -591,6 +921,7 @@ impl Backtest {
 			self.cash -= cost;
 			self.fees += fees;
 			let ask = current_record.close + (self.configuration.futures_spread_ticks as f64) * asset.tick_size;
+			self.add_traded_notional(&asset, ask, count)?;
 			let position = Position {
 				id: self.next_position_id,
 				symbol: current_record.symbol.clone(),
@@ -606,6 +937,7 @@ impl Backtest {
 			};
 			self.next_position_id += 1;
 			self.positions.push(position.clone());
+			self.add_fill(&position, count, ask, true);
 			if enable_logging {
 				let message = format!("Opened {side} position: {count} x {symbol} @ {ask:.2} (ID {})", position.id);
 				self.log_event(EventType::OpenPosition, message);
@@ -633,6 +965,7 @@ impl Backtest {
 		if asset.asset_type == AssetType::Futures {
 			let (value, profit, position_bid, fees) = self.get_position_value(&position, count, enable_fees)?;
 			bid = position_bid;
+			self.add_traded_notional(asset, bid, count)?;
 			self.cash += value;
 			self.fees += fees;
 			let profit_duration_stats = ProfitDurationStats {
@@ -641,6 +974,21 @@ impl Backtest {
 				bars_in_trade: position.bars_in_trade
 			};
 			self.profit_duration_stats.push(profit_duration_stats);
+			let trade = Trade {
+				position_id,
+				symbol: position.symbol.clone(),
+				side: position.side.to_string(),
+				count,
+				entry_time: position.time_opened,
+				exit_time: self.now,
+				entry_price: WebF64::new(position.price),
+				exit_price: WebF64::new(bid),
+				profit: WebF64::new(profit),
+				fees: WebF64::new(fees),
+				bars_in_trade: position.bars_in_trade
+			};
+			self.trades.push(trade);
+			self.add_fill(&position, count, bid, false);
 			let new_count = position.count - count;
 			if new_count == 0 {
 				// The entire position has been sold, remove it
@@ -668,6 +1016,13 @@ impl Backtest {
 		Ok(())
 	}
 
+	fn add_traded_notional(&mut self, asset: &Asset, price: f64, count: u32) -> Result<()> {
+		let notional = (count as f64) * price / asset.tick_size * asset.tick_value;
+		let (notional_usd, _) = self.convert_currency(&asset.currency, &FOREX_USD.to_string(), notional)?;
+		self.traded_notional += notional_usd.abs();
+		Ok(())
+	}
+
 	fn update_equity_curve(&mut self) -> EquityCurveData {
 		let account_value = self.get_account_value_internal(true);
 		if account_value > self.max_account_value {
@@ -756,6 +1111,84 @@ impl Backtest {
 		self.get_record(symbol, self.now, false)
 	}
 
+	fn latest_record(&self, symbol: &String) -> Result<OhlcRecord> {
+		self.get_record(symbol, self.now, true)
+	}
+
+	// First trading day of the period containing the current point in time
+	fn get_period_start(&self) -> NaiveDateTime {
+		self.periods
+			.range(self.now..)
+			.next()
+			.map(|(_, start)| *start)
+			.filter(|start| *start <= self.now)
+			.unwrap_or(self.now)
+	}
+
+	/*
+	Completed records of the rebalancing period prior to the current one, in descending order like "get_records".
+	N-day records are built backwards from the start of the current period so that they line up with rebalancing.
+	*/
+	fn get_period_records(&self, archive: &OhlcArchive, period: &ResamplePeriod, bars: usize) -> Vec<OhlcRecord> {
+		let period_start = self.get_period_start();
+		let source = archive.daily.get_adjusted_fallback();
+		match period.get_calendar_start(period_start) {
+			Some(calendar_start) => {
+				Resampler::new(*period, Alignment::Clock, MINUTES_PER_DAY)
+					.resample(source.range(..calendar_start).map(|(_, record)| record))
+					.into_iter()
+					.rev()
+					.take(bars)
+					.collect()
+			},
+			None => {
+				let days = period.count as usize;
+				let records = source
+					.range(..period_start)
+					.rev()
+					.take(bars * days)
+					.map(|(_, record)| record)
+					.collect::<Vec<&OhlcRecord>>();
+				records
+					.chunks(days)
+					.map(|chunk| {
+						let chunk = chunk
+							.iter()
+							.rev()
+							.cloned()
+							.collect::<Vec<&OhlcRecord>>();
+						merge_records(chunk[0].time, &chunk)
+					})
+					.collect()
+			}
+		}
+	}
+
+	/*
+	Merges the daily records of the current rebalancing period into a single record for weekly, monthly and N-day time frames.
+	Records of specific contracts are passed through unchanged since they are only used for pricing.
+	*/
+	fn get_current_period_record(&self, symbol: &String, record: OhlcRecord) -> Result<OhlcRecord> {
+		let Some(period) = self.time_frame.get_period() else {
+			return Ok(record);
+		};
+		if parse_globex_code(symbol).is_some() {
+			return Ok(record);
+		}
+		let archive = self.get_symbol_archive(symbol)?;
+		let period_start = self.get_period_start();
+		let records = archive.daily
+			.get_adjusted_fallback()
+			.range(period_start..=self.now)
+			.map(|(_, record)| record)
+			.collect::<Vec<&OhlcRecord>>();
+		let Some(first) = records.first() else {
+			return Ok(record);
+		};
+		let time = period.get_calendar_start(period_start).unwrap_or(first.time);
+		Ok(merge_records(time, &records))
+	}
+
 	fn get_record(&self, symbol: &String, time: NaiveDateTime, most_recent: bool) -> Result<OhlcRecord> {
 		let record;
 		let map_error = || anyhow!("Unable to find a record for {symbol} at {}", self.now);
@@ -810,7 +1243,8 @@ impl Backtest {
 		Ok(record)
 	}
 
-	fn get_time_sequence(from: &NaiveDateTime, to: &NaiveDateTime, time_frame: &TimeFrame, asset_manager: Arc<AssetManager>) -> Result<VecDeque<NaiveDateTime>> {
+	// Also returns the time frame of the reference records in minutes, which is required to determine the sessions
+	fn get_time_sequence(from: &NaiveDateTime, to: &NaiveDateTime, time_frame: &TimeFrame, asset_manager: Arc<AssetManager>) -> Result<(VecDeque<NaiveDateTime>, u16)> {
 		// Use S&P 500 futures as a timestamp reference for the core loop
 		// This only makes sense because the backtest currently targets futures
 		let time_reference_symbol = "ES".to_string();
@@ -828,7 +1262,65 @@ impl Backtest {
 		let time_sequence = BTreeSet::from_iter(time_keys_in_range)
 			.into_iter()
 			.collect();
-		Ok(time_sequence)
+		let source_time_frame = match time_frame {
+			TimeFrame::Intraday => time_reference.intraday_time_frame,
+			_ => MINUTES_PER_DAY
+		};
+		Ok((time_sequence, source_time_frame))
+	}
+
+	// Groups the steps into sessions the same way as the resampler, with daily data every trading day is a session of its own
+	fn get_sessions(time_sequence: &VecDeque<NaiveDateTime>, source_time_frame: u16) -> BTreeMap<NaiveDateTime, NaiveDateTime> {
+		let mut sessions = BTreeMap::new();
+		let mut start: Option<NaiveDateTime> = None;
+		let mut previous: Option<NaiveDateTime> = None;
+		for time in time_sequence {
+			let is_new_session = previous.is_none_or(|previous| starts_session(previous, *time, source_time_frame));
+			if is_new_session {
+				if let (Some(start), Some(previous)) = (start, previous) {
+					sessions.insert(previous, start);
+				}
+				start = Some(*time);
+			}
+			previous = Some(*time);
+		}
+		if let (Some(start), Some(previous)) = (start, previous) {
+			sessions.insert(previous, start);
+		}
+		sessions
+	}
+
+	// Groups the trading days of weekly, monthly and N-day time frames into periods
+	fn get_periods(time_sequence: &VecDeque<NaiveDateTime>, time_frame: &TimeFrame) -> Result<BTreeMap<NaiveDateTime, NaiveDateTime>> {
+		let mut periods = BTreeMap::new();
+		let Some(period) = time_frame.get_period() else {
+			return Ok(periods);
+		};
+		if period.count == 0 {
+			bail!("Invalid number of days in time frame");
+		}
+		let mut start: Option<NaiveDateTime> = None;
+		let mut previous: Option<NaiveDateTime> = None;
+		for (index, time) in time_sequence.iter().enumerate() {
+			let is_new_period = match previous {
+				Some(previous) => match period.get_calendar_start(*time) {
+					Some(calendar_start) => period.get_calendar_start(previous) != Some(calendar_start),
+					None => index.is_multiple_of(period.count as usize)
+				},
+				None => true
+			};
+			if is_new_period {
+				if let (Some(start), Some(previous)) = (start, previous) {
+					periods.insert(previous, start);
+				}
+				start = Some(*time);
+			}
+			previous = Some(*time);
+		}
+		if let (Some(start), Some(previous)) = (start, previous) {
+			periods.insert(previous, start);
+		}
+		Ok(periods)
 	}
 
 	fn get_account_value_internal(&self, enable_fees: bool) -> f64 {
@@ -847,9 +1339,9 @@ impl Backtest {
 		/*
 		This is a questionable workaround for holidays on which lots of CME futures are not being traded,
 		while ES is still available and causes the core event loop to iterate over those dates.
-		If we used current_record rather than most_recent_record, the equity curve would erroneously show a massive drawdown.
+		If we used current_record rather than latest_record, the equity curve would erroneously show a massive drawdown.
 		*/
-		let record = self.most_recent_record(&position.symbol)?;
+		let record = self.latest_record(&position.symbol)?;
 		let margin = (position.count as f64) * position.margin;
 		let bid = record.close;
 		let ticks = (count as f64) * (bid - position.price) / asset.tick_size;
@@ -898,6 +1390,7 @@ impl Backtest {
 				if log_margin_call {
 					let message = format!("The overnight margin of ${overnight_margin:.2} exceeds the account value of ${account_value:.2}, closing positions");
 					self.log_event(EventType::MarginCall, message);
+					self.strategy_events.push(StrategyEvent::MarginCall);
 				}
 				let close_result = self.close_position(position_id, position_count);
 				if close_result.is_err() {
@@ -955,6 +1448,13 @@ impl Backtest {
 							let new_position = self.get_position(position_id)?;
 							let message = format!("Rolled over {} position: {} x {} @ {:.2} (ID {})", new_position.side, new_position.count, new_position.symbol, new_position.price, new_position.id);
 							self.log_event(EventType::Rollover, message);
+							let rollover = Rollover {
+								previous_position_id: position.id,
+								previous_symbol: position.symbol.clone(),
+								position_id: new_position.id,
+								symbol: new_position.symbol
+							};
+							self.strategy_events.push(StrategyEvent::Rollover(rollover));
 						},
 						Err(error) => {
 							// The automatic rollover failed, possibly due to a lack of funds
@@ -969,6 +1469,18 @@ impl Backtest {
 		Ok(())
 	}
 
+	fn add_fill(&mut self, position: &Position, count: u32, price: f64, opening: bool) {
+		let fill = Fill {
+			position_id: position.id,
+			symbol: position.symbol.clone(),
+			side: position.side.clone(),
+			count,
+			price,
+			opening
+		};
+		self.strategy_events.push(StrategyEvent::Fill(fill));
+	}
+
 	fn get_globex_code(symbol: &String) -> Result<GlobexCode> {
 		GlobexCode::new(symbol)
 			.with_context(|| anyhow!("Unable to parse Globex code {symbol}"))
@@ -992,6 +1504,9 @@ impl Backtest {
 				maintenance_margin: WebF64::new(maintenance_margin),
 				overnight_margin: WebF64::new(overnight_margin)
 			};
+			if let Some(observer) = &self.observer {
+				observer.on_daily_stats(&equity_curve_daily);
+			}
 			self.equity_curve_daily.push(equity_curve_daily);
 		}
 		Ok(())
@@ -1008,12 +1523,12 @@ impl Backtest {
 		Ok(())
 	}
 
-	fn mean_nan(samples: &Vec<f64>) -> f64 {
+	fn mean_nan(samples: &[f64]) -> f64 {
 		let mean = mean(samples.iter());
 		Self::error_to_nan(mean)
 	}
 
-	fn standard_deviation_nan(samples: &Vec<f64>, mean: f64) -> f64 {
+	fn standard_deviation_nan(samples: &[f64], mean: f64) -> f64 {
 		let standard_deviation = standard_deviation_mean(samples.iter(), mean);
 		Self::error_to_nan(standard_deviation)
 	}
@@ -1027,6 +1542,13 @@ impl Backtest {
 	}
 
 	fn get_daily_returns(equity_curve_daily: &Vec<DailyStats>) -> Vec<f64> {
+		Self::get_dated_daily_returns(equity_curve_daily)
+			.into_iter()
+			.map(|(_, daily_return)| daily_return)
+			.collect()
+	}
+
+	fn get_dated_daily_returns(equity_curve_daily: &[DailyStats]) -> Vec<(NaiveDateTime, f64)> {
 		equity_curve_daily
 			.windows(2)
 			.filter_map(|window| {
@@ -1036,7 +1558,7 @@ impl Backtest {
 					// Filter out the final pathological values of a failed run
 					if value1 > 0.0 && value2 > 0.0 {
 						let daily_return = value2 / value1 - 1.0;
-						Some(daily_return)
+						Some((daily2.date, daily_return))
 					} else {
 						None
 					}
@@ -1047,6 +1569,201 @@ impl Backtest {
 			.collect()
 	}
 
+	fn get_value_at_risk(daily_returns: &[f64]) -> (f64, f64) {
+		// Historical VaR/CVaR, expressed as positive loss ratios
+		let Ok(threshold) = quantile(daily_returns.iter(), VALUE_AT_RISK_LEVEL) else {
+			return (f64::NAN, f64::NAN);
+		};
+		let tail: Vec<f64> = daily_returns
+			.iter()
+			.filter(|x| **x <= threshold)
+			.cloned()
+			.collect();
+		let value_at_risk = - threshold;
+		let conditional_value_at_risk = - Self::mean_nan(&tail);
+		(value_at_risk, conditional_value_at_risk)
+	}
+
+	fn get_parametric_value_at_risk(daily_returns: &[f64]) -> (f64, f64) {
+		// Variance-covariance method, assumes normally distributed daily returns
+		let mean = Self::mean_nan(daily_returns);
+		let standard_deviation = Self::standard_deviation_nan(daily_returns, mean);
+		let density = (- 0.5 * VALUE_AT_RISK_Z_SCORE * VALUE_AT_RISK_Z_SCORE).exp() / (2.0 * std::f64::consts::PI).sqrt();
+		let value_at_risk = - (mean + VALUE_AT_RISK_Z_SCORE * standard_deviation);
+		let conditional_value_at_risk = - (mean - standard_deviation * density / VALUE_AT_RISK_LEVEL);
+		(value_at_risk, conditional_value_at_risk)
+	}
+
+	fn get_ulcer_index(equity_curve_daily: &[DailyStats]) -> f64 {
+		let squared_drawdowns: Vec<f64> = equity_curve_daily
+			.iter()
+			.map(|x| x.equity_curve.drawdown_percent.get().powi(2))
+			.collect();
+		Self::mean_nan(&squared_drawdowns).sqrt()
+	}
+
+	fn get_drawdown_durations(equity_curve_daily: &[DailyStats]) -> (i64, Option<i64>) {
+		let mut longest_drawdown = 0;
+		let mut peak_date: Option<NaiveDateTime> = None;
+		let mut trough: Option<(NaiveDateTime, f64)> = None;
+		let mut time_to_recovery = None;
+		for daily in equity_curve_daily {
+			let drawdown_percent = daily.equity_curve.drawdown_percent.get();
+			if drawdown_percent >= 0.0 {
+				// A new peak has been reached, which terminates the current drawdown
+				if let Some(peak) = peak_date {
+					longest_drawdown = longest_drawdown.max((daily.date - peak).num_days());
+				}
+				if let Some((trough_date, _)) = trough {
+					if time_to_recovery.is_none() {
+						time_to_recovery = Some((daily.date - trough_date).num_days());
+					}
+				}
+				peak_date = Some(daily.date);
+			} else if trough.is_none_or(|(_, x)| drawdown_percent < x) {
+				// New maximum drawdown, reset the recovery time
+				trough = Some((daily.date, drawdown_percent));
+				time_to_recovery = None;
+			}
+		}
+		// Account for a drawdown that lasted until the end of the backtest
+		if let (Some(peak), Some(last)) = (peak_date, equity_curve_daily.last()) {
+			longest_drawdown = longest_drawdown.max((last.date - peak).num_days());
+		}
+		(longest_drawdown, time_to_recovery)
+	}
+
+	fn get_period_returns(equity_curve_daily: &[DailyStats], monthly: bool) -> Vec<PeriodReturn> {
+		let mut period_returns = Vec::new();
+		let Some(first) = equity_curve_daily.first() else {
+			return period_returns;
+		};
+		let get_period_return = |year, month, value: f64, previous_value: f64| PeriodReturn {
+			year,
+			month,
+			total_return: WebF64::precise(value / previous_value - 1.0)
+		};
+		let mut previous_value = first.equity_curve.account_value.get();
+		let mut current_period: Option<(i32, Option<u32>, f64)> = None;
+		for daily in equity_curve_daily {
+			let date = daily.date.date();
+			let year = date.year();
+			let month = if monthly {
+				Some(date.month())
+			} else {
+				None
+			};
+			let value = daily.equity_curve.account_value.get();
+			if let Some((current_year, current_month, current_value)) = current_period {
+				if current_year != year || current_month != month {
+					// The previous period ended with the last value recorded in it
+					period_returns.push(get_period_return(current_year, current_month, current_value, previous_value));
+					previous_value = current_value;
+				}
+			}
+			current_period = Some((year, month, value));
+		}
+		if let Some((year, month, value)) = current_period {
+			period_returns.push(get_period_return(year, month, value, previous_value));
+		}
+		period_returns
+	}
+
+	fn get_rolling_sharpe_ratio(equity_curve_daily: &[DailyStats]) -> Vec<DateValue> {
+		// Annualized and without a risk-free rate, this is merely meant to visualize the stability of returns
+		let dated_returns = Self::get_dated_daily_returns(equity_curve_daily);
+		if dated_returns.len() < ROLLING_SHARPE_WINDOW {
+			return Vec::new();
+		}
+		dated_returns
+			.windows(ROLLING_SHARPE_WINDOW)
+			.filter_map(|window| {
+				let (date, _) = window.last()?;
+				let returns: Vec<f64> = window
+					.iter()
+					.map(|(_, daily_return)| *daily_return)
+					.collect();
+				let mean = Self::mean_nan(&returns);
+				let standard_deviation = Self::standard_deviation_nan(&returns, mean);
+				let sharpe_ratio = TRADING_DAYS_PER_YEAR.sqrt() * mean / standard_deviation;
+				let date_value = DateValue {
+					date: *date,
+					value: WebF64::new(sharpe_ratio)
+				};
+				Some(date_value)
+			})
+			.collect()
+	}
+
+	fn get_exposure(equity_curve_daily: &[DailyStats]) -> f64 {
+		// Ratio of days on which the account held any positions, skipping the initial value
+		let days = equity_curve_daily.len().saturating_sub(1);
+		if days == 0 {
+			return f64::NAN;
+		}
+		let days_in_market = equity_curve_daily
+			.iter()
+			.skip(1)
+			.filter(|x| x.maintenance_margin.get() > 0.0)
+			.count();
+		(days_in_market as f64) / (days as f64)
+	}
+
+	fn get_turnover(&self, equity_curve_daily: &[DailyStats], years: f64) -> f64 {
+		// Annual traded notional value relative to the average account value
+		let account_values: Vec<f64> = equity_curve_daily
+			.iter()
+			.map(|x| x.equity_curve.account_value.get())
+			.collect();
+		let mean_account_value = Self::mean_nan(&account_values);
+		self.traded_notional / mean_account_value / years
+	}
+
+	fn get_beta_alpha(&self, equity_curve_daily: &[DailyStats]) -> Result<(f64, f64)> {
+		let Some(benchmark) = &self.benchmark else {
+			return Ok((f64::NAN, f64::NAN));
+		};
+		let get_close = |date: &NaiveDateTime| benchmark.get_value(date);
+		let pairs: Vec<(f64, f64)> = equity_curve_daily
+			.windows(2)
+			.filter_map(|window| {
+				let [daily1, daily2] = window else {
+					return None;
+				};
+				let value1 = daily1.equity_curve.account_value.get();
+				let value2 = daily2.equity_curve.account_value.get();
+				let close1 = get_close(&daily1.date)?;
+				let close2 = get_close(&daily2.date)?;
+				if value1 > 0.0 && value2 > 0.0 && close1 > 0.0 && close2 > 0.0 {
+					Some((value2 / value1 - 1.0, close2 / close1 - 1.0))
+				} else {
+					None
+				}
+			})
+			.collect();
+		let benchmark_returns: Vec<f64> = pairs
+			.iter()
+			.map(|(_, benchmark_return)| *benchmark_return)
+			.collect();
+		let strategy_returns: Vec<f64> = pairs
+			.iter()
+			.map(|(strategy_return, _)| *strategy_return)
+			.collect();
+		let benchmark_mean = Self::mean_nan(&benchmark_returns);
+		let benchmark_variance = Self::standard_deviation_nan(&benchmark_returns, benchmark_mean).powi(2);
+		let beta = Self::error_to_nan(covariance(&pairs)) / benchmark_variance;
+		// Jensen's alpha without a risk-free rate, annualized
+		let alpha = TRADING_DAYS_PER_YEAR * (Self::mean_nan(&strategy_returns) - beta * benchmark_mean);
+		Ok((beta, alpha))
+	}
+
+	fn get_equity_curve_values(equity_curve_daily: &[DailyStats]) -> Vec<(NaiveDateTime, f64)> {
+		equity_curve_daily
+			.iter()
+			.map(|x| (x.date, x.equity_curve.account_value.get()))
+			.collect()
+	}
+
 	fn get_trade_results(&self, long: bool, short: bool) -> Result<TradeResults> {
 		let source = self.profit_duration_stats
 			.iter()
@@ -1091,7 +1808,34 @@ impl Backtest {
 		Ok(results)
 	}
 
-	fn get_ratios(&self, max_drawdown: f64, equity_curve_daily: &Vec<DailyStats>) -> Result<(f64, f64, f64)> {
+	fn get_ratios(&self, equity_curve_daily: &[DailyStats]) -> Result<(f64, f64)> {
+		// Daily excess returns over the T-bill rate of the same day, annualized afterwards
+		let t_bills = self.asset_manager.get_time_series("TB3MS")?;
+		let dated_returns = Self::get_dated_daily_returns(equity_curve_daily);
+		let mut daily_returns = Vec::new();
+		let mut risk_free_rates = Vec::new();
+		for (date, daily_return) in dated_returns {
+			let annual_rate = t_bills.get(&date.date())? / 100.0;
+			daily_returns.push(daily_return);
+			risk_free_rates.push(get_periodic_rate(annual_rate, TRADING_DAYS_PER_YEAR));
+		}
+		let targets = match self.configuration.minimum_acceptable_return {
+			Some(minimum_acceptable_return) => {
+				let daily_target = get_periodic_rate(minimum_acceptable_return, TRADING_DAYS_PER_YEAR);
+				vec![daily_target; daily_returns.len()]
+			},
+			None => risk_free_rates.clone()
+		};
+		let sharpe_ratio = Self::error_to_nan(sharpe_ratio(&daily_returns, &risk_free_rates));
+		let sortino_ratio = Self::error_to_nan(sortino_ratio(&daily_returns, &targets));
+		let output = (
+			annualize_ratio(sharpe_ratio, TRADING_DAYS_PER_YEAR),
+			annualize_ratio(sortino_ratio, TRADING_DAYS_PER_YEAR)
+		);
+		Ok(output)
+	}
+
+	fn get_legacy_ratios(&self, max_drawdown: f64, equity_curve_daily: &Vec<DailyStats>) -> Result<(f64, f64, f64)> {
 		let daily_returns = Self::get_daily_returns(equity_curve_daily);
 		let mean_daily_returns = Self::mean_nan(&daily_returns);
 		let daily_standard_deviation = Self::standard_deviation_nan(&daily_returns, mean_daily_returns);
@@ -1152,7 +1896,7 @@ impl Backtest {
 		if self.configuration.enable_interest {
 			let date = self.now.date();
 			let annual_rate = (self.fed_funds_rate.get(&date)? / 100.0 - 0.005).max(0.0);
-			let daily_rate = (annual_rate + 1.0).powf(1.0 / TRADING_DAYS_PER_YEAR) - 1.0;
+			let daily_rate = get_periodic_rate(annual_rate, TRADING_DAYS_PER_YEAR);
 			let Some((_, maximum_ratio)) = interpolation_table.last() else {
 				bail!("Empty interpolation table");
 			};
@@ -1203,6 +1947,70 @@ impl BacktestResult {
 		}
 	}
 
+	// Scalar metrics used to summarize and compare stored results
+	pub fn get_metrics(&self) -> Vec<(&'static str, f64)> {
+		vec![
+			("finalCash", self.final_cash.get()),
+			("profit", self.profit.get()),
+			("totalReturn", self.total_return.get()),
+			("compoundAnnualGrowthRate", self.compound_annual_growth_rate.get()),
+			("sharpeRatio", self.sharpe_ratio.get()),
+			("sortinoRatio", self.sortino_ratio.get()),
+			("calmarRatio", self.calmar_ratio.get()),
+			("maxDrawdown", self.max_drawdown.get()),
+			("annualizedVolatility", self.annualized_volatility.get()),
+			("ulcerIndex", self.ulcer_index.get()),
+			("exposure", self.exposure.get()),
+			("turnover", self.turnover.get()),
+			("fees", self.fees.get()),
+			("trades", self.all_trades.trades as f64),
+			("winRate", self.all_trades.win_rate.get()),
+			("profitFactor", self.all_trades.profit_factor.get())
+		]
+	}
+
+	// Daily equity curve with full precision for exports
+	pub fn get_equity_curve_table(&self) -> Result<Table> {
+		let daily = &self.equity_curve_daily;
+		let get_floats = |get_value: fn(&DailyStats) -> &WebF64| daily
+			.iter()
+			.map(|x| get_value(x).get())
+			.collect();
+		let mut table = Table::new(daily.len());
+		table.add_time("date", daily.iter().map(|x| x.date).collect())?;
+		table.add_float("accountValue", get_floats(|x| &x.equity_curve.account_value))?;
+		table.add_float("drawdown", get_floats(|x| &x.equity_curve.drawdown))?;
+		table.add_float("drawdownPercent", get_floats(|x| &x.equity_curve.drawdown_percent))?;
+		table.add_float("maintenanceMargin", get_floats(|x| &x.maintenance_margin))?;
+		table.add_float("overnightMargin", get_floats(|x| &x.overnight_margin))?;
+		Ok(table)
+	}
+
+	pub fn get_trades_table(&self) -> Result<Table> {
+		let trades = &self.trades;
+		let get_floats = |get_value: fn(&Trade) -> &WebF64| trades
+			.iter()
+			.map(|x| get_value(x).get())
+			.collect();
+		let get_integers = |get_value: fn(&Trade) -> u32| trades
+			.iter()
+			.map(|x| Some(get_value(x) as i64))
+			.collect();
+		let mut table = Table::new(trades.len());
+		table.add_integer("positionId", get_integers(|x| x.position_id))?;
+		table.add_text("symbol", trades.iter().map(|x| x.symbol.clone()).collect())?;
+		table.add_text("side", trades.iter().map(|x| x.side.clone()).collect())?;
+		table.add_integer("count", get_integers(|x| x.count))?;
+		table.add_time("entryTime", trades.iter().map(|x| x.entry_time).collect())?;
+		table.add_time("exitTime", trades.iter().map(|x| x.exit_time).collect())?;
+		table.add_float("entryPrice", get_floats(|x| &x.entry_price))?;
+		table.add_float("exitPrice", get_floats(|x| &x.exit_price))?;
+		table.add_float("profit", get_floats(|x| &x.profit))?;
+		table.add_float("fees", get_floats(|x| &x.fees))?;
+		table.add_integer("barsInTrade", get_integers(|x| x.bars_in_trade))?;
+		Ok(table)
+	}
+
 	fn get_keys(&self) -> BacktestOrderKeys {
 		(self.sortino_ratio.get(), self.sharpe_ratio.get(), self.total_return.get())
 	}
@@ -1233,6 +2041,26 @@ impl Ord for BacktestResult {
 }
 
 impl SimplifiedBacktestResult {
+	pub fn get_parameters(&self) -> &StrategyParameters {
+		&self.parameters
+	}
+
+	pub fn get_metrics(&self) -> Vec<(&'static str, f64)> {
+		vec![
+			("trades", self.trades as f64),
+			("finalCash", self.final_cash.get()),
+			("profit", self.profit.get()),
+			("annualAverageProfit", self.annual_average_profit.get()),
+			("totalReturn", self.total_return.get()),
+			("annualAverageReturn", self.annual_average_return.get()),
+			("compoundAnnualGrowthRate", self.compound_annual_growth_rate.get()),
+			("sharpeRatio", self.sharpe_ratio.get()),
+			("sortinoRatio", self.sortino_ratio.get()),
+			("calmarRatio", self.calmar_ratio.get()),
+			("maxDrawdown", self.max_drawdown.get())
+		]
+	}
+
 	fn get_keys(&self) -> BacktestOrderKeys {
 		(self.sortino_ratio.get(), self.sharpe_ratio.get(), self.total_return.get())
 	}
@@ -1292,7 +2120,7 @@ impl Ord for SimplifiedBacktestResult {
 }
 
 impl BacktestSeries {
-	pub fn new(best_parameters: StrategyParameters, best_result: BacktestResult, results: &Vec<(&StrategyParameters, BacktestResult)>, stopwatch: Stopwatch) -> BacktestSeries {
+	pub fn new(manifest: BacktestManifest, best_parameters: StrategyParameters, best_result: BacktestResult, results: &Vec<(&StrategyParameters, BacktestResult)>, stopwatch: Stopwatch) -> BacktestSeries {
 		let mut simplified_results: Vec<SimplifiedBacktestResult> = results
 			.iter()
 			.map(|(parameters, result)| result.simple((*parameters).clone()))
@@ -1301,6 +2129,8 @@ impl BacktestSeries {
 		let median_result = Self::get_median_result(&simplified_results);
 		let stopwatch_secs = WebF64::new(stopwatch.elapsed().as_secs_f64());
 		Self {
+			id: None,
+			manifest,
 			best_parameters,
 			best_result,
 			results: simplified_results,
@@ -1309,6 +2139,75 @@ impl BacktestSeries {
 		}
 	}
 
+	pub fn set_id(&mut self, id: u64) {
+		self.id = Some(id);
+	}
+
+	pub fn get_manifest(&self) -> &BacktestManifest {
+		&self.manifest
+	}
+
+	pub fn get_best_parameters(&self) -> &StrategyParameters {
+		&self.best_parameters
+	}
+
+	pub fn get_best_result(&self) -> &BacktestResult {
+		&self.best_result
+	}
+
+	pub fn get_results(&self) -> &Vec<SimplifiedBacktestResult> {
+		&self.results
+	}
+
+	/*
+	Table of all parameter combinations of a sweep, one row per backtest.
+	Numeric parameters are exported as floats, all other parameter types as strings.
+	*/
+	pub fn get_sweep_table(&self) -> Result<Table> {
+		let mut table = Table::new(self.results.len());
+		let Some(first) = self.results.first() else {
+			return Ok(table);
+		};
+		for parameter in first.parameters.iter() {
+			let name = &parameter.name;
+			let parameters: Vec<Option<&StrategyParameter>> = self.results
+				.iter()
+				.map(|result| result.parameters
+					.iter()
+					.find(|x| x.name == *name))
+				.collect();
+			let values: Option<Vec<f64>> = parameters
+				.iter()
+				.map(|x| x
+					.filter(|x| x.limit.is_none())
+					.and_then(|x| x.value.as_ref())
+					.map(|x| x.get()))
+				.collect();
+			match values {
+				Some(values) => table.add_float(name, values)?,
+				None => {
+					let strings = parameters
+						.iter()
+						.map(|x| x
+							.map(|x| x.string_value
+								.clone()
+								.unwrap_or_else(|| x.get_value_string()))
+							.unwrap_or_default())
+						.collect();
+					table.add_text(name, strings)?;
+				}
+			}
+		}
+		for (index, (name, _)) in first.get_metrics().iter().enumerate() {
+			let values = self.results
+				.iter()
+				.map(|x| x.get_metrics()[index].1)
+				.collect();
+			table.add_float(name, values)?;
+		}
+		Ok(table)
+	}
+
 	fn get_median_result(simplified_results: &Vec<SimplifiedBacktestResult>) -> SimplifiedBacktestResult {
 		let n = simplified_results.len();
 		let odd = n % 2 == 1;
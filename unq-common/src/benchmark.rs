@@ -0,0 +1,179 @@
+use std::sync::Arc;
+use anyhow::{bail, Result};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::backtest::TRADING_DAYS_PER_YEAR;
+use crate::manager::{AssetManager, CsvTimeSeries};
+use crate::ohlc::OhlcArchive;
+use crate::stats::{mean, standard_deviation};
+use crate::web::WebF64;
+
+/*
+Reference asset the equity curve of a backtest is compared against.
+The benchmark is treated as a buy and hold position that is fully invested at the start of the backtest.
+Its values are either taken from the daily close of an OHLC archive (e.g. "ES")
+or from a .csv time series loaded by the asset manager.
+*/
+pub struct Benchmark {
+	symbol: String,
+	source: BenchmarkSource
+}
+
+enum BenchmarkSource {
+	Archive(Arc<OhlcArchive>),
+	TimeSeries(Arc<CsvTimeSeries>)
+}
+
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchmarkResult {
+	symbol: String,
+	equity_curve: Vec<BenchmarkCurveData>,
+	total_return: WebF64,
+	// Total return of the strategy minus the total return of the benchmark
+	relative_performance: WebF64,
+	// Annualized standard deviation of the daily active returns
+	tracking_error: WebF64,
+	information_ratio: WebF64,
+	up_capture: WebF64,
+	down_capture: WebF64
+}
+
+#[derive(Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchmarkCurveData {
+	date: NaiveDateTime,
+	account_value: WebF64
+}
+
+impl Benchmark {
+	pub fn new(symbol: &String, asset_manager: &AssetManager) -> Result<Benchmark> {
+		// Prefer OHLC archives over time series in case of ambiguous names
+		let source = if let Ok(archive) = asset_manager.get_archive(symbol) {
+			BenchmarkSource::Archive(archive)
+		} else if let Ok(time_series) = asset_manager.get_time_series(symbol) {
+			BenchmarkSource::TimeSeries(time_series)
+		} else {
+			bail!("Unable to find an archive or time series for benchmark \"{symbol}\"");
+		};
+		let benchmark = Benchmark {
+			symbol: symbol.clone(),
+			source
+		};
+		Ok(benchmark)
+	}
+
+	pub fn get_value(&self, time: &NaiveDateTime) -> Option<f64> {
+		match &self.source {
+			BenchmarkSource::Archive(archive) => archive.daily
+				.get_adjusted_fallback()
+				.range(..=time)
+				.next_back()
+				.map(|(_, record)| record.close),
+			BenchmarkSource::TimeSeries(time_series) => time_series
+				.get(&time.date())
+				.ok()
+		}
+	}
+
+	/*
+	Returns pairs of daily returns (strategy, benchmark) for an equity curve of (date, account value) samples.
+	Days on which either of them lacks a valid value are skipped.
+	*/
+	pub fn get_return_pairs(&self, equity_curve: &[(NaiveDateTime, f64)]) -> Vec<(f64, f64)> {
+		equity_curve
+			.windows(2)
+			.filter_map(|window| {
+				let [(date1, value1), (date2, value2)] = window else {
+					return None;
+				};
+				let benchmark1 = self.get_value(date1)?;
+				let benchmark2 = self.get_value(date2)?;
+				if *value1 > 0.0 && *value2 > 0.0 && benchmark1 > 0.0 && benchmark2 > 0.0 {
+					Some((value2 / value1 - 1.0, benchmark2 / benchmark1 - 1.0))
+				} else {
+					None
+				}
+			})
+			.collect()
+	}
+
+	pub fn get_result(&self, equity_curve: &[(NaiveDateTime, f64)]) -> BenchmarkResult {
+		let benchmark_curve = self.get_equity_curve(equity_curve);
+		let total_return = match (benchmark_curve.first(), benchmark_curve.last()) {
+			(Some(first), Some(last)) => last.account_value.get() / first.account_value.get() - 1.0,
+			_ => f64::NAN
+		};
+		let strategy_total_return = match (equity_curve.first(), equity_curve.last()) {
+			(Some((_, first)), Some((_, last))) => last / first - 1.0,
+			_ => f64::NAN
+		};
+		let pairs = self.get_return_pairs(equity_curve);
+		let active_returns: Vec<f64> = pairs
+			.iter()
+			.map(|(strategy_return, benchmark_return)| strategy_return - benchmark_return)
+			.collect();
+		let tracking_error = TRADING_DAYS_PER_YEAR.sqrt() * standard_deviation(active_returns.iter()).unwrap_or(f64::NAN);
+		let annual_active_return = TRADING_DAYS_PER_YEAR * mean(active_returns.iter()).unwrap_or(f64::NAN);
+		let information_ratio = annual_active_return / tracking_error;
+		let up_capture = get_capture_ratio(&pairs, true);
+		let down_capture = get_capture_ratio(&pairs, false);
+		BenchmarkResult {
+			symbol: self.symbol.clone(),
+			equity_curve: benchmark_curve,
+			total_return: WebF64::precise(total_return),
+			relative_performance: WebF64::precise(strategy_total_return - total_return),
+			tracking_error: WebF64::precise(tracking_error),
+			information_ratio: WebF64::new(information_ratio),
+			up_capture: WebF64::precise(up_capture),
+			down_capture: WebF64::precise(down_capture)
+		}
+	}
+
+	fn get_equity_curve(&self, equity_curve: &[(NaiveDateTime, f64)]) -> Vec<BenchmarkCurveData> {
+		// Scale the benchmark so that it starts out with the same account value as the strategy
+		let Some((_, starting_value)) = equity_curve.first() else {
+			return Vec::new();
+		};
+		let mut initial_value: Option<f64> = None;
+		equity_curve
+			.iter()
+			.filter_map(|(date, _)| {
+				let value = self.get_value(date)?;
+				let initial = *initial_value.get_or_insert(value);
+				let data = BenchmarkCurveData {
+					date: *date,
+					account_value: WebF64::new(starting_value * value / initial)
+				};
+				Some(data)
+			})
+			.collect()
+	}
+}
+
+fn unzip_returns(pairs: &[(f64, f64)]) -> (Vec<f64>, Vec<f64>) {
+	pairs
+		.iter()
+		.cloned()
+		.unzip()
+}
+
+fn get_capture_ratio(pairs: &[(f64, f64)], up: bool) -> f64 {
+	// Ratio of the mean strategy return to the mean benchmark return on days the benchmark went up/down
+	let filtered_pairs: Vec<(f64, f64)> = pairs
+		.iter()
+		.filter(|(_, benchmark_return)| {
+			if up {
+				*benchmark_return > 0.0
+			} else {
+				*benchmark_return < 0.0
+			}
+		})
+		.cloned()
+		.collect();
+	let (strategy_returns, benchmark_returns) = unzip_returns(&filtered_pairs);
+	let strategy_mean = mean(strategy_returns.iter()).unwrap_or(f64::NAN);
+	let benchmark_mean = mean(benchmark_returns.iter()).unwrap_or(f64::NAN);
+	strategy_mean / benchmark_mean
+}
@@ -0,0 +1,65 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+use anyhow::{bail, Result};
+use lru::LruCache;
+use crate::ohlc::OhlcArchive;
+
+/*
+Keeps the most recently used archives in memory until their estimated size exceeds the memory limit.
+The most recently used archive is always retained, even if it exceeds the limit on its own.
+*/
+pub struct ArchiveCache {
+	// In bytes
+	memory_limit: usize,
+	state: Mutex<CacheState>
+}
+
+struct CacheState {
+	// Archives and their estimated memory usage
+	archives: LruCache<String, (Arc<OhlcArchive>, usize)>,
+	memory_usage: usize
+}
+
+impl ArchiveCache {
+	pub fn new(memory_limit: usize) -> ArchiveCache {
+		let state = CacheState {
+			archives: LruCache::unbounded(),
+			memory_usage: 0
+		};
+		ArchiveCache {
+			memory_limit,
+			state: Mutex::new(state)
+		}
+	}
+
+	pub fn get(&self, symbol: &String, load: impl FnOnce() -> Result<OhlcArchive>) -> Result<Arc<OhlcArchive>> {
+		{
+			let mut state = self.lock()?;
+			if let Some((archive, _)) = state.archives.get(symbol) {
+				return Ok(archive.clone());
+			}
+		}
+		// Loading may take a while so other archives remain accessible in the meantime
+		let archive = Arc::new(load()?);
+		let memory_usage = archive.get_memory_usage();
+		let mut state = self.lock()?;
+		if let Some((_, previous_usage)) = state.archives.put(symbol.clone(), (archive.clone(), memory_usage)) {
+			// Another thread loaded the same archive concurrently
+			state.memory_usage -= previous_usage;
+		}
+		state.memory_usage += memory_usage;
+		while state.memory_usage > self.memory_limit && state.archives.len() > 1 {
+			let Some((_, (_, evicted_usage))) = state.archives.pop_lru() else {
+				break;
+			};
+			state.memory_usage -= evicted_usage;
+		}
+		Ok(archive)
+	}
+
+	fn lock(&self) -> Result<MutexGuard<'_, CacheState>> {
+		let Ok(state) = self.state.lock() else {
+			bail!("Failed to acquire archive cache lock");
+		};
+		Ok(state)
+	}
+}
@@ -1,11 +1,12 @@
 use std::sync::Arc;
 use chrono::{Duration, Local, Months, NaiveDateTime, TimeDelta, Timelike};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use anyhow::{Result, anyhow, Context, bail};
-use unq_common::ohlc::{OhlcArchive, TimeFrame};
+use utoipa::ToSchema;
+use crate::ohlc::{OhlcArchive, TimeFrame};
 
-#[derive(Deserialize, Clone)]
-enum OffsetUnit {
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, ToSchema)]
+pub enum OffsetUnit {
 	#[serde(rename = "m")]
 	Minutes,
 	#[serde(rename = "h")]
@@ -20,15 +21,15 @@ enum OffsetUnit {
 	Years
 }
 
-#[derive(Deserialize, PartialEq, Clone)]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, ToSchema)]
 #[serde(rename_all = "camelCase")]
-enum SpecialDateTime {
+pub enum SpecialDateTime {
 	First,
 	Last,
 	Now
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct RelativeDateTime {
 	date: Option<NaiveDateTime>,
@@ -59,6 +60,33 @@ pub struct RelativeDateTime {
 }
 
 impl RelativeDateTime {
+	pub fn from_date(date: NaiveDateTime) -> RelativeDateTime {
+		RelativeDateTime {
+			date: Some(date),
+			offset: None,
+			offset_unit: None,
+			special_keyword: None
+		}
+	}
+
+	pub fn from_offset(offset: i16, offset_unit: OffsetUnit) -> RelativeDateTime {
+		RelativeDateTime {
+			date: None,
+			offset: Some(offset),
+			offset_unit: Some(offset_unit),
+			special_keyword: None
+		}
+	}
+
+	pub fn from_keyword(special_keyword: SpecialDateTime) -> RelativeDateTime {
+		RelativeDateTime {
+			date: None,
+			offset: None,
+			offset_unit: None,
+			special_keyword: Some(special_keyword)
+		}
+	}
+
 	pub fn resolve(&self, other: &RelativeDateTime, time_frame: &TimeFrame, archives: &Vec<Arc<OhlcArchive>>) -> Result<NaiveDateTime> {
 		match (self.date.is_some(), self.offset.is_some(), self.offset_unit.is_some(), self.special_keyword.is_some()) {
 			(true, false, false, false) => Ok(self.date.unwrap()),
@@ -0,0 +1,185 @@
+use std::io::Write;
+use std::sync::Arc;
+use anyhow::{bail, Result};
+use arrow_array::{ArrayRef, Float64Array, Int64Array, RecordBatch, StringArray, TimestampSecondArray};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use chrono::NaiveDateTime;
+use parquet::arrow::ArrowWriter;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+	Csv,
+	Parquet
+}
+
+impl ExportFormat {
+	pub fn get_extension(&self) -> &str {
+		match self {
+			ExportFormat::Csv => "csv",
+			ExportFormat::Parquet => "parquet"
+		}
+	}
+
+	pub fn get_content_type(&self) -> &str {
+		match self {
+			ExportFormat::Csv => "text/csv",
+			ExportFormat::Parquet => "application/vnd.apache.parquet"
+		}
+	}
+}
+
+/*
+Column-oriented table used to export data without the rounding applied by WebF64.
+CSV files contain the shortest representation of each float that still round-trips to the same value.
+Parquet files use native 64-bit floats, integers and second-resolution timestamps.
+*/
+pub struct Table {
+	columns: Vec<Column>,
+	rows: usize
+}
+
+struct Column {
+	name: String,
+	data: ColumnData
+}
+
+enum ColumnData {
+	Float(Vec<f64>),
+	Integer(Vec<Option<i64>>),
+	Text(Vec<String>),
+	Time(Vec<NaiveDateTime>)
+}
+
+impl Table {
+	pub fn new(rows: usize) -> Table {
+		Table {
+			columns: Vec::new(),
+			rows
+		}
+	}
+
+	pub fn add_float(&mut self, name: &str, values: Vec<f64>) -> Result<()> {
+		self.add_column(name, values.len(), ColumnData::Float(values))
+	}
+
+	pub fn add_integer(&mut self, name: &str, values: Vec<Option<i64>>) -> Result<()> {
+		self.add_column(name, values.len(), ColumnData::Integer(values))
+	}
+
+	pub fn add_text(&mut self, name: &str, values: Vec<String>) -> Result<()> {
+		self.add_column(name, values.len(), ColumnData::Text(values))
+	}
+
+	pub fn add_time(&mut self, name: &str, values: Vec<NaiveDateTime>) -> Result<()> {
+		self.add_column(name, values.len(), ColumnData::Time(values))
+	}
+
+	pub fn write(&self, format: ExportFormat, writer: impl Write + Send) -> Result<()> {
+		match format {
+			ExportFormat::Csv => self.write_csv(writer),
+			ExportFormat::Parquet => self.write_parquet(writer)
+		}
+	}
+
+	pub fn to_bytes(&self, format: ExportFormat) -> Result<Vec<u8>> {
+		let mut output = Vec::new();
+		self.write(format, &mut output)?;
+		Ok(output)
+	}
+
+	fn add_column(&mut self, name: &str, length: usize, data: ColumnData) -> Result<()> {
+		if length != self.rows {
+			bail!("Column \"{name}\" contains {length} values but the table has {} rows", self.rows);
+		}
+		if self.columns.iter().any(|x| x.name == name) {
+			bail!("Duplicate column \"{name}\"");
+		}
+		let column = Column {
+			name: name.to_string(),
+			data
+		};
+		self.columns.push(column);
+		Ok(())
+	}
+
+	fn write_csv(&self, writer: impl Write) -> Result<()> {
+		let mut csv_writer = csv::Writer::from_writer(writer);
+		let header: Vec<&String> = self.columns
+			.iter()
+			.map(|x| &x.name)
+			.collect();
+		csv_writer.write_record(header)?;
+		for row in 0..self.rows {
+			let record: Vec<String> = self.columns
+				.iter()
+				.map(|column| column.data.get_string(row))
+				.collect();
+			csv_writer.write_record(record)?;
+		}
+		csv_writer.flush()?;
+		Ok(())
+	}
+
+	fn write_parquet(&self, writer: impl Write + Send) -> Result<()> {
+		let fields: Vec<Field> = self.columns
+			.iter()
+			.map(|column| Field::new(column.name.as_str(), column.data.get_data_type(), column.data.is_nullable()))
+			.collect();
+		let schema = Arc::new(Schema::new(fields));
+		let arrays: Vec<ArrayRef> = self.columns
+			.iter()
+			.map(|column| column.data.get_array())
+			.collect();
+		let batch = RecordBatch::try_new(schema.clone(), arrays)?;
+		let mut arrow_writer = ArrowWriter::try_new(writer, schema, None)?;
+		arrow_writer.write(&batch)?;
+		arrow_writer.close()?;
+		Ok(())
+	}
+}
+
+impl ColumnData {
+	fn get_string(&self, row: usize) -> String {
+		match self {
+			ColumnData::Float(values) => values[row].to_string(),
+			ColumnData::Integer(values) => values[row]
+				.map(|x| x.to_string())
+				.unwrap_or_default(),
+			ColumnData::Text(values) => values[row].clone(),
+			ColumnData::Time(values) => values[row].format(TIME_FORMAT).to_string()
+		}
+	}
+
+	fn get_data_type(&self) -> DataType {
+		match self {
+			ColumnData::Float(_) => DataType::Float64,
+			ColumnData::Integer(_) => DataType::Int64,
+			ColumnData::Text(_) => DataType::Utf8,
+			ColumnData::Time(_) => DataType::Timestamp(TimeUnit::Second, None)
+		}
+	}
+
+	fn is_nullable(&self) -> bool {
+		matches!(self, ColumnData::Integer(_))
+	}
+
+	fn get_array(&self) -> ArrayRef {
+		match self {
+			ColumnData::Float(values) => Arc::new(Float64Array::from(values.clone())),
+			ColumnData::Integer(values) => Arc::new(Int64Array::from(values.clone())),
+			ColumnData::Text(values) => Arc::new(StringArray::from(values.clone())),
+			ColumnData::Time(values) => {
+				let timestamps: Vec<i64> = values
+					.iter()
+					.map(|x| x.and_utc().timestamp())
+					.collect();
+				Arc::new(TimestampSecondArray::from(timestamps))
+			}
+		}
+	}
+}
@@ -1,35 +1,93 @@
+pub mod api;
+pub mod archive;
 pub mod backtest;
+pub mod benchmark;
+pub mod datetime;
+pub mod export;
 pub mod manager;
+pub mod manifest;
+pub mod mapped;
 pub mod ohlc;
+pub mod resample;
 pub mod globex;
 pub mod strategy;
 pub mod web;
 pub mod stats;
 mod panama;
+mod cache;
 
-use std::{fs, fs::File, path::PathBuf};
+use std::{fs, path::PathBuf, str::FromStr};
 use configparser::ini::Ini;
 use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
 use anyhow::{anyhow, bail, Context, Error, Result};
+use crate::archive::{encode_archive, ArchiveFile};
+use crate::mapped::MappedArchive;
 use crate::ohlc::{OhlcArchive, RawOhlcArchive};
 
+// zstd-compressed archives
+pub const ARCHIVE_EXTENSION: &str = "zrk";
+// Uncompressed archives that can be memory-mapped
+pub const MAPPED_ARCHIVE_EXTENSION: &str = "rk";
+
 pub trait PathDisplay {
 	fn to_string(&self) -> &str;
 }
 
 pub fn read_archive(path: &PathBuf, skip_front_contract: bool) -> Result<OhlcArchive> {
-	let file = File::open(path)?;
-	let mut buffer = Vec::<u8>::new();
-	zstd::stream::copy_decode(file, &mut buffer)?;
-	let raw_archive: RawOhlcArchive = unsafe { rkyv::from_bytes_unchecked(&buffer)? };
-	let archive = raw_archive.to_archive(skip_front_contract)?;
-	return Ok(archive);
-}
-
-pub fn write_archive(path: &PathBuf, archive: &RawOhlcArchive) -> Result<()> {
-	let binary_data = rkyv::to_bytes::<_, 1024>(archive)?;
-	let file = File::create(path.clone())?;
-	zstd::stream::copy_encode(binary_data.as_slice(), file, 1)?;
+	let data = fs::read(path)
+		.with_context(|| anyhow!("Failed to read archive \"{}\"", path.to_string()))?;
+	decode_archive(&data, skip_front_contract)
+		.with_context(|| anyhow!("Failed to load archive \"{}\"", path.to_string()))
+}
+
+// Validates the archive and builds the maps straight from the archived records
+pub fn decode_archive(data: &[u8], skip_front_contract: bool) -> Result<OhlcArchive> {
+	let archive_file = ArchiveFile::parse(data)?;
+	let payload = archive_file.decode_payload()?;
+	let raw_archive = archive_file.check_payload(&payload)?;
+	raw_archive.to_archive(skip_front_contract)
+}
+
+// Reads a compressed or memory-mapped archive of any format version, returns the format version along with the records
+pub fn read_raw_archive(path: &PathBuf) -> Result<(u16, RawOhlcArchive)> {
+	let data = fs::read(path)
+		.with_context(|| anyhow!("Failed to read archive \"{}\"", path.to_string()))?;
+	let archive_file = ArchiveFile::parse(&data)
+		.with_context(|| anyhow!("Failed to load archive \"{}\"", path.to_string()))?;
+	let payload = archive_file.decode_payload()?;
+	let raw_archive = archive_file.read_payload(&payload)
+		.with_context(|| anyhow!("Failed to load archive \"{}\"", path.to_string()))?;
+	Ok((archive_file.format_version, raw_archive))
+}
+
+pub fn write_archive(path: &PathBuf, symbol: &str, parser_version: &str, archive: &RawOhlcArchive) -> Result<()> {
+	let data = encode_archive(symbol, parser_version, archive, true)?;
+	write_file(path, &data)
+}
+
+// The mapping is only used while loading, the records are materialized into an OhlcArchive
+pub fn read_mapped_archive(path: &PathBuf, skip_front_contract: bool) -> Result<OhlcArchive> {
+	let mapped_archive = MappedArchive::open(path)?;
+	mapped_archive.to_archive(skip_front_contract)
+}
+
+// Memory-mapped archives are stored without compression
+pub fn write_mapped_archive(path: &PathBuf, symbol: &str, parser_version: &str, archive: &RawOhlcArchive) -> Result<()> {
+	let data = encode_archive(symbol, parser_version, archive, false)?;
+	write_file(path, &data)
+}
+
+/*
+Archives must never be modified in place while they might still be mapped or read by another process.
+The data is written to a temporary file first, which then replaces the archive.
+*/
+fn write_file(path: &PathBuf, data: &[u8]) -> Result<()> {
+	let temporary_path = path.with_extension("tmp");
+	fs::write(&temporary_path, data)
+		.with_context(|| anyhow!("Failed to write archive to \"{}\"", temporary_path.to_string()))?;
+	fs::rename(&temporary_path, path)
+		.with_context(|| anyhow!("Failed to replace archive \"{}\"", path.to_string()))?;
 	Ok(())
 }
 
@@ -41,8 +99,37 @@ pub fn get_ini(path: &str) -> Result<Ini> {
 	Ok(config)
 }
 
+pub fn get_ini_string(config: &Ini, section: &str, key: &str) -> Result<String> {
+	config.get(section, key)
+		.with_context(|| anyhow!("Failed to find key \"{key}\" in section \"{section}\" in configuration file"))
+}
+
+pub fn get_ini_value<T: FromStr>(config: &Ini, section: &str, key: &str) -> Result<T> {
+	let value = get_ini_string(config, section, key)?;
+	value.parse()
+		.map_err(|_| anyhow!("Failed to parse value for key \"{key}\" in section \"{section}\" in configuration file"))
+}
+
+// Returns None if the key is missing but fails if the value is invalid
+pub fn get_ini_optional<T: FromStr>(config: &Ini, section: &str, key: &str) -> Result<Option<T>> {
+	match config.get(section, key) {
+		Some(_) => Ok(Some(get_ini_value(config, section, key)?)),
+		None => Ok(None)
+	}
+}
+
+// Hexadecimal SHA-256 digest used to identify the contents of archives, scripts and configuration files
+pub fn get_hash(data: &[u8]) -> String {
+	let digest = Sha256::digest(data);
+	hex::encode(digest)
+}
+
 pub fn get_archive_file_name(symbol: &String) -> String {
-	format!("{symbol}.zrk")
+	format!("{symbol}.{ARCHIVE_EXTENSION}")
+}
+
+pub fn get_mapped_archive_file_name(symbol: &String) -> String {
+	format!("{symbol}.{MAPPED_ARCHIVE_EXTENSION}")
 }
 
 pub fn read_csv<T>(path: PathBuf, mut on_record: impl FnMut(T)) -> Result<()>
@@ -65,7 +152,7 @@ where
 }
 
 fn get_files_by_extension(directory: &String, extension: &str) -> Result<Vec<(String, PathBuf)>> {
-	let entries = fs::read_dir(&directory)
+	let entries = fs::read_dir(directory)
 		.map_err(Error::msg)
 		.with_context(|| anyhow!("Failed to read list of files from {directory}"))?;
 	let stem_paths = entries
@@ -1,12 +1,16 @@
 use std::collections::HashMap;
 use std::collections::BTreeMap;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use anyhow::{Context, Result, bail, Error, anyhow};
-use chrono::NaiveDate;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
-use crate::{get_files_by_extension, read_archive, read_csv, OhlcArchive, PathDisplay};
+use utoipa::ToSchema;
+use crate::archive::{read_archive_header, ArchiveFile};
+use crate::cache::ArchiveCache;
+use crate::{decode_archive, get_files_by_extension, get_hash, read_csv, read_archive, read_mapped_archive, OhlcArchive, PathDisplay, ARCHIVE_EXTENSION, MAPPED_ARCHIVE_EXTENSION};
 
 #[derive(Deserialize, Clone, PartialEq)]
 pub enum AssetType {
@@ -28,41 +32,109 @@ pub struct Asset {
 	pub physical_delivery: bool
 }
 
+type ArchiveMap = HashMap<String, Arc<OhlcArchive>>;
+type SnapshotMap = HashMap<String, ArchiveSnapshot>;
+
 pub struct CsvTimeSeries {
 	time_series: BTreeMap<NaiveDate, f64>
 }
 
+/*
+Identifies the exact version of an archive that was loaded by the asset manager.
+Stored alongside backtest results so that runs performed on different data can be told apart.
+*/
+#[derive(Serialize, Deserialize, Clone, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveSnapshot {
+	pub symbol: String,
+	// Checksum of the payload from the archive header, SHA-256 hash of the entire file for legacy archives
+	pub hash: String,
+	pub modified: NaiveDateTime
+}
+
+enum ArchiveStorage {
+	// All archives are loaded at startup
+	Eager(ArchiveMap),
+	// Archives are loaded on first use and evicted from the cache once the memory limit has been exceeded
+	Lazy {
+		paths: HashMap<String, PathBuf>,
+		cache: ArchiveCache
+	}
+}
+
 pub struct AssetManager {
-	tickers: HashMap<String, Arc<OhlcArchive>>,
+	tickers: ArchiveStorage,
+	snapshots: SnapshotMap,
 	assets: HashMap<String, Asset>,
+	// SHA-256 hash of the asset definitions in assets.csv
+	assets_hash: String,
 	time_series: HashMap<String, Arc<CsvTimeSeries>>
 }
 
 impl AssetManager {
-	pub fn new(ticker_directory: &String, csv_directory: &String, asset_path: &String) -> Result<AssetManager> {
+	/*
+	Without a memory limit all archives are loaded into memory at startup.
+	With a memory limit (in bytes) only the snapshots are created at startup and archives are loaded on first use instead.
+	*/
+	pub fn new(ticker_directory: &String, csv_directory: &String, asset_path: &String, memory_limit: Option<usize>) -> Result<AssetManager> {
 		let assets = Self::load_assets(asset_path)?;
+		let assets_data = fs::read(asset_path)
+			.with_context(|| anyhow!("Failed to read asset definitions from \"{asset_path}\""))?;
+		let assets_hash = get_hash(&assets_data);
 		let time_series = Self::load_csv_files(csv_directory)?;
-		let tickers = Self::load_archives(ticker_directory, &assets)?;
+		let (tickers, snapshots) = match memory_limit {
+			Some(memory_limit) => Self::scan_archives(ticker_directory, memory_limit)?,
+			None => Self::load_archives(ticker_directory, &assets)?
+		};
 		let manager = AssetManager {
 			tickers,
+			snapshots,
 			assets,
+			assets_hash,
 			time_series
 		};
 		Ok(manager)
 	}
 
 	pub fn get_archive(&self, symbol: &String) -> Result<Arc<OhlcArchive>> {
-		if let Some(archive) = self.tickers.get(symbol) {
-			Ok(archive.clone())
-		} else {
-			bail!("Unable to find an archive for ticker {symbol}");
+		match &self.tickers {
+			ArchiveStorage::Eager(tickers) => {
+				let Some(archive) = tickers.get(symbol) else {
+					bail!("Unable to find an archive for ticker {symbol}");
+				};
+				Ok(archive.clone())
+			},
+			ArchiveStorage::Lazy { paths, cache } => {
+				let Some(path) = paths.get(symbol) else {
+					bail!("Unable to find an archive for ticker {symbol}");
+				};
+				cache.get(symbol, || {
+					let physical_delivery = Self::physical_delivery(symbol.to_string(), &self.assets);
+					Self::read_archive(path, physical_delivery)
+				})
+			}
 		}
 	}
 
+	pub fn get_snapshot(&self, symbol: &String) -> Result<ArchiveSnapshot> {
+		let Some(snapshot) = self.snapshots.get(symbol) else {
+			bail!("Unable to find a snapshot for ticker {symbol}");
+		};
+		Ok(snapshot.clone())
+	}
+
+	pub fn get_snapshots(&self) -> &HashMap<String, ArchiveSnapshot> {
+		&self.snapshots
+	}
+
+	pub fn get_assets_hash(&self) -> &String {
+		&self.assets_hash
+	}
+
 	pub fn resolve_symbols(&self, symbols: &Vec<String>) -> Result<Vec<String>> {
 		let all_keyword = "all";
 		if symbols.iter().any(|x| x == all_keyword) {
-			let output = self.tickers
+			let output = self.snapshots
 				.keys()
 				.cloned()
 				.collect();
@@ -94,16 +166,106 @@ impl AssetManager {
 		Ok(assets)
 	}
 
-	fn load_archives(ticker_directory: &String, assets: &HashMap<String, Asset>) -> Result<HashMap<String, Arc<OhlcArchive>>> {
-		let stem_paths = get_files_by_extension(ticker_directory, "zrk")?;
+	fn load_archives(ticker_directory: &String, assets: &HashMap<String, Asset>) -> Result<(ArchiveStorage, SnapshotMap)> {
+		let stem_paths = Self::get_archive_paths(ticker_directory)?;
 		let tuples = stem_paths.par_iter().map(|(symbol, path)| {
 			let physical_delivery = Self::physical_delivery(symbol.to_string(), assets);
-			let archive = read_archive(path, physical_delivery)?;
+			// Mapped archives are only read in full if they lack a header, like in scan_archives
+			let (archive, hash) = if Self::is_mapped_archive(path) {
+				let header = read_archive_header(path)
+					.with_context(|| anyhow!("Failed to read header of archive \"{}\"", path.to_string()))?;
+				let hash = match header {
+					Some(header) => header.checksum,
+					None => {
+						let data = fs::read(path)
+							.with_context(|| anyhow!("Failed to read archive \"{}\"", path.to_string()))?;
+						get_hash(&data)
+					}
+				};
+				let archive = read_mapped_archive(path, physical_delivery)?;
+				(archive, hash)
+			} else {
+				let data = fs::read(path)
+					.with_context(|| anyhow!("Failed to read archive \"{}\"", path.to_string()))?;
+				let archive = decode_archive(&data, physical_delivery)
+					.with_context(|| anyhow!("Failed to load archive \"{}\"", path.to_string()))?;
+				let hash = match ArchiveFile::parse(&data)?.header {
+					Some(header) => header.checksum,
+					None => get_hash(&data)
+				};
+				(archive, hash)
+			};
 			let archive_arc = Arc::new(archive);
-			Ok((symbol.clone(), archive_arc))
-		}).collect::<Result<Vec<(String, Arc<OhlcArchive>)>>>()?;
-		let map: HashMap<String, Arc<OhlcArchive>> = tuples.into_iter().collect();
-		Ok(map)
+			let snapshot = Self::get_archive_snapshot(symbol, path, hash)?;
+			Ok((symbol.clone(), archive_arc, snapshot))
+		}).collect::<Result<Vec<(String, Arc<OhlcArchive>, ArchiveSnapshot)>>>()?;
+		let mut tickers = HashMap::new();
+		let mut snapshots = HashMap::new();
+		for (symbol, archive, snapshot) in tuples {
+			tickers.insert(symbol.clone(), archive);
+			snapshots.insert(symbol, snapshot);
+		}
+		Ok((ArchiveStorage::Eager(tickers), snapshots))
+	}
+
+	/*
+	Only reads the headers of the archives, they are loaded by get_archive on first use.
+	Legacy archives without a header still need to be read in full to hash them.
+	*/
+	fn scan_archives(ticker_directory: &String, memory_limit: usize) -> Result<(ArchiveStorage, SnapshotMap)> {
+		let stem_paths = Self::get_archive_paths(ticker_directory)?;
+		let snapshots = stem_paths.par_iter().map(|(symbol, path)| {
+			let header = read_archive_header(path)
+				.with_context(|| anyhow!("Failed to read header of archive \"{}\"", path.to_string()))?;
+			let hash = match header {
+				Some(header) => header.checksum,
+				None => {
+					let data = fs::read(path)
+						.with_context(|| anyhow!("Failed to read archive \"{}\"", path.to_string()))?;
+					get_hash(&data)
+				}
+			};
+			let snapshot = Self::get_archive_snapshot(symbol, path, hash)?;
+			Ok((symbol.clone(), snapshot))
+		}).collect::<Result<SnapshotMap>>()?;
+		let storage = ArchiveStorage::Lazy {
+			paths: stem_paths.into_iter().collect(),
+			cache: ArchiveCache::new(memory_limit)
+		};
+		Ok((storage, snapshots))
+	}
+
+	// Memory-mapped archives take precedence over compressed archives with the same symbol
+	fn get_archive_paths(ticker_directory: &String) -> Result<HashMap<String, PathBuf>> {
+		let mut paths: HashMap<String, PathBuf> = get_files_by_extension(ticker_directory, ARCHIVE_EXTENSION)?
+			.into_iter()
+			.collect();
+		let mapped_paths = get_files_by_extension(ticker_directory, MAPPED_ARCHIVE_EXTENSION)?;
+		paths.extend(mapped_paths);
+		Ok(paths)
+	}
+
+	fn get_archive_snapshot(symbol: &str, path: &Path, hash: String) -> Result<ArchiveSnapshot> {
+		let modified: DateTime<Utc> = fs::metadata(path)?.modified()?.into();
+		let snapshot = ArchiveSnapshot {
+			symbol: symbol.to_string(),
+			hash,
+			modified: modified.naive_utc()
+		};
+		Ok(snapshot)
+	}
+
+	fn is_mapped_archive(path: &Path) -> bool {
+		path.extension()
+			.and_then(|x| x.to_str()) == Some(MAPPED_ARCHIVE_EXTENSION)
+	}
+
+	fn read_archive(path: &PathBuf, physical_delivery: bool) -> Result<OhlcArchive> {
+		if Self::is_mapped_archive(path) {
+			read_mapped_archive(path, physical_delivery)
+		} else {
+			read_archive(path, physical_delivery)
+		}
 	}
 
 	fn physical_delivery(symbol: String, assets: &HashMap<String, Asset>) -> bool {
@@ -0,0 +1,111 @@
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::backtest::BacktestConfiguration;
+use crate::get_hash;
+use crate::manager::{ArchiveSnapshot, AssetManager};
+use crate::ohlc::TimeFrame;
+use crate::strategy::StrategyParameters;
+use crate::web::serialize_full_precision;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/*
+Everything that is required to run a series of backtests, with all relative dates already resolved.
+Parameters may still contain ranges and multiple values that are expanded into multiple backtests.
+*/
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BacktestSpecification {
+	pub strategy: String,
+	pub symbols: Vec<String>,
+	pub parameters: StrategyParameters,
+	pub from: NaiveDateTime,
+	pub to: NaiveDateTime,
+	pub time_frame: TimeFrame,
+	pub configuration: BacktestConfiguration
+}
+
+/*
+Records the inputs of a backtest series so that it can be reproduced later on.
+Apart from the specification itself this includes content hashes of the data the results depended on,
+which makes it possible to detect archives, asset definitions or scripts that have since changed.
+*/
+#[derive(Serialize, Deserialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BacktestManifest {
+	// Version of the backtesting engine
+	pub version: String,
+	// Parameters are serialized without rounding so that replaying a manifest sent by a client yields the same backtests
+	#[serde(serialize_with = "serialize_full_precision")]
+	pub specification: BacktestSpecification,
+	pub configuration_hash: String,
+	// Archives of all symbols and the benchmark, if it is an archive
+	pub archives: Vec<ArchiveSnapshot>,
+	pub assets_hash: String,
+	// Only set for script strategies
+	pub script_hash: Option<String>
+}
+
+impl BacktestManifest {
+	pub fn new(specification: BacktestSpecification, script_source: Option<&String>, asset_manager: &AssetManager) -> Result<BacktestManifest> {
+		let configuration_hash = Self::get_configuration_hash(&specification.configuration)?;
+		let mut archives = specification.symbols
+			.iter()
+			.map(|symbol| asset_manager.get_snapshot(symbol))
+			.collect::<Result<Vec<ArchiveSnapshot>>>()?;
+		if let Some(benchmark) = &specification.configuration.benchmark {
+			// Benchmarks based on .csv time series aren't tracked
+			if let Ok(snapshot) = asset_manager.get_snapshot(benchmark) {
+				if !specification.symbols.contains(benchmark) {
+					archives.push(snapshot);
+				}
+			}
+		}
+		let assets_hash = asset_manager.get_assets_hash().clone();
+		let script_hash = script_source.map(|x| get_hash(x.as_bytes()));
+		let manifest = BacktestManifest {
+			version: VERSION.to_string(),
+			specification,
+			configuration_hash,
+			archives,
+			assets_hash,
+			script_hash
+		};
+		Ok(manifest)
+	}
+
+	/*
+	Compares a stored manifest to one generated from the current state of the server with the same specification.
+	Returns a list of warnings about inputs that no longer match.
+	*/
+	pub fn get_mismatches(&self, current: &BacktestManifest) -> Vec<String> {
+		let mut warnings = Vec::new();
+		if self.version != current.version {
+			warnings.push(format!("Backtest engine version changed from {} to {}", self.version, current.version));
+		}
+		for snapshot in self.archives.iter() {
+			match current.archives.iter().find(|x| x.symbol == snapshot.symbol) {
+				Some(current_snapshot) => {
+					if current_snapshot.hash != snapshot.hash {
+						warnings.push(format!("Archive of {} has been modified ({})", snapshot.symbol, current_snapshot.modified));
+					}
+				},
+				None => warnings.push(format!("Archive of {} is no longer available", snapshot.symbol))
+			}
+		}
+		if self.assets_hash != current.assets_hash {
+			warnings.push("Asset definitions have been modified".to_string());
+		}
+		if self.script_hash != current.script_hash {
+			warnings.push("Script source has been modified".to_string());
+		}
+		warnings
+	}
+
+	pub fn get_configuration_hash(configuration: &BacktestConfiguration) -> Result<String> {
+		let json = serde_json::to_string(configuration)?;
+		Ok(get_hash(json.as_bytes()))
+	}
+}
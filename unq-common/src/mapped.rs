@@ -0,0 +1,57 @@
+use std::fs::File;
+use std::path::PathBuf;
+use anyhow::{anyhow, bail, Context, Result};
+use memmap2::Mmap;
+use crate::archive::ArchiveFile;
+use crate::ohlc::{ArchivedRawOhlcArchive, OhlcArchive, RawOhlcArchive};
+use crate::PathDisplay;
+
+/*
+Uncompressed archive that is memory-mapped rather than read into memory.
+Opening it skips decompression and the intermediate copy of the file, but to_archive still deserializes the archived types
+into an OhlcArchive with owned records, so a loaded archive takes up as much memory as one read from a compressed file.
+The archive is validated once when it is opened. Archives must be replaced rather than modified in place
+(see write_mapped_archive) since changes to the underlying file would be visible through the mapping.
+*/
+pub struct MappedArchive {
+	mmap: Mmap,
+	payload_offset: usize
+}
+
+impl MappedArchive {
+	pub fn open(path: &PathBuf) -> Result<MappedArchive> {
+		let file = File::open(path)
+			.with_context(|| anyhow!("Failed to open archive \"{}\"", path.to_string()))?;
+		let mmap = unsafe { Mmap::map(&file) }
+			.with_context(|| anyhow!("Failed to map archive \"{}\"", path.to_string()))?;
+		let payload_offset = Self::validate(&mmap)
+			.with_context(|| anyhow!("Failed to load archive \"{}\"", path.to_string()))?;
+		let archive = MappedArchive {
+			mmap,
+			payload_offset
+		};
+		Ok(archive)
+	}
+
+	pub fn get_archive(&self) -> &ArchivedRawOhlcArchive {
+		// The payload has already been validated by open
+		unsafe { rkyv::archived_root::<RawOhlcArchive>(&self.mmap[self.payload_offset..]) }
+	}
+
+	pub fn to_archive(&self, skip_front_contract: bool) -> Result<OhlcArchive> {
+		self.get_archive().to_archive(skip_front_contract)
+	}
+
+	// Mappings are page-aligned and the payload starts at a multiple of 16, which satisfies the alignment requirements of rkyv
+	fn validate(data: &[u8]) -> Result<usize> {
+		let archive_file = ArchiveFile::parse(data)?;
+		if archive_file.is_compressed() {
+			bail!("Compressed archives can't be memory-mapped");
+		}
+		let payload_offset = archive_file.get_payload_offset(data);
+		let payload = &data[payload_offset..];
+		archive_file.verify_checksum(payload)?;
+		archive_file.check_payload(payload)?;
+		Ok(payload_offset)
+	}
+}
@@ -1,30 +1,87 @@
+use std::cmp::Reverse;
 use std::collections::{BTreeMap, HashSet};
+use std::mem::size_of;
 use anyhow::{bail, Result};
 use chrono::NaiveDateTime;
-use rkyv::{Archive, Deserialize, Serialize};
+use rkyv::{Archive, Deserialize, Infallible, Serialize};
+use utoipa::ToSchema;
 use crate::globex::GlobexCode;
 use crate::panama::{OffsetMap, PanamaCanal};
+use crate::resample::{PeriodUnit, ResamplePeriod};
 
 pub type OhlcVec = Vec<OhlcRecord>;
 pub type OhlcMap = BTreeMap<NaiveDateTime, OhlcRecord>;
 pub type OhlcContractMap = BTreeMap<NaiveDateTime, OhlcVec>;
 
-#[derive(Clone, PartialEq, Archive, Serialize, serde::Deserialize)]
+#[derive(Clone, PartialEq, Archive, Serialize, serde::Serialize, serde::Deserialize, ToSchema)]
 pub enum TimeFrame {
 	#[serde(rename = "daily")]
 	Daily,
 	#[serde(rename = "intraday")]
-	Intraday
+	Intraday,
+	/*
+	Long-horizon time frames built from daily records.
+	The backtest still iterates over trading days to keep track of interest, margin and the equity curve,
+	but the strategy is only executed on the last trading day of each period.
+	*/
+	#[serde(rename = "weekly")]
+	Weekly,
+	#[serde(rename = "monthly")]
+	Monthly,
+	// A fixed number of trading days, e.g. {"days": 5}
+	#[serde(rename = "days")]
+	Days(u16)
 }
 
 #[derive(Archive, Serialize, Deserialize)]
+#[archive(check_bytes)]
 pub struct RawOhlcArchive {
 	pub daily: Vec<RawOhlcRecord>,
-	pub intraday: Vec<RawOhlcRecord>,
-	pub intraday_time_frame: u16
+	// One series for each intraday resolution, e.g. M1, M15 and H1
+	pub intraday: Vec<RawIntradaySeries>,
+	// Bars aggregated from tick data by unq-parser, empty unless the ticker directory contained tick files
+	pub bars: Vec<RawBarSeries>
 }
 
 #[derive(Archive, Serialize, Deserialize)]
+#[archive(check_bytes)]
+pub struct RawIntradaySeries {
+	// Minutes
+	pub time_frame: u16,
+	pub records: Vec<RawOhlcRecord>
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Archive, Serialize, Deserialize, serde::Serialize, serde::Deserialize, ToSchema)]
+#[archive(check_bytes)]
+#[serde(rename_all = "camelCase")]
+pub enum BarType {
+	// Fixed intervals of "size" minutes, aligned to midnight
+	Time,
+	// A new bar is started once "size" contracts have been traded
+	Volume,
+	// A new bar is started once the traded value (price times size, without the contract multiplier) reaches "size"
+	Dollar,
+	// A new bar is started once the range between high and low reaches "size" in price units
+	Range
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Archive, Serialize, Deserialize, serde::Serialize, serde::Deserialize, ToSchema)]
+#[archive(check_bytes)]
+#[serde(rename_all = "camelCase")]
+pub struct BarSpecification {
+	pub bar_type: BarType,
+	pub size: f64
+}
+
+#[derive(Archive, Serialize, Deserialize)]
+#[archive(check_bytes)]
+pub struct RawBarSeries {
+	pub specification: BarSpecification,
+	pub records: Vec<RawOhlcRecord>
+}
+
+#[derive(Archive, Serialize, Deserialize)]
+#[archive(check_bytes)]
 pub struct RawOhlcRecord {
 	pub symbol: String,
 	pub time: NaiveDateTime,
@@ -36,10 +93,32 @@ pub struct RawOhlcRecord {
 	pub open_interest: Option<u32>
 }
 
+// Read access shared by deserialized records and the archived records of memory-mapped files, which are converted to OhlcRecord on load
+pub trait RawRecord {
+	fn get_symbol(&self) -> &str;
+	fn get_time(&self) -> NaiveDateTime;
+	fn to_archive(&self) -> OhlcRecord;
+}
+
 pub struct OhlcArchive {
 	pub daily: OhlcData,
+	// Finest intraday resolution available, used by backtests with intraday time frames
 	pub intraday: OhlcData,
-	pub intraday_time_frame: u16
+	// Zero if the archive contains no intraday data
+	pub intraday_time_frame: u16,
+	// Coarser intraday resolutions, ordered by time frame
+	pub additional_intraday: Vec<IntradaySeries>,
+	pub bars: Vec<BarSeries>
+}
+
+pub struct IntradaySeries {
+	pub time_frame: u16,
+	pub data: OhlcData
+}
+
+pub struct BarSeries {
+	pub specification: BarSpecification,
+	pub data: OhlcData
 }
 
 /*
@@ -74,29 +153,95 @@ pub struct OhlcRecord {
 	pub open_interest: Option<u32>
 }
 
+impl TimeFrame {
+	// Period between two executions of the strategy, None if it is executed on every step of the backtest
+	pub fn get_period(&self) -> Option<ResamplePeriod> {
+		match self {
+			TimeFrame::Daily | TimeFrame::Intraday => None,
+			TimeFrame::Weekly => Some(ResamplePeriod::new(PeriodUnit::Week, 1)),
+			TimeFrame::Monthly => Some(ResamplePeriod::new(PeriodUnit::Month, 1)),
+			TimeFrame::Days(days) => Some(ResamplePeriod::new(PeriodUnit::Day, *days))
+		}
+	}
+}
+
 impl RawOhlcArchive {
 	pub fn to_archive(&self, skip_front_contract: bool) -> Result<OhlcArchive> {
-		let is_contract = Self::is_contract(&self.daily);
-		let (daily, intraday) = if is_contract {
-			let (daily, offset_map_opt) = Self::get_data(&self.daily, None, skip_front_contract)?;
-			let Some(offset_map) = offset_map_opt else {
+		let intraday_records: Vec<(u16, &[RawOhlcRecord])> = self.intraday
+			.iter()
+			.map(|x| (x.time_frame, x.records.as_slice()))
+			.collect();
+		let bar_records: Vec<(BarSpecification, &[RawOhlcRecord])> = self.bars
+			.iter()
+			.map(|x| (x.specification, x.records.as_slice()))
+			.collect();
+		Self::from_records(&self.daily, &intraday_records, &bar_records, skip_front_contract)
+	}
+
+	// Finest intraday resolution, zero if there is no intraday data
+	pub fn get_intraday_time_frame(&self) -> u16 {
+		self.intraday
+			.iter()
+			.map(|x| x.time_frame)
+			.min()
+			.unwrap_or(0)
+	}
+
+	pub fn get_intraday_record_count(&self) -> usize {
+		self.intraday
+			.iter()
+			.map(|x| x.records.len())
+			.sum()
+	}
+
+	fn from_records<R: RawRecord>(daily_records: &[R], intraday_records: &[(u16, &[R])], bar_records: &[(BarSpecification, &[R])], skip_front_contract: bool) -> Result<OhlcArchive> {
+		let is_contract = Self::is_contract(daily_records);
+		let (daily, offset_map_opt) = Self::get_data(daily_records, None, skip_front_contract)?;
+		let daily_offset_map = if is_contract {
+			let Some(offset_map) = &offset_map_opt else {
 				bail!("Missing offset map");
 			};
 			let Some(daily_adjusted) = &daily.adjusted else {
 				bail!("Missing daily adjusted records");
 			};
-			let daily_offset_map = Some((daily_adjusted, &offset_map));
-			let (intraday, _) = Self::get_data(&self.intraday, daily_offset_map, skip_front_contract)?;
-			(daily, intraday)
+			Some((daily_adjusted, offset_map))
+		} else {
+			None
+		};
+		let mut intraday_series = Vec::new();
+		for (time_frame, records) in intraday_records {
+			let (data, _) = Self::get_data(records, daily_offset_map, skip_front_contract)?;
+			let series = IntradaySeries {
+				time_frame: *time_frame,
+				data
+			};
+			intraday_series.push(series);
+		}
+		let mut bars = Vec::new();
+		for (specification, records) in bar_records {
+			let (mut data, _) = Self::get_data(records, daily_offset_map, skip_front_contract)?;
+			if let (true, Some(contract_map)) = (is_contract, &data.contract_map) {
+				data.unadjusted = Self::get_rolled_data_from_map(contract_map, &daily.unadjusted);
+			}
+			let series = BarSeries {
+				specification: *specification,
+				data
+			};
+			bars.push(series);
+		}
+		intraday_series.sort_by_key(|x| x.time_frame);
+		let (intraday, intraday_time_frame) = if intraday_series.is_empty() {
+			(OhlcData::new(), 0)
 		} else {
-			let (daily, _) = Self::get_data(&self.daily, None, skip_front_contract)?;
-			let (intraday, _) = Self::get_data(&self.intraday, None, skip_front_contract)?;
-			(daily, intraday)
+			let primary = intraday_series.remove(0);
+			(primary.data, primary.time_frame)
 		};
 		let archive = OhlcArchive {
 			daily,
 			intraday,
-			intraday_time_frame: self.intraday_time_frame
+			intraday_time_frame,
+			additional_intraday: intraday_series,
+			bars
 		};
 		Ok(archive)
 	}
@@ -136,10 +281,10 @@ impl RawOhlcArchive {
 		Ok(Some(max.unwrap().clone()))
 	}
 
-	fn is_contract(records: &Vec<RawOhlcRecord>) -> bool {
+	fn is_contract<R: RawRecord>(records: &[R]) -> bool {
 		let mut contracts = HashSet::new();
 		for x in records {
-			contracts.insert(&x.symbol);
+			contracts.insert(x.get_symbol());
 			if contracts.len() >= 2 {
 				return true;
 			}
@@ -147,7 +292,7 @@ impl RawOhlcArchive {
 		false
 	}
 
-	fn get_data(records: &Vec<RawOhlcRecord>, daily_offset_map: Option<(&OhlcMap, &OffsetMap)>, skip_front_contract: bool) -> Result<(OhlcData, Option<OffsetMap>)> {
+	fn get_data<R: RawRecord>(records: &[R], daily_offset_map: Option<(&OhlcMap, &OffsetMap)>, skip_front_contract: bool) -> Result<(OhlcData, Option<OffsetMap>)> {
 		let is_contract = Self::is_contract(records);
 		if is_contract {
 			// Futures contract
@@ -209,10 +354,10 @@ impl RawOhlcArchive {
 		}
 	}
 
-	fn get_unadjusted_data(records: &Vec<RawOhlcRecord>) -> OhlcMap {
+	fn get_unadjusted_data<R: RawRecord>(records: &[R]) -> OhlcMap {
 		let mut output = OhlcMap::new();
 		for x in records {
-			output.insert(x.time, x.to_archive());
+			output.insert(x.get_time(), x.to_archive());
 		}
 		output
 	}
@@ -227,6 +372,24 @@ impl RawOhlcArchive {
 		Ok(output)
 	}
 
+	/*
+	Bars other than time bars of different contracts rarely share timestamps, so the most popular record can't be determined for each point in time.
+	The continuous contract follows the roll-overs of the daily data instead.
+	*/
+	fn get_rolled_data_from_map(map: &OhlcContractMap, daily: &OhlcMap) -> OhlcMap {
+		let mut output = OhlcMap::new();
+		for (time, records) in map {
+			let contract = daily
+				.range(..=time)
+				.next_back()
+				.map(|(_, record)| &record.symbol);
+			if let Some(record) = records.iter().find(|x| Some(&x.symbol) == contract) {
+				output.insert(*time, record.clone());
+			}
+		}
+		output
+	}
+
 	fn get_adjusted_data_from_map(map: &OhlcContractMap, skip_front_contract: bool) -> Result<Option<(OhlcMap, OffsetMap)>> {
 		let Some(mut panama) = PanamaCanal::new(map, skip_front_contract)? else {
 			return Ok(None);
@@ -235,23 +398,52 @@ impl RawOhlcArchive {
 		Ok(Some(output))
 	}
 
-	fn get_contract_map(records: &Vec<RawOhlcRecord>) -> OhlcContractMap {
+	fn get_contract_map<R: RawRecord>(records: &[R]) -> OhlcContractMap {
 		let mut map = OhlcContractMap::new();
 		records.iter().for_each(|x| {
 			let record = x.to_archive();
-			if let Some(records) = map.get_mut(&x.time) {
+			if let Some(records) = map.get_mut(&record.time) {
 				records.push(record);
 			} else {
+				let time = record.time;
 				let records = vec![record];
-				map.insert(x.time, records);
+				map.insert(time, records);
 			}
 		});
 		map
 	}
 }
 
-impl RawOhlcRecord {
-	pub fn to_archive(&self) -> OhlcRecord {
+impl ArchivedRawOhlcArchive {
+	// Builds the maps straight from the archived records without deserializing them first
+	pub fn to_archive(&self, skip_front_contract: bool) -> Result<OhlcArchive> {
+		let bar_records: Vec<(BarSpecification, &[ArchivedRawOhlcRecord])> = self.bars
+			.iter()
+			.map(|x| {
+				let specification = x.specification
+					.deserialize(&mut Infallible)
+					.unwrap_or_else(|error| match error {});
+				(specification, x.records.as_slice())
+			})
+			.collect();
+		let intraday_records: Vec<(u16, &[ArchivedRawOhlcRecord])> = self.intraday
+			.iter()
+			.map(|x| (x.time_frame, x.records.as_slice()))
+			.collect();
+		RawOhlcArchive::from_records(self.daily.as_slice(), &intraday_records, &bar_records, skip_front_contract)
+	}
+}
+
+impl RawRecord for RawOhlcRecord {
+	fn get_symbol(&self) -> &str {
+		self.symbol.as_str()
+	}
+
+	fn get_time(&self) -> NaiveDateTime {
+		self.time
+	}
+
+	fn to_archive(&self) -> OhlcRecord {
 		OhlcRecord {
 			symbol: self.symbol.clone(),
 			time: self.time,
@@ -265,6 +457,31 @@ impl RawOhlcRecord {
 	}
 }
 
+impl RawRecord for ArchivedRawOhlcRecord {
+	fn get_symbol(&self) -> &str {
+		self.symbol.as_str()
+	}
+
+	fn get_time(&self) -> NaiveDateTime {
+		self.time
+			.deserialize(&mut Infallible)
+			.unwrap_or_else(|error| match error {})
+	}
+
+	fn to_archive(&self) -> OhlcRecord {
+		OhlcRecord {
+			symbol: self.symbol.to_string(),
+			time: self.get_time(),
+			open: self.open,
+			high: self.high,
+			low: self.low,
+			close: self.close,
+			volume: self.volume,
+			open_interest: self.open_interest.as_ref().copied()
+		}
+	}
+}
+
 impl OhlcRecord {
 	pub fn apply_offset(&self, offset: f64) -> OhlcRecord {
 		OhlcRecord {
@@ -282,15 +499,99 @@ impl OhlcRecord {
 
 impl OhlcArchive {
 	pub fn get_data(&self, time_frame: &TimeFrame) -> &OhlcData {
-		if *time_frame == TimeFrame::Daily {
-			&self.daily
-		} else {
+		if *time_frame == TimeFrame::Intraday {
 			&self.intraday
+		} else {
+			&self.daily
 		}
 	}
+
+	// All intraday resolutions along with their time frames, finest first
+	pub fn get_intraday_resolutions(&self) -> Vec<(u16, &OhlcData)> {
+		let primary = Some((self.intraday_time_frame, &self.intraday))
+			.filter(|(time_frame, _)| *time_frame > 0);
+		primary
+			.into_iter()
+			.chain(self.additional_intraday.iter().map(|x| (x.time_frame, &x.data)))
+			.collect()
+	}
+
+	/*
+	Selects the intraday resolution that records of the specified time frame are built from, starting at "from".
+	Only resolutions that evenly divide the time frame are considered. The coarsest one whose records reach back far enough is preferred,
+	since finer resolutions frequently cover shorter periods. Otherwise the one with the earliest records is used.
+	*/
+	pub fn get_intraday_source(&self, time_frame: u16, from: &NaiveDateTime) -> Option<(u16, &OhlcData)> {
+		let candidates: Vec<(u16, &OhlcData, NaiveDateTime)> = self.get_intraday_resolutions()
+			.into_iter()
+			.filter(|(resolution, _)| time_frame.is_multiple_of(*resolution))
+			.filter_map(|(resolution, data)| {
+				let first = data.unadjusted.first_key_value()?;
+				Some((resolution, data, *first.0))
+			})
+			.collect();
+		let covering = candidates
+			.iter()
+			.rev()
+			.find(|(_, _, first)| first <= from);
+		let earliest = candidates
+			.iter()
+			.min_by_key(|(resolution, _, first)| (*first, Reverse(*resolution)));
+		covering
+			.or(earliest)
+			.map(|(resolution, data, _)| (*resolution, *data))
+	}
+
+	pub fn get_bars(&self, specification: &BarSpecification) -> Option<&OhlcData> {
+		self.bars
+			.iter()
+			.find(|x| x.specification == *specification)
+			.map(|x| &x.data)
+	}
+
+	// Rough estimate of the memory occupied by the records, excluding the internal overhead of the maps
+	pub fn get_memory_usage(&self) -> usize {
+		let additional_intraday: usize = self.additional_intraday
+			.iter()
+			.map(|x| x.data.get_memory_usage())
+			.sum();
+		let bars: usize = self.bars
+			.iter()
+			.map(|x| x.data.get_memory_usage())
+			.sum();
+		self.daily.get_memory_usage() + self.intraday.get_memory_usage() + additional_intraday + bars
+	}
 }
 
 impl OhlcData {
+	fn new() -> OhlcData {
+		OhlcData {
+			unadjusted: OhlcMap::new(),
+			adjusted: None,
+			contract_map: None
+		}
+	}
+
+	fn get_memory_usage(&self) -> usize {
+		let get_record_size = |record: &OhlcRecord| size_of::<NaiveDateTime>() + size_of::<OhlcRecord>() + record.symbol.len();
+		let get_map_size = |map: &OhlcMap| -> usize {
+			map.values()
+				.map(get_record_size)
+				.sum()
+		};
+		let unadjusted = get_map_size(&self.unadjusted);
+		let adjusted = self.adjusted
+			.as_ref()
+			.map_or(0, get_map_size);
+		let contracts: usize = self.contract_map
+			.iter()
+			.flat_map(|x| x.values())
+			.flatten()
+			.map(get_record_size)
+			.sum();
+		unadjusted + adjusted + contracts
+	}
+
 	pub fn get_adjusted_fallback(&self) -> &OhlcMap {
 		match &self.adjusted {
 			Some(ref x) => x,
@@ -0,0 +1,255 @@
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::ohlc::{OhlcMap, OhlcRecord};
+
+const MINUTES_PER_HOUR: u32 = 60;
+const MINUTES_PER_DAY: u16 = 1440;
+// Intraday records separated by an idle period of at least this many minutes belong to different sessions
+const SESSION_GAP_MINUTES: i64 = 60;
+
+/*
+Size of the buckets that records are aggregated into, e.g.:
+
+{"unit": "minute", "count": 15}
+{"unit": "week", "count": 1}
+
+Minute and hour buckets require intraday records. Day, week and month buckets can be built from daily or intraday records.
+*/
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ResamplePeriod {
+	pub unit: PeriodUnit,
+	pub count: u16
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum PeriodUnit {
+	Minute,
+	Hour,
+	// Trading days, i.e. calendar dates with clock alignment and sessions with session alignment
+	Day,
+	// Calendar weeks starting on Monday
+	Week,
+	// Calendar months
+	Month
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum Alignment {
+	// Bars start at multiples of the time frame since midnight, e.g. 09:00, 10:00 and 11:00 for H1
+	#[default]
+	Clock,
+	// Bars start at multiples of the time frame since the first record of each session, e.g. 09:30, 10:30 and 11:30 for H1
+	Session
+}
+
+/*
+Aggregates records sorted by time into buckets of a fixed period:
+- open: open of the first record
+- high/low: extremes of all records
+- close: close of the last record
+- volume: sum of all records
+- open_interest: most recent value, since it is a level rather than a flow
+Buckets are labeled with their start and buckets without any records are omitted.
+The last bucket may be incomplete, e.g. the current week in the middle of the week.
+*/
+pub struct Resampler {
+	period: ResamplePeriod,
+	alignment: Alignment,
+	// Time frame of the source records in minutes, 1440 for daily records
+	source_time_frame: u16,
+	session_start: NaiveDateTime,
+	previous_time: Option<NaiveDateTime>,
+	// Start of the current trading day and the number of trading days so far, for day buckets
+	trading_day: Option<NaiveDateTime>,
+	trading_days: u32,
+	day_bucket: NaiveDateTime
+}
+
+impl ResamplePeriod {
+	pub fn new(unit: PeriodUnit, count: u16) -> ResamplePeriod {
+		ResamplePeriod {
+			unit,
+			count
+		}
+	}
+
+	pub fn minutes(count: u16) -> ResamplePeriod {
+		Self::new(PeriodUnit::Minute, count)
+	}
+
+	// Length in minutes for minute and hour periods, None for the calendar-based units
+	pub fn get_minutes(&self) -> Option<u32> {
+		match self.unit {
+			PeriodUnit::Minute => Some(self.count as u32),
+			PeriodUnit::Hour => Some(self.count as u32 * MINUTES_PER_HOUR),
+			_ => None
+		}
+	}
+
+	pub fn is_intraday(&self) -> bool {
+		self.get_minutes().is_some()
+	}
+
+	/*
+	End of the bucket starting at the specified time, i.e. the point in time at which it is complete.
+	None if it depends on the data, which is the case for sessions and multiple trading days.
+	*/
+	pub fn get_end(&self, start: NaiveDateTime, alignment: Alignment) -> Option<NaiveDateTime> {
+		let count = self.count.max(1);
+		match (self.get_minutes(), self.unit) {
+			(Some(minutes), _) => Some(start + Duration::minutes(minutes.max(1) as i64)),
+			(None, PeriodUnit::Day) if count == 1 && alignment == Alignment::Clock => Some(start + Duration::days(1)),
+			(None, PeriodUnit::Week) => Some(start + Duration::weeks(count as i64)),
+			(None, PeriodUnit::Month) => start.checked_add_months(Months::new(count as u32)),
+			_ => None
+		}
+	}
+
+	// Start of the week or month bucket containing the specified time, None for the other units
+	pub fn get_calendar_start(&self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+		let count = self.count.max(1);
+		let start = match self.unit {
+			PeriodUnit::Week => {
+				// 1970-01-05 was a Monday
+				let first_monday = NaiveDate::from_ymd_opt(1970, 1, 5).unwrap();
+				let weeks = (time.date() - first_monday).num_days().div_euclid(7);
+				first_monday + Duration::weeks(weeks - weeks.rem_euclid(count as i64))
+			},
+			PeriodUnit::Month => {
+				let months = time.year() * 12 + time.month0() as i32;
+				let start = months - months.rem_euclid(count as i32);
+				NaiveDate::from_ymd_opt(start.div_euclid(12), start.rem_euclid(12) as u32 + 1, 1).unwrap()
+			},
+			_ => return None
+		};
+		Some(start.and_time(NaiveTime::MIN))
+	}
+}
+
+impl Resampler {
+	pub fn new(period: ResamplePeriod, alignment: Alignment, source_time_frame: u16) -> Resampler {
+		Resampler {
+			period,
+			alignment,
+			source_time_frame,
+			session_start: NaiveDateTime::MIN,
+			previous_time: None,
+			trading_day: None,
+			trading_days: 0,
+			day_bucket: NaiveDateTime::MIN
+		}
+	}
+
+	pub fn resample<'a, I>(mut self, records: I) -> Vec<OhlcRecord>
+	where
+		I: IntoIterator<Item = &'a OhlcRecord>
+	{
+		let mut output = Vec::new();
+		let mut bucket: Option<(NaiveDateTime, Vec<&OhlcRecord>)> = None;
+		for record in records {
+			let bucket_time = self.get_bucket_time(record.time);
+			match bucket.as_mut() {
+				Some((time, bucket_records)) if *time == bucket_time => bucket_records.push(record),
+				_ => {
+					if let Some((time, bucket_records)) = bucket.take() {
+						output.push(merge_records(time, &bucket_records));
+					}
+					bucket = Some((bucket_time, vec![record]));
+				}
+			}
+		}
+		if let Some((time, bucket_records)) = bucket {
+			output.push(merge_records(time, &bucket_records));
+		}
+		output
+	}
+
+	pub fn resample_map(self, source: &OhlcMap) -> OhlcMap {
+		self.resample(source.values())
+			.into_iter()
+			.map(|x| (x.time, x))
+			.collect()
+	}
+
+	fn get_bucket_time(&mut self, time: NaiveDateTime) -> NaiveDateTime {
+		let is_new_session = match self.previous_time {
+			Some(previous_time) => starts_session(previous_time, time, self.source_time_frame),
+			None => true
+		};
+		if is_new_session {
+			self.session_start = time;
+		}
+		self.previous_time = Some(time);
+		let count = self.period.count.max(1);
+		let midnight = time.date().and_time(NaiveTime::MIN);
+		match self.period.get_minutes() {
+			Some(minutes) => {
+				let (origin, offset) = match self.alignment {
+					Alignment::Clock => (midnight, (time - midnight).num_minutes()),
+					Alignment::Session => (self.session_start, (time - self.session_start).num_minutes())
+				};
+				let minutes = minutes.max(1) as i64;
+				origin + Duration::minutes(offset - offset % minutes)
+			},
+			None => match self.period.unit {
+				PeriodUnit::Day => {
+					let trading_day = match self.alignment {
+						Alignment::Clock => midnight,
+						Alignment::Session => self.session_start
+					};
+					if self.trading_day != Some(trading_day) {
+						if self.trading_days.is_multiple_of(count as u32) {
+							self.day_bucket = trading_day;
+						}
+						self.trading_day = Some(trading_day);
+						self.trading_days += 1;
+					}
+					self.day_bucket
+				},
+				_ => self.period.get_calendar_start(time).unwrap()
+			}
+		}
+	}
+}
+
+/*
+Indicates whether a record starts a new session, based on the idle period between the end of the previous record and the current one.
+The time frame of the records is specified in minutes and every daily record is a session of its own.
+*/
+pub fn starts_session(previous_time: NaiveDateTime, time: NaiveDateTime, time_frame: u16) -> bool {
+	if time_frame >= MINUTES_PER_DAY {
+		return true;
+	}
+	(time - previous_time).num_minutes() - time_frame as i64 >= SESSION_GAP_MINUTES
+}
+
+pub fn merge_records(time: NaiveDateTime, records: &[&OhlcRecord]) -> OhlcRecord {
+	let first = records.first().unwrap();
+	let last = records.last().unwrap();
+	let high = records
+		.iter()
+		.map(|x| x.high)
+		.fold(f64::MIN, f64::max);
+	let low = records
+		.iter()
+		.map(|x| x.low)
+		.fold(f64::MAX, f64::min);
+	let volume = records
+		.iter()
+		.fold(0u32, |sum, x| sum.saturating_add(x.volume));
+	// Adjusted series switch contracts, the bucket belongs to the contract of its most recent record
+	OhlcRecord {
+		symbol: last.symbol.clone(),
+		time,
+		open: first.open,
+		high,
+		low,
+		close: last.close,
+		volume,
+		open_interest: last.open_interest
+	}
+}
@@ -60,4 +60,134 @@ where
 	};
 	let standard_deviation = (delta_sum / (divisor as f64)).sqrt();
 	Ok(standard_deviation)
+}
+
+pub fn skewness<'a, I>(samples: I) -> Result<f64>
+where
+	I: Iterator<Item = &'a f64> + Clone
+{
+	let (n, mean, standard_deviation) = get_moment_parameters(samples.clone())?;
+	let sum: f64 = samples
+		.map(|x| ((x - mean) / standard_deviation).powi(3))
+		.sum();
+	let skewness = sum / n;
+	Ok(skewness)
+}
+
+// Excess kurtosis, i.e. 0.0 for a normal distribution
+pub fn kurtosis<'a, I>(samples: I) -> Result<f64>
+where
+	I: Iterator<Item = &'a f64> + Clone
+{
+	let (n, mean, standard_deviation) = get_moment_parameters(samples.clone())?;
+	let sum: f64 = samples
+		.map(|x| ((x - mean) / standard_deviation).powi(4))
+		.sum();
+	let kurtosis = sum / n - 3.0;
+	Ok(kurtosis)
+}
+
+// Linear interpolation between the two closest ranks, p ranges from 0.0 to 1.0
+pub fn quantile<'a, I>(samples: I, p: f64) -> Result<f64>
+where
+	I: Iterator<Item = &'a f64>
+{
+	if !(0.0..=1.0).contains(&p) {
+		bail!("Invalid quantile ({p})");
+	}
+	let mut sorted: Vec<f64> = samples
+		.cloned()
+		.collect();
+	if sorted.is_empty() {
+		bail!("Not enough samples to calculate quantile");
+	}
+	sorted.sort_by(|x, y| x.total_cmp(y));
+	let position = p * ((sorted.len() - 1) as f64);
+	let lower = position.floor() as usize;
+	let upper = position.ceil() as usize;
+	let weight = position - (lower as f64);
+	let quantile = sorted[lower] + weight * (sorted[upper] - sorted[lower]);
+	Ok(quantile)
+}
+
+pub fn covariance(pairs: &[(f64, f64)]) -> Result<f64> {
+	let n = pairs.len();
+	if n < 2 {
+		bail!("Not enough samples to calculate covariance");
+	}
+	let x_mean = mean(pairs.iter().map(|(x, _)| x))?;
+	let y_mean = mean(pairs.iter().map(|(_, y)| y))?;
+	let sum: f64 = pairs
+		.iter()
+		.map(|(x, y)| (x - x_mean) * (y - y_mean))
+		.sum();
+	let covariance = sum / ((n - 1) as f64);
+	Ok(covariance)
+}
+
+fn get_moment_parameters<'a, I>(samples: I) -> Result<(f64, f64, f64)>
+where
+	I: Iterator<Item = &'a f64> + Clone
+{
+	let n = samples.clone().count();
+	let mean = mean(samples.clone())?;
+	let standard_deviation = standard_deviation_mean_biased(samples, mean)?;
+	if standard_deviation == 0.0 {
+		bail!("Unable to calculate moments of samples without variance");
+	}
+	Ok((n as f64, mean, standard_deviation))
+}
+
+/*
+Sharpe ratio of per-period returns with a per-period risk-free rate, e.g. daily returns and daily T-bill yields.
+The result is not annualized, see annualize_ratio.
+*/
+pub fn sharpe_ratio(returns: &[f64], risk_free_rates: &[f64]) -> Result<f64> {
+	let excess_returns = get_excess_returns(returns, risk_free_rates)?;
+	let mean = mean(excess_returns.iter())?;
+	let standard_deviation = standard_deviation_mean(excess_returns.iter(), mean)?;
+	Ok(mean / standard_deviation)
+}
+
+/*
+Downside deviation relative to a per-period target return (minimum acceptable return).
+Returns above the target count as zero deviation rather than being discarded, so the divisor is the total number of periods.
+*/
+pub fn downside_deviation(returns: &[f64], targets: &[f64]) -> Result<f64> {
+	let excess_returns = get_excess_returns(returns, targets)?;
+	let squared_shortfalls: Vec<f64> = excess_returns
+		.iter()
+		.map(|x| x.min(0.0).powi(2))
+		.collect();
+	let downside_deviation = mean(squared_shortfalls.iter())?.sqrt();
+	Ok(downside_deviation)
+}
+
+// Sortino ratio based on target downside deviation, not annualized
+pub fn sortino_ratio(returns: &[f64], targets: &[f64]) -> Result<f64> {
+	let excess_returns = get_excess_returns(returns, targets)?;
+	let mean = mean(excess_returns.iter())?;
+	let downside_deviation = downside_deviation(returns, targets)?;
+	Ok(mean / downside_deviation)
+}
+
+pub fn annualize_ratio(ratio: f64, periods_per_year: f64) -> f64 {
+	periods_per_year.sqrt() * ratio
+}
+
+// Converts an annual rate such as 0.05 to the equivalent compounded rate for a single period
+pub fn get_periodic_rate(annual_rate: f64, periods_per_year: f64) -> f64 {
+	(annual_rate + 1.0).powf(1.0 / periods_per_year) - 1.0
+}
+
+fn get_excess_returns(returns: &[f64], targets: &[f64]) -> Result<Vec<f64>> {
+	if returns.len() != targets.len() {
+		bail!("The number of returns and targets must be identical");
+	}
+	let excess_returns = returns
+		.iter()
+		.zip(targets.iter())
+		.map(|(x, target)| x - target)
+		.collect();
+	Ok(excess_returns)
 }
\ No newline at end of file
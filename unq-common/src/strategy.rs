@@ -3,6 +3,8 @@ use std::collections::VecDeque;
 use std::fmt::{Display, Formatter};
 use anyhow::{Result, bail};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::backtest::{Fill, Rollover};
 use crate::web::WebF64;
 
 #[derive(PartialEq, Debug)]
@@ -18,8 +20,62 @@ pub enum StrategyParameterType {
 type StrategyParameterSelect<'a, T> = &'a dyn Fn(&StrategyParameter) -> Option<T>;
 type StrategyParameterValueSelect<'a, T> = (StrategyParameterType, &'a dyn Fn(&StrategyParameter) -> Vec<T>);
 
+/*
+Strategies are driven by the backtest runner, which calls "next" on every step of the backtest
+(or on the last trading day of each period with weekly, monthly and N-day time frames).
+The lifecycle hooks are optional and do nothing by default. Within a step they are called in the following order:
+- on_margin_call, on_day_start, on_session_start, on_fill and on_rollover for the events that occurred while advancing to the step
+- next
+- on_session_close on the last step of each session
+- on_month_end on the last step of the last session of each month
+- on_fill for the positions opened and closed by the strategy during the step
+Sessions are separated by idle periods like in the resampler so that Globex evening sessions aren't split at midnight.
+on_day_start is called on the first step of each trading day, which is the date on which its session closes.
+With daily, weekly, monthly and N-day time frames every trading day is a session of its own.
+*/
 pub trait Strategy {
 	fn next(&mut self) -> Result<()>;
+
+	// Called once before the first step of the backtest
+	fn on_start(&mut self) -> Result<()> {
+		Ok(())
+	}
+
+	fn on_day_start(&mut self) -> Result<()> {
+		Ok(())
+	}
+
+	fn on_session_start(&mut self) -> Result<()> {
+		Ok(())
+	}
+
+	fn on_session_close(&mut self) -> Result<()> {
+		Ok(())
+	}
+
+	fn on_month_end(&mut self) -> Result<()> {
+		Ok(())
+	}
+
+	// Called after a position has automatically been rolled over to a new contract
+	fn on_rollover(&mut self, _rollover: &Rollover) -> Result<()> {
+		Ok(())
+	}
+
+	// Called for every position that has been opened or (partially) closed, including automatic ones
+	fn on_fill(&mut self, _fill: &Fill) -> Result<()> {
+		Ok(())
+	}
+
+	// Called when positions had to be liquidated because the overnight margin exceeded the account value
+	fn on_margin_call(&mut self) -> Result<()> {
+		Ok(())
+	}
+
+	// Called once after the final step of the backtest, when all positions have been closed
+	fn on_finish(&mut self) -> Result<()> {
+		Ok(())
+	}
 }
 
 #[derive(Debug)]
@@ -65,7 +121,7 @@ Strategy parameters specified in the "backtest" command.
 - increment: None
 - values: Some({1.2, 3.4, 4.5})
 */
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct StrategyParameter {
 	pub name: String,
@@ -78,7 +134,8 @@ pub struct StrategyParameter {
 	pub string_values: Option<Vec<String>>
 }
 
-#[derive(Serialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[schema(value_type = Vec<StrategyParameter>)]
 pub struct StrategyParameters(VecDeque<StrategyParameter>);
 
 impl StrategyParameter {
@@ -95,6 +152,21 @@ impl StrategyParameter {
 		}
 	}
 
+	// The value of the parameter in the notation of the prompt language, without the name
+	pub fn get_value_string(&self) -> String {
+		let join = |values: Vec<String>| format!("[{}]", values.join(", "));
+		match (&self.value, &self.limit, &self.increment, &self.values, self.bool_value, &self.string_value, &self.string_values) {
+			(Some(value), Some(limit), Some(increment), _, _, _, _) => format!("{} to {} step {}", value.get(), limit.get(), increment.get()),
+			(Some(value), Some(limit), None, _, _, _, _) => format!("{} to {}", value.get(), limit.get()),
+			(Some(value), None, _, _, _, _, _) => value.get().to_string(),
+			(_, _, _, Some(values), _, _, _) => join(values.iter().map(|x| x.get().to_string()).collect()),
+			(_, _, _, _, Some(bool_value), _, _) => bool_value.to_string(),
+			(_, _, _, _, _, Some(string_value), _) => format!("\"{string_value}\""),
+			(_, _, _, _, _, _, Some(string_values)) => join(string_values.iter().map(|x| format!("\"{x}\"")).collect()),
+			_ => "?".to_string()
+		}
+	}
+
 	pub fn get_type(&self) -> Result<StrategyParameterType> {
 		let tuple = (
 			self.value.is_some(),
@@ -221,4 +293,21 @@ impl StrategyParameters {
 	pub fn iter(&self) -> Iter<StrategyParameter> {
 		self.0.iter()
 	}
+}
+
+impl Display for StrategyParameter {
+	// Uses the notation of the Unquantified prompt language, e.g. "period: 10 to 50 step 10"
+	fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(formatter, "{}: {}", self.name, self.get_value_string())
+	}
+}
+
+impl Display for StrategyParameters {
+	fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+		let parameters: Vec<String> = self.0
+			.iter()
+			.map(|x| x.to_string())
+			.collect();
+		formatter.write_str(parameters.join(", ").as_str())
+	}
 }
\ No newline at end of file
@@ -1,23 +1,84 @@
+use std::cell::Cell;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use anyhow::{bail, Error};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use utoipa::{PartialSchema, ToSchema};
+use utoipa::openapi::{KnownFormat, ObjectBuilder, RefOr, Schema, SchemaFormat, Type};
 
+// Significant digits are limited to the number of digits a f64 can represent
+const MAX_SIGNIFICANT_DIGITS: u8 = 17;
+
+thread_local! {
+	// Serialization is synchronous so the precision only needs to be set on the thread performing it
+	static PRECISION: Cell<Precision> = const { Cell::new(Precision::Compact) };
+	// Set while results are being written to disk, see with_storage_format
+	static STORAGE_FORMAT: Cell<bool> = const { Cell::new(false) };
+}
+
+/*
+Float wrapper for values sent to clients.
+By default values are rounded to a fixed number of decimals to keep responses compact.
+The rounding can be overridden for all values serialized within with_precision.
+*/
 #[derive(Clone, Debug)]
 pub struct WebF64 {
 	value: f64,
-	precision: i32
+	// Number of decimals in compact mode, None if the value is never rounded to decimals
+	precision: Option<i32>
+}
+
+/*
+Controls how WebF64 values are serialized:
+- "compact": the default, rounds values to 2 or 3 decimals
+- "full": no rounding at all
+- "1" to "17": rounds values to the specified number of significant digits, e.g. 0.0067 rather than 0.01 with "2"
+*/
+#[derive(Clone, Copy, PartialEq, Debug, Default, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Precision {
+	#[default]
+	Compact,
+	Full,
+	SignificantDigits(u8)
+}
+
+/*
+Representations accepted when deserializing WebF64 values:
+- plain numbers sent by clients, which are rounded to 2 decimals like WebF64::new
+- objects written by with_storage_format, which retain the precision of the original value
+NaN and infinite values are serialized as null by serde_json so null is read back as NaN.
+*/
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum WebF64Representation {
+	Value(Option<f64>),
+	Stored {
+		value: Option<f64>,
+		precision: Option<i32>
+	}
 }
 
 impl WebF64 {
 	pub fn new(value: f64) -> WebF64 {
 		WebF64 {
 			value,
-			precision: 2
+			precision: Some(2)
 		}
 	}
 
 	pub fn precise(value: f64) -> WebF64 {
 		WebF64 {
 			value,
-			precision: 3
+			precision: Some(3)
+		}
+	}
+
+	// Values such as correlation coefficients that are only rounded if significant digits are requested
+	pub fn exact(value: f64) -> WebF64 {
+		WebF64 {
+			value,
+			precision: None
 		}
 	}
 
@@ -40,8 +101,18 @@ impl Serialize for WebF64 {
 	where
 		S: Serializer,
 	{
-		let factor = 10f64.powi(self.precision);
-		let rounded = (self.value * factor).round() / factor;
+		if STORAGE_FORMAT.get() {
+			let stored = WebF64Representation::Stored {
+				value: Some(self.value),
+				precision: self.precision
+			};
+			return stored.serialize(serializer);
+		}
+		let rounded = match (PRECISION.get(), self.precision) {
+			(Precision::Compact, Some(precision)) => round_decimals(self.value, precision),
+			(Precision::SignificantDigits(digits), _) => round_significant_digits(self.value, digits),
+			_ => self.value
+		};
 		serializer.serialize_f64(rounded)
 	}
 }
@@ -51,11 +122,113 @@ impl<'de> Deserialize<'de> for WebF64 {
 	where
 		D: Deserializer<'de>,
 	{
-		let value = f64::deserialize(deserializer)?;
-		let output = WebF64 {
-			value,
-			precision: 2
+		let output = match WebF64Representation::deserialize(deserializer)? {
+			WebF64Representation::Value(value) => WebF64 {
+				value: value.unwrap_or(f64::NAN),
+				precision: Some(2)
+			},
+			WebF64Representation::Stored { value, precision } => WebF64 {
+				value: value.unwrap_or(f64::NAN),
+				precision
+			}
 		};
 		Ok(output)
 	}
+}
+
+// Rounding is applied during serialization so the schema is a plain double
+impl PartialSchema for WebF64 {
+	fn schema() -> RefOr<Schema> {
+		ObjectBuilder::new()
+			.schema_type(Type::Number)
+			.format(Some(SchemaFormat::KnownFormat(KnownFormat::Double)))
+			.into()
+	}
+}
+
+impl ToSchema for WebF64 {}
+
+impl FromStr for Precision {
+	type Err = Error;
+
+	fn from_str(string: &str) -> Result<Self, Self::Err> {
+		match string {
+			"compact" => Ok(Precision::Compact),
+			"full" => Ok(Precision::Full),
+			_ => {
+				let Ok(digits) = string.parse::<u8>() else {
+					bail!("Invalid precision \"{string}\", must be \"compact\", \"full\" or a number of significant digits");
+				};
+				if digits == 0 || digits > MAX_SIGNIFICANT_DIGITS {
+					bail!("Number of significant digits must be between 1 and {MAX_SIGNIFICANT_DIGITS}");
+				}
+				Ok(Precision::SignificantDigits(digits))
+			}
+		}
+	}
+}
+
+impl TryFrom<String> for Precision {
+	type Error = Error;
+
+	fn try_from(string: String) -> Result<Self, Self::Error> {
+		string.parse()
+	}
+}
+
+// Inverse of FromStr, used to pass the precision on to the server in query strings
+impl Display for Precision {
+	fn fmt(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Precision::Compact => write!(formatter, "compact"),
+			Precision::Full => write!(formatter, "full"),
+			Precision::SignificantDigits(digits) => write!(formatter, "{digits}")
+		}
+	}
+}
+
+// Runs a function, typically serialization, with the specified precision applied to all WebF64 values
+pub fn with_precision<T>(precision: Precision, function: impl FnOnce() -> T) -> T {
+	let previous = PRECISION.replace(precision);
+	let output = function();
+	PRECISION.set(previous);
+	output
+}
+
+/*
+Runs a function with WebF64 values serialized as objects that include their precision.
+Used for results written to disk so that they are rounded the same way after being loaded again.
+*/
+pub fn with_storage_format<T>(function: impl FnOnce() -> T) -> T {
+	let previous = STORAGE_FORMAT.replace(true);
+	let output = function();
+	STORAGE_FORMAT.set(previous);
+	output
+}
+
+// Serializes a field with Precision::Full regardless of the precision requested by the client
+pub fn serialize_full_precision<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+	T: Serialize,
+	S: Serializer
+{
+	with_precision(Precision::Full, || value.serialize(serializer))
+}
+
+fn round_decimals(value: f64, decimals: i32) -> f64 {
+	let factor = 10f64.powi(decimals);
+	(value * factor).round() / factor
+}
+
+fn round_significant_digits(value: f64, digits: u8) -> f64 {
+	if value == 0.0 || !value.is_finite() {
+		return value;
+	}
+	let magnitude = value.abs().log10().floor() as i32;
+	let factor = 10f64.powi(digits as i32 - 1 - magnitude);
+	if factor.is_finite() && factor != 0.0 {
+		(value * factor).round() / factor
+	} else {
+		value
+	}
 }
\ No newline at end of file
@@ -0,0 +1,291 @@
+use std::path::Path;
+use anyhow::{anyhow, bail, Context, Result};
+use configparser::ini::Ini;
+use csv::{ReaderBuilder, StringRecord, Trim};
+use unq_common::PathDisplay;
+use crate::input::{get_file_symbol, parse_time, InputFormat, InputRecord, InputResult};
+
+/*
+Delimited text files with a configurable column mapping, e.g.:
+
+[6e]
+input_format = generic
+delimiter = semicolon
+has_headers = true
+columns = symbol=Ticker, time=Date, open=Open, high=High, low=Low, close=Close, volume=Volume, open_interest=OI
+time_formats = %d.%m.%Y %H:%M|%d.%m.%Y
+
+The delimiter is either "comma" (default), "semicolon", "tab", "pipe" or a single character.
+Columns are identified by their header or by their zero-based index if the files have no headers.
+The time, open, high, low and close columns are mandatory. Without a symbol column the symbol is derived from the file name.
+The vendor layouts (Barchart, Norgate/CSI) are presets of this format.
+*/
+pub struct GenericFormat {
+	delimiter: u8,
+	has_headers: bool,
+	columns: ColumnMapping<String>,
+	time_formats: Vec<String>,
+	// Rows whose first field starts with this prefix are skipped, e.g. the footer of Barchart downloads
+	ignore_prefix: Option<String>,
+	default_patterns: (&'static str, Option<&'static str>)
+}
+
+// Column names or indices, also used by ParquetFormat
+#[derive(Clone)]
+pub struct ColumnMapping<T> {
+	pub symbol: Option<T>,
+	pub time: T,
+	pub open: T,
+	pub high: T,
+	pub low: T,
+	pub close: T,
+	pub volume: Option<T>,
+	pub open_interest: Option<T>
+}
+
+impl GenericFormat {
+	pub fn from_ini(ini: &Ini, section: &str) -> Result<GenericFormat> {
+		// Semicolons can't be specified directly since configparser treats them as the start of a comment
+		let delimiter = match ini.get(section, "delimiter").as_deref() {
+			Some("comma") | None => b',',
+			Some("semicolon") => b';',
+			Some("tab") => b'\t',
+			Some("pipe") => b'|',
+			Some(delimiter) if delimiter.len() == 1 => delimiter.as_bytes()[0],
+			Some(delimiter) => bail!("Invalid delimiter \"{delimiter}\" in section \"{section}\"")
+		};
+		let has_headers = match ini.get(section, "has_headers") {
+			Some(value) => value.parse::<bool>()
+				.with_context(|| anyhow!("Invalid value for \"has_headers\" in section \"{section}\""))?,
+			None => true
+		};
+		let Some(columns_string) = ini.get(section, "columns") else {
+			bail!("Missing column mapping \"columns\" in section \"{section}\"");
+		};
+		let columns = ColumnMapping::parse(&columns_string)
+			.with_context(|| anyhow!("Invalid column mapping in section \"{section}\""))?;
+		let time_formats = match ini.get(section, "time_formats") {
+			Some(time_formats) => time_formats
+				.split('|')
+				.map(|x| x.trim().to_string())
+				.collect(),
+			None => vec!["%Y-%m-%d %H:%M".to_string(), "%Y-%m-%d".to_string()]
+		};
+		let format = GenericFormat {
+			delimiter,
+			has_headers,
+			columns,
+			time_formats,
+			ignore_prefix: None,
+			default_patterns: (r"(?i)\.(csv|txt)$", None)
+		};
+		Ok(format)
+	}
+
+	// Barchart.com downloads, daily files contain an "Open Int" column
+	pub fn barchart() -> GenericFormat {
+		let columns = ColumnMapping {
+			symbol: None,
+			time: "Time".to_string(),
+			open: "Open".to_string(),
+			high: "High".to_string(),
+			low: "Low".to_string(),
+			close: "Last".to_string(),
+			volume: Some("Volume".to_string()),
+			open_interest: Some("Open Int".to_string())
+		};
+		GenericFormat {
+			delimiter: b',',
+			has_headers: true,
+			columns,
+			time_formats: vec!["%m/%d/%Y %H:%M".to_string(), "%m/%d/%Y".to_string(), "%Y-%m-%d %H:%M".to_string(), "%Y-%m-%d".to_string()],
+			ignore_prefix: Some("Downloaded from".to_string()),
+			default_patterns: (r"(?i)_(daily|price-history).*\.csv$", Some(r"(?i)_intraday.*\.csv$"))
+		}
+	}
+
+	// Norgate and CSI daily files: date, open, high, low, close, volume and optionally open interest, with or without a header
+	pub fn norgate() -> GenericFormat {
+		let columns = ColumnMapping {
+			symbol: None,
+			time: "0".to_string(),
+			open: "1".to_string(),
+			high: "2".to_string(),
+			low: "3".to_string(),
+			close: "4".to_string(),
+			volume: Some("5".to_string()),
+			open_interest: Some("6".to_string())
+		};
+		GenericFormat {
+			delimiter: b',',
+			has_headers: false,
+			columns,
+			time_formats: vec!["%Y%m%d".to_string(), "%Y-%m-%d".to_string()],
+			ignore_prefix: None,
+			default_patterns: (r"(?i)\.(csv|txt)$", None)
+		}
+	}
+
+	fn get_indices(&self, headers: Option<&StringRecord>) -> Result<ColumnMapping<usize>> {
+		let get_index = |column: &String| -> Result<usize> {
+			match headers {
+				Some(headers) => headers
+					.iter()
+					.position(|x| x.eq_ignore_ascii_case(column))
+					.with_context(|| anyhow!("Unable to find column \"{column}\"")),
+				None => column
+					.parse::<usize>()
+					.with_context(|| anyhow!("Invalid column index \"{column}\""))
+			}
+		};
+		let get_optional_index = |column: &Option<String>| -> Result<Option<usize>> {
+			match (column, headers) {
+				// Optional columns such as open interest are frequently missing from some of the files
+				(Some(column), Some(headers)) => Ok(headers.iter().position(|x| x.eq_ignore_ascii_case(column))),
+				(Some(column), None) => Ok(Some(get_index(column)?)),
+				(None, _) => Ok(None)
+			}
+		};
+		let indices = ColumnMapping {
+			symbol: get_optional_index(&self.columns.symbol)?,
+			time: get_index(&self.columns.time)?,
+			open: get_index(&self.columns.open)?,
+			high: get_index(&self.columns.high)?,
+			low: get_index(&self.columns.low)?,
+			close: get_index(&self.columns.close)?,
+			volume: get_optional_index(&self.columns.volume)?,
+			open_interest: get_optional_index(&self.columns.open_interest)?
+		};
+		Ok(indices)
+	}
+
+	fn get_record(&self, row: &StringRecord, indices: &ColumnMapping<usize>, file_symbol: &str) -> InputResult {
+		let get_field = |index: usize| row.get(index).unwrap_or("");
+		let symbol = match indices.symbol {
+			Some(index) => get_field(index).to_string(),
+			None => file_symbol.to_string()
+		};
+		let time_string = get_field(indices.time);
+		let Some(time) = parse_time(time_string, &self.time_formats) else {
+			return Err(format!("{symbol}: unable to parse time \"{time_string}\""));
+		};
+		let get_price = |index: usize| -> std::result::Result<f64, String> {
+			let field = get_field(index);
+			field.parse::<f64>()
+				.map_err(|_| format!("{symbol} {time}: invalid price \"{field}\""))
+		};
+		// Some vendors provide volume and open interest as floating point numbers
+		let get_count = |index: Option<usize>| -> std::result::Result<Option<u32>, String> {
+			let Some(field) = index.map(get_field).filter(|x| !x.is_empty()) else {
+				return Ok(None);
+			};
+			field.parse::<f64>()
+				.ok()
+				.filter(|x| *x >= 0.0 && *x <= u32::MAX as f64)
+				.map(|x| Some(x.round() as u32))
+				.ok_or_else(|| format!("{symbol} {time}: invalid number \"{field}\""))
+		};
+		let record = InputRecord {
+			time,
+			open: get_price(indices.open)?,
+			high: get_price(indices.high)?,
+			low: get_price(indices.low)?,
+			close: get_price(indices.close)?,
+			volume: get_count(indices.volume)?.unwrap_or(0),
+			open_interest: get_count(indices.open_interest)?,
+			symbol
+		};
+		Ok(record)
+	}
+}
+
+impl InputFormat for GenericFormat {
+	fn get_default_patterns(&self) -> (&'static str, Option<&'static str>) {
+		self.default_patterns
+	}
+
+	fn read(&self, path: &Path, on_record: &mut dyn FnMut(InputResult)) -> Result<()> {
+		let path_buf = path.to_path_buf();
+		let mut reader = ReaderBuilder::new()
+			.delimiter(self.delimiter)
+			.has_headers(false)
+			.flexible(true)
+			.trim(Trim::All)
+			.from_path(path)
+			.with_context(|| anyhow!("Unable to read \"{}\"", path_buf.to_string()))?;
+		let file_symbol = get_file_symbol(path)?;
+		let mut rows = reader.records();
+		let indices = if self.has_headers {
+			let Some(headers) = rows.next() else {
+				return Ok(());
+			};
+			let headers = headers.with_context(|| anyhow!("Unable to parse headers in \"{}\"", path_buf.to_string()))?;
+			self.get_indices(Some(&headers))
+				.with_context(|| anyhow!("Unsupported layout of \"{}\"", path_buf.to_string()))?
+		} else {
+			self.get_indices(None)?
+		};
+		let mut first_row = true;
+		for row in rows {
+			let row = row.with_context(|| anyhow!("Failed to read row in \"{}\"", path_buf.to_string()))?;
+			let is_ignored = self.ignore_prefix
+				.as_ref()
+				.is_some_and(|prefix| row.get(0).is_some_and(|x| x.starts_with(prefix.as_str())));
+			if is_ignored || row.iter().all(|x| x.is_empty()) {
+				continue;
+			}
+			let record = self.get_record(&row, &indices, &file_symbol);
+			// Files without headers may still start with one, which is skipped rather than reported
+			if first_row && !self.has_headers && record.is_err() {
+				first_row = false;
+				continue;
+			}
+			first_row = false;
+			on_record(record);
+		}
+		Ok(())
+	}
+}
+
+impl ColumnMapping<String> {
+	// Parses a comma-separated list of assignments such as "time=Date, open=Open"
+	pub fn parse(columns: &str) -> Result<ColumnMapping<String>> {
+		let mut symbol = None;
+		let mut time = None;
+		let mut open = None;
+		let mut high = None;
+		let mut low = None;
+		let mut close = None;
+		let mut volume = None;
+		let mut open_interest = None;
+		for assignment in columns.split(',') {
+			let Some((key, column)) = assignment.split_once('=') else {
+				bail!("Invalid column assignment \"{assignment}\"");
+			};
+			let column = Some(column.trim().to_string());
+			match key.trim() {
+				"symbol" => symbol = column,
+				"time" => time = column,
+				"open" => open = column,
+				"high" => high = column,
+				"low" => low = column,
+				"close" => close = column,
+				"volume" => volume = column,
+				"open_interest" => open_interest = column,
+				key => bail!("Unknown column \"{key}\"")
+			}
+		}
+		let require = |column: Option<String>, name: &str| column.with_context(|| anyhow!("Missing mandatory column \"{name}\""));
+		let mapping = ColumnMapping {
+			symbol,
+			time: require(time, "time")?,
+			open: require(open, "open")?,
+			high: require(high, "high")?,
+			low: require(low, "low")?,
+			close: require(close, "close")?,
+			volume,
+			open_interest
+		};
+		Ok(mapping)
+	}
+}
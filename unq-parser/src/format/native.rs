@@ -0,0 +1,47 @@
+use std::path::Path;
+use anyhow::Result;
+use serde::Deserialize;
+use unq_common::read_csv;
+use crate::input::{parse_time, InputFormat, InputRecord, InputResult};
+
+// Layout of the .csv files originally supported by the parser
+pub struct NativeFormat;
+
+#[derive(Deserialize)]
+struct CsvRecord {
+	symbol: String,
+	time: String,
+	open: f64,
+	high: f64,
+	low: f64,
+	close: f64,
+	volume: u32,
+	open_interest: Option<u32>
+}
+
+impl InputFormat for NativeFormat {
+	fn get_default_patterns(&self) -> (&'static str, Option<&'static str>) {
+		(r"D1\.csv$", Some(r"(H1|M\d+)\.csv$"))
+	}
+
+	fn read(&self, path: &Path, on_record: &mut dyn FnMut(InputResult)) -> Result<()> {
+		let time_formats = ["%Y-%m-%d %H:%M".to_string(), "%Y-%m-%d".to_string()];
+		read_csv::<CsvRecord>(path.to_path_buf(), |record| {
+			let Some(time) = parse_time(&record.time, &time_formats) else {
+				on_record(Err(format!("{}: unable to parse time \"{}\"", record.symbol, record.time)));
+				return;
+			};
+			let input_record = InputRecord {
+				symbol: record.symbol,
+				time,
+				open: record.open,
+				high: record.high,
+				low: record.low,
+				close: record.close,
+				volume: record.volume,
+				open_interest: record.open_interest
+			};
+			on_record(Ok(input_record));
+		})
+	}
+}
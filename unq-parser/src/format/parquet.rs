@@ -0,0 +1,163 @@
+use std::fs::File;
+use std::path::Path;
+use anyhow::{anyhow, bail, Context, Result};
+use arrow_array::{Array, ArrayRef, RecordBatch};
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Float64Type, Int64Type, TimestampSecondType};
+use arrow_schema::{DataType, TimeUnit};
+use chrono::DateTime;
+use configparser::ini::Ini;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use crate::format::generic::ColumnMapping;
+use crate::input::{get_file_symbol, InputFormat, InputRecord, InputResult};
+
+/*
+Parquet files such as the ones created by the export feature of unq-server.
+The columns default to symbol, time, open, high, low, close, volume and open_interest and may be renamed with
+"columns", using the same syntax as the generic format. Column names are matched case-insensitively and regardless
+of underscores, so "openInterest" matches "open_interest".
+Timestamps, dates and strings are accepted for the time column, all numeric types for prices, volume and open interest.
+*/
+pub struct ParquetFormat {
+	columns: ColumnMapping<String>
+}
+
+// Columns of one record batch, cast to the types used by InputRecord
+struct BatchColumns {
+	symbol: Option<ArrayRef>,
+	time: ArrayRef,
+	open: ArrayRef,
+	high: ArrayRef,
+	low: ArrayRef,
+	close: ArrayRef,
+	volume: Option<ArrayRef>,
+	open_interest: Option<ArrayRef>
+}
+
+impl ParquetFormat {
+	pub fn from_ini(ini: &Ini, section: &str) -> Result<ParquetFormat> {
+		let columns = match ini.get(section, "columns") {
+			Some(columns) => ColumnMapping::parse(&columns)
+				.with_context(|| anyhow!("Invalid column mapping in section \"{section}\""))?,
+			None => ColumnMapping {
+				symbol: Some("symbol".to_string()),
+				time: "time".to_string(),
+				open: "open".to_string(),
+				high: "high".to_string(),
+				low: "low".to_string(),
+				close: "close".to_string(),
+				volume: Some("volume".to_string()),
+				open_interest: Some("open_interest".to_string())
+			}
+		};
+		Ok(ParquetFormat { columns })
+	}
+
+	fn get_columns(&self, batch: &RecordBatch) -> Result<BatchColumns> {
+		let get_optional_column = |name: &String, data_type: DataType| -> Result<Option<ArrayRef>> {
+			let normalize = |x: &str| x.replace('_', "").to_lowercase();
+			let name_key = normalize(name);
+			let index = batch.schema()
+				.fields()
+				.iter()
+				.position(|x| normalize(x.name()) == name_key);
+			let Some(index) = index else {
+				return Ok(None);
+			};
+			let array = arrow_cast::cast(batch.column(index), &data_type)
+				.with_context(|| anyhow!("Unable to convert column \"{name}\" to {data_type}"))?;
+			Ok(Some(array))
+		};
+		let get_column = |name: &String, data_type: DataType| -> Result<ArrayRef> {
+			let Some(array) = get_optional_column(name, data_type)? else {
+				bail!("Unable to find column \"{name}\"");
+			};
+			Ok(array)
+		};
+		let get_optional = |name: &Option<String>, data_type: DataType| -> Result<Option<ArrayRef>> {
+			match name {
+				Some(name) => get_optional_column(name, data_type),
+				None => Ok(None)
+			}
+		};
+		let columns = BatchColumns {
+			symbol: get_optional(&self.columns.symbol, DataType::Utf8)?,
+			time: get_column(&self.columns.time, DataType::Timestamp(TimeUnit::Second, None))?,
+			open: get_column(&self.columns.open, DataType::Float64)?,
+			high: get_column(&self.columns.high, DataType::Float64)?,
+			low: get_column(&self.columns.low, DataType::Float64)?,
+			close: get_column(&self.columns.close, DataType::Float64)?,
+			volume: get_optional(&self.columns.volume, DataType::Int64)?,
+			open_interest: get_optional(&self.columns.open_interest, DataType::Int64)?
+		};
+		Ok(columns)
+	}
+
+	fn get_record(columns: &BatchColumns, row: usize, file_symbol: &str) -> InputResult {
+		let symbol = match &columns.symbol {
+			Some(array) if array.is_valid(row) => array.as_string::<i32>().value(row).to_string(),
+			_ => file_symbol.to_string()
+		};
+		let times = columns.time.as_primitive::<TimestampSecondType>();
+		let time = Some(times)
+			.filter(|x| x.is_valid(row))
+			.and_then(|x| DateTime::from_timestamp(x.value(row), 0))
+			.map(|x| x.naive_utc());
+		let Some(time) = time else {
+			return Err(format!("{symbol}: missing or invalid time"));
+		};
+		let get_price = |array: &ArrayRef| -> std::result::Result<f64, String> {
+			if array.is_valid(row) {
+				Ok(array.as_primitive::<Float64Type>().value(row))
+			} else {
+				Err(format!("{symbol} {time}: missing price"))
+			}
+		};
+		let get_count = |array: &Option<ArrayRef>| -> std::result::Result<Option<u32>, String> {
+			match array {
+				Some(array) if array.is_valid(row) => {
+					let value = array.as_primitive::<Int64Type>().value(row);
+					let count = u32::try_from(value)
+						.map_err(|_| format!("{symbol} {time}: invalid number {value}"))?;
+					Ok(Some(count))
+				},
+				_ => Ok(None)
+			}
+		};
+		let record = InputRecord {
+			time,
+			open: get_price(&columns.open)?,
+			high: get_price(&columns.high)?,
+			low: get_price(&columns.low)?,
+			close: get_price(&columns.close)?,
+			volume: get_count(&columns.volume)?.unwrap_or(0),
+			open_interest: get_count(&columns.open_interest)?,
+			symbol
+		};
+		Ok(record)
+	}
+}
+
+impl InputFormat for ParquetFormat {
+	fn get_default_patterns(&self) -> (&'static str, Option<&'static str>) {
+		(r"D1\.parquet$", Some(r"(H1|M\d+)\.parquet$"))
+	}
+
+	fn read(&self, path: &Path, on_record: &mut dyn FnMut(InputResult)) -> Result<()> {
+		let file = File::open(path)
+			.with_context(|| anyhow!("Unable to read \"{}\"", path.display()))?;
+		let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+			.and_then(|x| x.build())
+			.with_context(|| anyhow!("Unable to read Parquet file \"{}\"", path.display()))?;
+		let file_symbol = get_file_symbol(path)?;
+		for batch in reader {
+			let batch = batch.with_context(|| anyhow!("Failed to read record batch from \"{}\"", path.display()))?;
+			let columns = self.get_columns(&batch)
+				.with_context(|| anyhow!("Unsupported layout of \"{}\"", path.display()))?;
+			for row in 0..batch.num_rows() {
+				on_record(Self::get_record(&columns, row, &file_symbol));
+			}
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,126 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use unq_common::{get_hash, PathDisplay};
+use crate::parser::PARSER_VERSION;
+
+/*
+Records the .csv files an archive was created from so that subsequent runs in incremental mode only need to parse
+the files that have been added or modified since.
+Files are considered to be unchanged if their modification time and size match, otherwise their content hash is compared.
+*/
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceState {
+	parser_version: String,
+	// SHA-256 hash of unq-parser.ini, changes to filters or symbol mappings require a full rebuild
+	configuration_hash: String,
+	// Indexed by file name
+	files: BTreeMap<String, SourceFile>
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SourceFile {
+	modified: DateTime<Utc>,
+	size: u64,
+	hash: String
+}
+
+pub enum SourceChanges {
+	Unchanged,
+	// Paths of the .csv files that have been added or modified
+	Modified(HashSet<PathBuf>),
+	// No previous state, a different parser version or configuration, or some files have been removed
+	Rebuild
+}
+
+impl SourceState {
+	// Returns None if there is no state file yet
+	pub fn read(path: &PathBuf) -> Result<Option<SourceState>> {
+		if !path.exists() {
+			return Ok(None);
+		}
+		let data = fs::read(path)
+			.with_context(|| anyhow!("Failed to read source state from \"{}\"", path.to_string()))?;
+		let state = serde_json::from_slice(&data)
+			.with_context(|| anyhow!("Failed to deserialize source state in \"{}\"", path.to_string()))?;
+		Ok(Some(state))
+	}
+
+	// Written after the archive so that an interrupted run merely causes the same files to be merged again
+	pub fn write(&self, path: &PathBuf) -> Result<()> {
+		let data = serde_json::to_vec_pretty(self)?;
+		let temporary_path = path.with_extension("tmp");
+		fs::write(&temporary_path, data)
+			.with_context(|| anyhow!("Failed to write source state to \"{}\"", temporary_path.to_string()))?;
+		fs::rename(&temporary_path, path)
+			.with_context(|| anyhow!("Failed to replace source state \"{}\"", path.to_string()))?;
+		Ok(())
+	}
+
+	// Determines the current state of the files and how it differs from the previous one
+	pub fn update(previous: Option<&SourceState>, configuration_hash: &String, paths: &[PathBuf]) -> Result<(SourceState, SourceChanges)> {
+		let previous = previous.filter(|x|
+			x.parser_version == PARSER_VERSION &&
+			x.configuration_hash == *configuration_hash);
+		let mut files = BTreeMap::new();
+		let mut modified_paths = HashSet::new();
+		for path in paths {
+			let file_name = Self::get_file_name(path)?;
+			let metadata = fs::metadata(path)
+				.with_context(|| anyhow!("Failed to read metadata of \"{}\"", path.to_string()))?;
+			let modified: DateTime<Utc> = metadata.modified()?.into();
+			let size = metadata.len();
+			let previous_file = previous.and_then(|x| x.files.get(&file_name));
+			let file = match previous_file {
+				Some(file) if file.modified == modified && file.size == size => file.clone(),
+				_ => {
+					let data = fs::read(path)
+						.with_context(|| anyhow!("Failed to read \"{}\"", path.to_string()))?;
+					let hash = get_hash(&data);
+					if previous_file.is_none_or(|x| x.hash != hash) {
+						modified_paths.insert(path.clone());
+					}
+					SourceFile {
+						modified,
+						size,
+						hash
+					}
+				}
+			};
+			files.insert(file_name, file);
+		}
+		let changes = match previous {
+			Some(previous) => {
+				let removed = previous.files
+					.keys()
+					.any(|x| !files.contains_key(x));
+				if removed {
+					SourceChanges::Rebuild
+				} else if modified_paths.is_empty() {
+					SourceChanges::Unchanged
+				} else {
+					SourceChanges::Modified(modified_paths)
+				}
+			},
+			None => SourceChanges::Rebuild
+		};
+		let state = SourceState {
+			parser_version: PARSER_VERSION.to_string(),
+			configuration_hash: configuration_hash.clone(),
+			files
+		};
+		Ok((state, changes))
+	}
+
+	fn get_file_name(path: &Path) -> Result<String> {
+		let Some(file_name) = path.file_name().and_then(|x| x.to_str()) else {
+			bail!("Invalid file name \"{}\"", path.display());
+		};
+		Ok(file_name.to_string())
+	}
+}
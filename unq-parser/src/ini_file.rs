@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use anyhow::{Context, Result};
 use configparser::ini::Ini;
+use crate::validation::VALIDATION_SECTION;
 
 pub type IniMap = HashMap<String, HashMap<String, Option<String>>>;
 
@@ -8,5 +9,6 @@ pub fn get_ini_sections(ini: &Ini) -> Result<IniMap> {
 	let mut config_map = ini.get_map()
 		.with_context(|| "Unable to read configuration file")?;
 	config_map.remove("data");
+	config_map.remove(VALIDATION_SECTION);
 	Ok(config_map)
 }
\ No newline at end of file
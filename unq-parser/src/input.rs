@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{NaiveDate, NaiveDateTime};
+use configparser::ini::Ini;
+use regex::Regex;
+use unq_common::ohlc::{BarSpecification, BarType};
+use crate::format::generic::GenericFormat;
+use crate::format::native::NativeFormat;
+use crate::format::parquet::ParquetFormat;
+use crate::ini_file::get_ini_sections;
+use crate::tick::parse_bar_specifications;
+
+const DATA_SECTION: &str = "data";
+const DEFAULT_FORMAT: &str = "native";
+const DEFAULT_TICK_PATTERN: &str = r"Tick\.csv$";
+
+// Record read from an input file, regardless of the layout of the file
+pub struct InputRecord {
+	pub symbol: String,
+	pub time: NaiveDateTime,
+	pub open: f64,
+	pub high: f64,
+	pub low: f64,
+	pub close: f64,
+	pub volume: u32,
+	pub open_interest: Option<u32>
+}
+
+// Rows that can't be converted are passed on as errors so that they show up as dropped rows in the validation report
+pub type InputResult = std::result::Result<InputRecord, String>;
+
+pub trait InputFormat: Send + Sync {
+	// Default regular expressions matching the names of files with daily and intraday data
+	fn get_default_patterns(&self) -> (&'static str, Option<&'static str>);
+	fn read(&self, path: &Path, on_record: &mut dyn FnMut(InputResult)) -> Result<()>;
+}
+
+#[derive(Clone, Copy)]
+pub enum FileKind {
+	Daily,
+	Intraday,
+	Tick
+}
+
+// Input format and file name patterns used for one ticker directory
+pub struct TickerInput {
+	format: Box<dyn InputFormat>,
+	daily_pattern: Regex,
+	intraday_pattern: Option<Regex>,
+	tick_pattern: Regex,
+	// Bars aggregated from tick files, None for the default of time bars matching intraday_time_frame
+	bar_specifications: Option<Vec<BarSpecification>>
+}
+
+/*
+The input format is selected with the key "input_format" in [data], which serves as the default, or in the section of a ticker:
+
+[data]
+input_format = native
+
+[cl]
+input_format = barchart
+
+[6e]
+input_format = generic
+delimiter = semicolon
+columns = time=Date, open=Open, high=High, low=Low, close=Close, volume=Volume, open_interest=OI
+time_formats = %d.%m.%Y %H:%M|%d.%m.%Y
+daily_files = _daily\.csv$
+intraday_files = _60\.csv$
+
+[nq]
+tick_files = _trades\.csv$
+tick_bars = time=1, time=5, volume=2000, dollar=50000000, range=10
+
+Supported formats:
+- "native": symbol,time,open,high,low,close,volume,open_interest with "D1.csv", "H1.csv" and "M<n>.csv" suffixes
+- "norgate"/"csi": daily files with date,open,high,low,close,volume[,open_interest] and an optional header
+- "barchart": Barchart.com downloads with Time,Open,High,Low,Last,...,Volume,Open Int
+- "generic": configurable delimiter, column mapping and time formats (see GenericFormat)
+- "parquet": Parquet files with the columns of the native format, renamed with "columns" if necessary
+The file name patterns of all formats can be overridden with the regular expressions "daily_files" and "intraday_files".
+Tick files (see read_ticks) are matched by "tick_files", which defaults to "Tick.csv" suffixes, regardless of the input format.
+They are aggregated into the bars specified by "tick_bars" (see parse_bar_specifications), or time bars matching intraday_time_frame.
+*/
+pub struct InputFormats {
+	default: TickerInput,
+	// Indexed by the uppercase root of the ticker directory
+	tickers: HashMap<String, TickerInput>
+}
+
+impl InputFormats {
+	pub fn from_ini(ini: &Ini) -> Result<InputFormats> {
+		let default = TickerInput::from_ini(ini, DATA_SECTION, None)?;
+		let default_format = ini.get(DATA_SECTION, "input_format");
+		let mut tickers = HashMap::new();
+		for section in get_ini_sections(ini)?.keys() {
+			let has_input_keys = ["input_format", "daily_files", "intraday_files", "tick_files", "tick_bars"]
+				.iter()
+				.any(|key| ini.get(section, key).is_some());
+			if has_input_keys {
+				let input = TickerInput::from_ini(ini, section, default_format.clone())?;
+				tickers.insert(section.to_uppercase(), input);
+			}
+		}
+		let formats = InputFormats {
+			default,
+			tickers
+		};
+		Ok(formats)
+	}
+
+	pub fn get(&self, root: &str) -> &TickerInput {
+		self.tickers
+			.get(&root.to_uppercase())
+			.unwrap_or(&self.default)
+	}
+}
+
+impl TickerInput {
+	fn from_ini(ini: &Ini, section: &str, default_format: Option<String>) -> Result<TickerInput> {
+		let format_name = ini.get(section, "input_format")
+			.or(default_format)
+			.unwrap_or(DEFAULT_FORMAT.to_string());
+		let format: Box<dyn InputFormat> = match format_name.as_str() {
+			"native" => Box::new(NativeFormat),
+			"norgate" | "csi" => Box::new(GenericFormat::norgate()),
+			"barchart" => Box::new(GenericFormat::barchart()),
+			"generic" => Box::new(GenericFormat::from_ini(ini, section)?),
+			"parquet" => Box::new(ParquetFormat::from_ini(ini, section)?),
+			_ => bail!("Unknown input format \"{format_name}\" in section \"{section}\"")
+		};
+		let (default_daily, default_intraday) = format.get_default_patterns();
+		let get_pattern = |key, default: Option<&str>| -> Result<Option<Regex>> {
+			let pattern = ini.get(section, key)
+				.or(ini.get(DATA_SECTION, key))
+				.or(default.map(|x| x.to_string()));
+			match pattern {
+				Some(pattern) => {
+					let regex = Regex::new(&pattern)
+						.with_context(|| anyhow!("Invalid regular expression for \"{key}\" in section \"{section}\""))?;
+					Ok(Some(regex))
+				},
+				None => Ok(None)
+			}
+		};
+		let Some(daily_pattern) = get_pattern("daily_files", Some(default_daily))? else {
+			bail!("Missing daily file pattern in section \"{section}\"");
+		};
+		let intraday_pattern = get_pattern("intraday_files", default_intraday)?;
+		let Some(tick_pattern) = get_pattern("tick_files", Some(DEFAULT_TICK_PATTERN))? else {
+			bail!("Missing tick file pattern in section \"{section}\"");
+		};
+		let bar_specifications = match ini.get(section, "tick_bars").or(ini.get(DATA_SECTION, "tick_bars")) {
+			Some(specifications) => {
+				let specifications = parse_bar_specifications(&specifications)
+					.with_context(|| anyhow!("Invalid value for \"tick_bars\" in section \"{section}\""))?;
+				Some(specifications)
+			},
+			None => None
+		};
+		let input = TickerInput {
+			format,
+			daily_pattern,
+			intraday_pattern,
+			tick_pattern,
+			bar_specifications
+		};
+		Ok(input)
+	}
+
+	pub fn get_paths(&self, directory: &Path, kind: FileKind) -> Result<Vec<PathBuf>> {
+		let pattern = match kind {
+			FileKind::Daily => &self.daily_pattern,
+			FileKind::Intraday => match &self.intraday_pattern {
+				Some(pattern) => pattern,
+				None => return Ok(Vec::new())
+			},
+			FileKind::Tick => &self.tick_pattern
+		};
+		// Tick files take precedence since the default daily pattern matches them, too
+		let exclude_ticks = !matches!(kind, FileKind::Tick);
+		let paths = fs::read_dir(directory)
+			.with_context(|| anyhow!("Unable to get list of input files from \"{}\"", directory.display()))?
+			.filter_map(|x| x.ok())
+			.map(|x| x.path())
+			.filter(|x|
+				x.is_file() &&
+				x.file_name()
+					.and_then(|x| x.to_str())
+					.is_some_and(|x| pattern.is_match(x) && !(exclude_ticks && self.tick_pattern.is_match(x))))
+			.collect();
+		Ok(paths)
+	}
+
+	pub fn read(&self, path: &Path, on_record: &mut dyn FnMut(InputResult)) -> Result<()> {
+		self.format.read(path, on_record)
+	}
+
+	pub fn get_bar_specifications(&self, intraday_time_frame: u16) -> Vec<BarSpecification> {
+		match &self.bar_specifications {
+			Some(specifications) => specifications.clone(),
+			None => {
+				let specification = BarSpecification {
+					bar_type: BarType::Time,
+					size: intraday_time_frame as f64
+				};
+				vec![specification]
+			}
+		}
+	}
+}
+
+// Tries the formats with a time component first, then the date-only ones
+pub fn parse_time(time_string: &str, formats: &[String]) -> Option<NaiveDateTime> {
+	let time_string = time_string.trim();
+	formats
+		.iter()
+		.find_map(|format|
+			NaiveDateTime::parse_from_str(time_string, format)
+				.ok()
+				.or_else(|| NaiveDate::parse_from_str(time_string, format)
+					.ok()
+					.and_then(|x| x.and_hms_opt(0, 0, 0))))
+}
+
+// Used by formats without a symbol column, e.g. "ESH24.csv" or "esh24_daily-prices.csv"
+pub fn get_file_symbol(path: &Path) -> Result<String> {
+	let Some(stem) = path.file_stem().and_then(|x| x.to_str()) else {
+		bail!("Unable to determine symbol from file name \"{}\"", path.display());
+	};
+	let symbol = stem
+		.split(['_', ' '])
+		.next()
+		.unwrap_or(stem)
+		.to_uppercase();
+	Ok(symbol)
+}
@@ -2,16 +2,31 @@ mod parser;
 mod filter;
 mod symbol;
 mod ini_file;
+mod migration;
+mod incremental;
+mod validation;
+mod input;
+mod tick;
+mod format {
+	pub mod native;
+	pub mod generic;
+	pub mod parquet;
+}
 
+use std::{env, fs};
 use std::path::PathBuf;
 use anyhow::{Result, bail};
 use filter::ContractFilter;
-use parser::CsvParser;
+use input::InputFormats;
+use parser::{CsvParser, ParserConfiguration};
 use symbol::SymbolMapper;
-use unq_common::get_ini;
+use validation::ValidationConfiguration;
+use unq_common::{get_hash, get_ini};
+
+const CONFIG_PATH: &str = "config/unq-parser.ini";
 
 fn main() -> Result<()> {
-	let ini = get_ini("config/unq-parser.ini")?;
+	let ini = get_ini(CONFIG_PATH)?;
 	let section = "data";
 	let get_value = |key| -> Result<String> {
 		match ini.get(section, key) {
@@ -23,11 +38,38 @@ fn main() -> Result<()> {
 	let enable_intraday = enable_intraday_string.parse::<bool>()?;
 	let intraday_time_frame_string = get_value("intraday_time_frame")?;
 	let intraday_time_frame = intraday_time_frame_string.parse::<u16>()?;
+	let get_optional_flag = |key| -> Result<bool> {
+		match ini.get(section, key) {
+			Some(value) => Ok(value.parse::<bool>()?),
+			None => Ok(false)
+		}
+	};
+	let memory_mapped = get_optional_flag("memory_mapped")?;
+	let incremental = get_optional_flag("incremental")?;
 	let input_directory = PathBuf::from(get_value("input_directory")?);
 	let output_directory = PathBuf::from(get_value("output_directory")?);
+	let arguments: Vec<String> = env::args().collect();
+	match arguments.as_slice() {
+		[_] => {},
+		// Converts legacy archives without a header to the current format rather than parsing any .csv files
+		[_, flag] if flag == "--migrate" => return migration::migrate_archives(&output_directory),
+		_ => bail!("Usage: unq-parser [--migrate]")
+	}
 	let filters = ContractFilter::from_ini(&ini)?;
 	let symbol_mapper = SymbolMapper::new(&ini)?;
-	let parser = CsvParser::new(enable_intraday, intraday_time_frame, input_directory, output_directory, filters, symbol_mapper);
+	let configuration_hash = get_hash(&fs::read(CONFIG_PATH)?);
+	let configuration = ParserConfiguration {
+		enable_intraday,
+		intraday_time_frame,
+		memory_mapped,
+		incremental,
+		input_directory,
+		output_directory,
+		configuration_hash
+	};
+	let validation = ValidationConfiguration::from_ini(&ini)?;
+	let inputs = InputFormats::from_ini(&ini)?;
+	let parser = CsvParser::new(configuration, validation, filters, symbol_mapper, inputs);
 	parser.run()?;
 	Ok(())
 }
\ No newline at end of file
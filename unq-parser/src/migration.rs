@@ -0,0 +1,41 @@
+use std::fs;
+use std::path::PathBuf;
+use anyhow::{anyhow, Context, Result};
+use unq_common::archive::ARCHIVE_FORMAT_VERSION;
+use unq_common::{read_raw_archive, write_archive, write_mapped_archive, PathDisplay, ARCHIVE_EXTENSION, MAPPED_ARCHIVE_EXTENSION};
+use crate::parser::PARSER_VERSION;
+
+/*
+Rewrites legacy archives without a header and archives using older format versions in the output directory using the current archive format.
+The records are left unchanged, the symbol is taken from the file name.
+Archives that already use the current format are skipped.
+*/
+pub fn migrate_archives(output_directory: &PathBuf) -> Result<()> {
+	let entries = fs::read_dir(output_directory)
+		.with_context(|| anyhow!("Unable to read directory \"{}\"", output_directory.to_string()))?;
+	let mut migrated = 0;
+	for path in entries.filter_map(|x| x.ok()).map(|x| x.path()) {
+		let extension = path.extension().and_then(|x| x.to_str());
+		let memory_mapped = match extension {
+			Some(ARCHIVE_EXTENSION) => false,
+			Some(MAPPED_ARCHIVE_EXTENSION) => true,
+			_ => continue
+		};
+		let Some(symbol) = path.file_stem().and_then(|x| x.to_str()) else {
+			continue;
+		};
+		let (format_version, archive) = read_raw_archive(&path)?;
+		if format_version == ARCHIVE_FORMAT_VERSION {
+			continue;
+		}
+		if memory_mapped {
+			write_mapped_archive(&path, symbol, PARSER_VERSION, &archive)?;
+		} else {
+			write_archive(&path, symbol, PARSER_VERSION, &archive)?;
+		}
+		println!("Migrated \"{}\" from format version {format_version} to {ARCHIVE_FORMAT_VERSION}", path.to_string());
+		migrated += 1;
+	}
+	println!("Migrated {migrated} archive(s)");
+	Ok(())
+}
@@ -1,27 +1,22 @@
-use std::{collections::BTreeMap, collections::HashSet, fs, path::{Path, PathBuf}};
-use regex::Regex;
-use serde;
-use chrono::{NaiveDate, NaiveDateTime};
+use std::{collections::BTreeMap, collections::HashMap, collections::HashSet, fs, path::{Path, PathBuf}};
+use chrono::NaiveDateTime;
 use stopwatch::Stopwatch;
 use rayon::prelude::*;
 use anyhow::{Result, anyhow, Context, bail};
-use unq_common::{get_archive_file_name, ohlc::RawOhlcArchive, read_csv, write_archive, PathDisplay};
-use unq_common::ohlc::RawOhlcRecord;
+use unq_common::{get_archive_file_name, get_mapped_archive_file_name, ohlc::RawOhlcArchive, read_raw_archive, write_archive, write_mapped_archive, PathDisplay};
+use unq_common::ohlc::{RawBarSeries, RawIntradaySeries, RawOhlcRecord};
 use crate::{filter::ContractFilter, symbol::SymbolMapper};
+use crate::incremental::{SourceChanges, SourceState};
+use crate::input::{FileKind, InputFormats, InputRecord, TickerInput};
+use crate::tick::{aggregate_ticks, read_ticks};
+use crate::validation::{IssueKind, ValidationConfiguration, ValidationReport};
 
 type OhlcTreeMap = BTreeMap<OhlcKey, RawOhlcRecord>;
 
-#[derive(serde::Deserialize)]
-struct CsvRecord {
-	symbol: String,
-	time: String,
-	open: f64,
-	high: f64,
-	low: f64,
-	close: f64,
-	volume: u32,
-	open_interest: Option<u32>
-}
+const MINUTES_PER_DAY: i64 = 1440;
+
+// Stored in the header of each archive
+pub const PARSER_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
 struct OhlcKey {
@@ -29,24 +24,40 @@ struct OhlcKey {
 	time: NaiveDateTime
 }
 
+// Settings from the [data] section of unq-parser.ini
+pub struct ParserConfiguration {
+	pub enable_intraday: bool,
+	// Time frame of intraday files whose resolution can't be determined from their records and of the default time bars built from ticks
+	pub intraday_time_frame: u16,
+	// Write uncompressed archives that the server can memory-map rather than zstd-compressed ones
+	pub memory_mapped: bool,
+	/*
+	Only parse .csv files that have been added or modified since the previous run and merge their records into the existing archives.
+	Records are never removed from an archive by a merge, so a full rebuild is performed if any .csv files have been removed.
+	*/
+	pub incremental: bool,
+	pub input_directory: PathBuf,
+	pub output_directory: PathBuf,
+	// SHA-256 hash of unq-parser.ini
+	pub configuration_hash: String
+}
+
 pub struct CsvParser {
-	enable_intraday: bool,
-	intraday_time_frame: u16,
-	input_directory: PathBuf,
-	output_directory: PathBuf,
+	configuration: ParserConfiguration,
+	validation: ValidationConfiguration,
 	filters: Vec<ContractFilter>,
-	symbol_mapper: SymbolMapper
+	symbol_mapper: SymbolMapper,
+	inputs: InputFormats
 }
 
 impl CsvParser {
-	pub fn new(enable_intraday: bool, intraday_time_frame: u16, input_directory: PathBuf, output_directory: PathBuf, filters: Vec<ContractFilter>, symbol_mapper: SymbolMapper) -> CsvParser {
+	pub fn new(configuration: ParserConfiguration, validation: ValidationConfiguration, filters: Vec<ContractFilter>, symbol_mapper: SymbolMapper, inputs: InputFormats) -> CsvParser {
 		CsvParser {
-			enable_intraday,
-			intraday_time_frame,
-			input_directory,
-			output_directory,
+			configuration,
+			validation,
 			filters,
-			symbol_mapper
+			symbol_mapper,
+			inputs
 		}
 	}
 
@@ -70,32 +81,9 @@ impl CsvParser {
 		Ok(iterator)
 	}
 
-	fn get_csv_paths(path: &PathBuf, filter: Regex) -> Result<impl Iterator<Item = PathBuf>> {
-		let iterator = fs::read_dir(path.clone())
-			.with_context(|| anyhow!("Unable to get list of .csv files from \"{}\"", path.to_string()))?
-			.filter_map(|x| x.ok())
-			.map(|x| x.path())
-			.filter(move |x|
-				x.is_file() &&
-				x.file_name()
-					.and_then(|x| x.to_str())
-					.map_or(false, |x| filter.is_match(x)));
-		Ok(iterator)
-	}
-
-	fn parse_date_time(time_string: &str) -> Result<NaiveDateTime>  {
-		match NaiveDateTime::parse_from_str(time_string, "%Y-%m-%d %H:%M") {
-			Ok(datetime) => Ok(datetime),
-			_ => match NaiveDate::parse_from_str(time_string, "%Y-%m-%d") {
-				Ok(date) => Ok(date.and_hms_opt(0, 0, 0).unwrap()),
-				_ => bail!("Failed to parse date time")
-			}
-		}
-	}
-
 	pub fn run(&self) -> Result<()> {
 		let stopwatch = Stopwatch::start_new();
-		let results: Result<Vec<()>> = Self::get_directories(&self.input_directory)?
+		let results: Result<Vec<()>> = Self::get_directories(&self.configuration.input_directory)?
 			.collect::<Vec<PathBuf>>()
 			.par_iter()
 			.map(|ticker_directory| {
@@ -109,25 +97,93 @@ impl CsvParser {
 
 	fn process_ticker_directory(&self, ticker_directory: &PathBuf) -> Result<()> {
 		let stopwatch = Stopwatch::start_new();
-		let daily_filter = Regex::new(r"D1\.csv$")?;
-		let intraday_filter = Regex::new(r"(H1|M\d+)\.csv$")?;
-		let (daily, daily_excluded) = self.parse_csv_files(ticker_directory, daily_filter, false)?;
-		let (intraday, intraday_excluded) = if self.enable_intraday {
-			self.parse_csv_files(ticker_directory, intraday_filter, true)?
+		let root = Self::get_last_token(ticker_directory);
+		let input = self.inputs.get(&root);
+		let daily_paths = input.get_paths(ticker_directory, FileKind::Daily)?;
+		let intraday_paths = if self.configuration.enable_intraday {
+			input.get_paths(ticker_directory, FileKind::Intraday)?
+		} else {
+			Vec::new()
+		};
+		let tick_paths = input.get_paths(ticker_directory, FileKind::Tick)?;
+		let symbol = self.symbol_mapper.translate(&root);
+		let archive_path = self.get_archive_path(&symbol);
+		let state_path = self.get_state_path(&symbol);
+		let mut source_state = None;
+		let mut existing_archive = None;
+		let mut modified_paths = None;
+		if self.configuration.incremental {
+			let previous_state = SourceState::read(&state_path)?;
+			let all_paths = [daily_paths.as_slice(), intraday_paths.as_slice(), tick_paths.as_slice()].concat();
+			let (state, changes) = SourceState::update(previous_state.as_ref(), &self.configuration.configuration_hash, &all_paths)?;
+			match changes {
+				SourceChanges::Unchanged if archive_path.exists() => {
+					println!("\"{}\" is up to date", archive_path.to_string());
+					return Ok(());
+				},
+				SourceChanges::Modified(paths) if archive_path.exists() => {
+					let (_, archive) = read_raw_archive(&archive_path)?;
+					existing_archive = Some(archive);
+					modified_paths = Some(paths);
+				},
+				_ => {}
+			}
+			source_state = Some(state);
+		}
+		// In incremental mode only the modified files are parsed and merged into the records of the existing archive
+		let filter_paths = |paths: Vec<PathBuf>| match &modified_paths {
+			Some(modified_paths) => paths
+				.into_iter()
+				.filter(|x| modified_paths.contains(x))
+				.collect(),
+			None => paths
+		};
+		let (existing_daily, existing_intraday, existing_bars) = match existing_archive {
+			Some(archive) => (archive.daily, archive.intraday, archive.bars),
+			None => (Vec::new(), Vec::new(), Vec::new())
+		};
+		// Bars depend on the entire sequence of ticks, so they are aggregated from all tick files again if any of them have been modified
+		let ticks_modified = modified_paths
+			.as_ref()
+			.is_none_or(|paths| tick_paths.iter().any(|x| paths.contains(x)));
+		let mut report = ValidationReport::new(&symbol);
+		let (daily, daily_excluded) = self.parse_csv_files(ticker_directory, input, filter_paths(daily_paths), existing_daily, &mut report)?;
+		let (intraday, intraday_excluded) = if self.configuration.enable_intraday {
+			self.parse_intraday_files(ticker_directory, input, filter_paths(intraday_paths), existing_intraday, &mut report)?
 		} else {
 			(Vec::new(), 0)
 		};
-		let archive_path = self.get_archive_path(ticker_directory);
+		let bars = if ticks_modified {
+			self.parse_tick_files(ticker_directory, input, tick_paths, &mut report)?
+		} else {
+			existing_bars
+		};
+		report.check_records(&daily, true, &root, &self.validation);
+		for series in &intraday {
+			report.check_records(&series.records, false, &root, &self.validation);
+		}
+		report.evaluate(&self.validation)?;
 		let archive = RawOhlcArchive {
 			daily,
 			intraday,
-			intraday_time_frame: self.intraday_time_frame
+			bars
+		};
+		if self.configuration.memory_mapped {
+			write_mapped_archive(&archive_path, &symbol, PARSER_VERSION, &archive)?;
+		} else {
+			write_archive(&archive_path, &symbol, PARSER_VERSION, &archive)?;
+		}
+		if let Some(source_state) = source_state {
+			source_state.write(&state_path)?;
+		}
+		let action = match &modified_paths {
+			Some(modified_paths) => format!("Merged {} modified file(s) into {}", modified_paths.len(), archive.daily.len() + archive.get_intraday_record_count()),
+			None => format!("Loaded {}", archive.daily.len() + archive.get_intraday_record_count())
 		};
-		write_archive(&archive_path, &archive)?;
 		if daily_excluded + intraday_excluded > 0 {
 			println!(
-				"Loaded {} records from \"{}\", excluded {} daily contracts, {} intraday contracts and wrote them to \"{}\" in {} ms",
-				archive.daily.len() + archive.intraday.len(),
+				"{} records from \"{}\", excluded {} daily contracts, {} intraday contracts and wrote them to \"{}\" in {} ms",
+				action,
 				ticker_directory.to_str().unwrap(),
 				daily_excluded,
 				intraday_excluded,
@@ -136,8 +192,8 @@ impl CsvParser {
 			);
 		} else {
 			println!(
-				"Loaded {} records from \"{}\" and wrote them to \"{}\" in {} ms",
-				archive.daily.len() + archive.intraday.len(),
+				"{} records from \"{}\" and wrote them to \"{}\" in {} ms",
+				action,
 				ticker_directory.to_str().unwrap(),
 				archive_path.to_str().unwrap(),
 				stopwatch.elapsed_ms()
@@ -146,50 +202,179 @@ impl CsvParser {
 		Ok(())
 	}
 
-	fn parse_csv_files(&self, path: &PathBuf, filter: Regex, sort_by_symbol: bool) -> Result<(Vec<RawOhlcRecord>, usize)> {
-		let csv_paths = Self::get_csv_paths(path, filter)?;
+	fn parse_csv_files(&self, path: &PathBuf, input: &TickerInput, csv_paths: Vec<PathBuf>, existing_records: Vec<RawOhlcRecord>, report: &mut ValidationReport) -> Result<(Vec<RawOhlcRecord>, usize)> {
 		let mut ohlc_map = OhlcTreeMap::new();
-		let symbol_path = Path::new(path);
-		let mut current_filter: Option<ContractFilter> = None;
-		if let Some(root_os) = symbol_path.file_name() {
-			let root = root_os.to_str().unwrap().to_string();
-			if let Some(filter) = self.filters.iter().find(|x| x.root == root) {
-				current_filter = Some(filter.clone());
-			}
+		let mut current_filter = self.get_filter(path);
+		let mut excluded_contracts = HashSet::new();
+		for csv_path in csv_paths {
+			let file_map = self.read_csv_file(&csv_path, input, &mut current_filter, &mut excluded_contracts, report)?;
+			Self::merge_records(&mut ohlc_map, file_map, report);
 		}
+		Self::add_existing_records(&mut ohlc_map, existing_records);
+		if ohlc_map.values().len() < 250 {
+			bail!("Missing data in {}", path.to_string());
+		}
+		let mut records: Vec<RawOhlcRecord> = ohlc_map.into_values().collect();
+		records.sort_by(|a, b| a.time.cmp(&b.time));
+		Ok((records, excluded_contracts.len()))
+	}
+
+	/*
+	Intraday files may contain different resolutions, e.g. "ES M1.csv" and "ES H1.csv".
+	The time frame of each file is determined from the spacing of its records, falling back to intraday_time_frame.
+	Records are merged into one series per time frame.
+	*/
+	fn parse_intraday_files(&self, path: &PathBuf, input: &TickerInput, csv_paths: Vec<PathBuf>, existing_series: Vec<RawIntradaySeries>, report: &mut ValidationReport) -> Result<(Vec<RawIntradaySeries>, usize)> {
+		let mut resolutions: BTreeMap<u16, OhlcTreeMap> = BTreeMap::new();
+		let mut current_filter = self.get_filter(path);
 		let mut excluded_contracts = HashSet::new();
 		for csv_path in csv_paths {
-			read_csv::<CsvRecord>(csv_path, |record| {
+			let file_map = self.read_csv_file(&csv_path, input, &mut current_filter, &mut excluded_contracts, report)?;
+			let time_frame = Self::get_time_frame(&file_map)
+				.unwrap_or(self.configuration.intraday_time_frame);
+			let ohlc_map = resolutions.entry(time_frame).or_default();
+			Self::merge_records(ohlc_map, file_map, report);
+		}
+		for series in existing_series {
+			let ohlc_map = resolutions.entry(series.time_frame).or_default();
+			Self::add_existing_records(ohlc_map, series.records);
+		}
+		let record_count: usize = resolutions.values().map(|x| x.len()).sum();
+		if record_count < 250 {
+			bail!("Missing intraday data in {}", path.to_string());
+		}
+		let series = resolutions
+			.into_iter()
+			.map(|(time_frame, ohlc_map)| {
+				let mut records: Vec<RawOhlcRecord> = ohlc_map.into_values().collect();
+				records.sort_by(|a, b| a.symbol.cmp(&b.symbol).then_with(|| a.time.cmp(&b.time)));
+				RawIntradaySeries {
+					time_frame,
+					records
+				}
+			})
+			.collect();
+		Ok((series, excluded_contracts.len()))
+	}
+
+	fn read_csv_file(&self, csv_path: &Path, input: &TickerInput, current_filter: &mut Option<ContractFilter>, excluded_contracts: &mut HashSet<String>, report: &mut ValidationReport) -> Result<OhlcTreeMap> {
+		let mut ohlc_map = OhlcTreeMap::new();
+		input.read(csv_path, &mut |result| {
+			let record = match result {
+				Ok(record) => record,
+				Err(message) => {
+					report.add_issue(IssueKind::DroppedRow, || message);
+					return;
+				}
+			};
+			if let Some(filter) = current_filter.as_mut() {
+				if !filter.is_included(&record.symbol) {
+					excluded_contracts.insert(record.symbol.clone());
+					return;
+				}
+			}
+			self.add_ohlc_record(&record, &mut ohlc_map, report);
+		})?;
+		if let Some(filter) = current_filter.as_mut() {
+			filter.reset();
+		}
+		Ok(ohlc_map)
+	}
+
+	// Later files replace records with the same symbol and time from earlier ones
+	fn merge_records(ohlc_map: &mut OhlcTreeMap, file_map: OhlcTreeMap, report: &mut ValidationReport) {
+		for (key, record) in file_map {
+			Self::insert_record(ohlc_map, key, record, report);
+		}
+	}
+
+	// Records from the .csv files replace existing records with the same symbol and time
+	fn add_existing_records(ohlc_map: &mut OhlcTreeMap, existing_records: Vec<RawOhlcRecord>) {
+		for record in existing_records {
+			let key = OhlcKey {
+				symbol: record.symbol.clone(),
+				time: record.time
+			};
+			ohlc_map.entry(key).or_insert(record);
+		}
+	}
+
+	// The most frequent positive gap in minutes between consecutive records of the same contract, gaps of a day or more are ignored
+	fn get_time_frame(ohlc_map: &OhlcTreeMap) -> Option<u16> {
+		let mut gap_counts: HashMap<i64, usize> = HashMap::new();
+		let mut previous: Option<&OhlcKey> = None;
+		for key in ohlc_map.keys() {
+			if let Some(previous) = previous.filter(|x| x.symbol == key.symbol) {
+				let gap = (key.time - previous.time).num_minutes();
+				if gap > 0 && gap < MINUTES_PER_DAY {
+					*gap_counts.entry(gap).or_insert(0) += 1;
+				}
+			}
+			previous = Some(key);
+		}
+		gap_counts
+			.into_iter()
+			.max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+			.map(|(gap, _)| gap as u16)
+	}
+
+	// Aggregates the trades from all tick files into the configured bars
+	fn parse_tick_files(&self, path: &PathBuf, input: &TickerInput, tick_paths: Vec<PathBuf>, report: &mut ValidationReport) -> Result<Vec<RawBarSeries>> {
+		if tick_paths.is_empty() {
+			return Ok(Vec::new());
+		}
+		let mut ticks = Vec::new();
+		let mut current_filter = self.get_filter(path);
+		for tick_path in tick_paths {
+			read_ticks(&tick_path, &mut |result| {
+				let mut tick = match result {
+					Ok(tick) => tick,
+					Err(message) => {
+						report.add_issue(IssueKind::DroppedRow, || message);
+						return;
+					}
+				};
 				if let Some(filter) = current_filter.as_mut() {
-					if !filter.is_included(&record.symbol) {
-						excluded_contracts.insert(record.symbol.clone());
+					if !filter.is_included(&tick.symbol) {
 						return;
 					}
 				}
-				self.add_ohlc_record(&record, &mut ohlc_map);
+				if tick.price <= 0.0 {
+					report.add_issue(IssueKind::NonPositivePrice, || format!("{} {}: tick at {}", tick.symbol, tick.time, tick.price));
+					return;
+				}
+				tick.symbol = self.symbol_mapper.translate(&tick.symbol);
+				ticks.push(tick);
 			})?;
 			if let Some(filter) = current_filter.as_mut() {
 				filter.reset();
 			}
 		}
-		if ohlc_map.values().len() < 250 {
-			bail!("Missing data in {}", path.to_string());
-		}
-		let mut records: Vec<RawOhlcRecord> = ohlc_map.into_values().collect();
-		records.sort_by(|a, b| {
-			if sort_by_symbol {
-				a.symbol.cmp(&b.symbol).then_with(|| a.time.cmp(&b.time))
-			} else {
-				a.time.cmp(&b.time)
-			}
-		});
-		Ok((records, excluded_contracts.len()))
+		// The sort is stable, ticks with the same timestamp remain in the order in which they were read
+		ticks.sort_by(|a, b| a.symbol.cmp(&b.symbol).then_with(|| a.time.cmp(&b.time)));
+		report.set_tick_records(ticks.len());
+		let bars: Vec<RawBarSeries> = input.get_bar_specifications(self.configuration.intraday_time_frame)
+			.into_iter()
+			.map(|specification| RawBarSeries {
+				specification,
+				records: aggregate_ticks(&ticks, &specification)
+			})
+			.collect();
+		let bar_count: usize = bars.iter().map(|x| x.records.len()).sum();
+		println!("Aggregated {} ticks from \"{}\" into {bar_count} bars", ticks.len(), path.to_string());
+		Ok(bars)
 	}
 
-	fn add_ohlc_record(&self, record: &CsvRecord, ohlc_map: &mut OhlcTreeMap) {
-		let Ok(time) = Self::parse_date_time(record.time.as_str()) else {
-			return;
-		};
+	fn get_filter(&self, path: &Path) -> Option<ContractFilter> {
+		let root = path.file_name()?.to_str()?;
+		self.filters
+			.iter()
+			.find(|x| x.root == root)
+			.cloned()
+	}
+
+	fn add_ohlc_record(&self, record: &InputRecord, ohlc_map: &mut OhlcTreeMap, report: &mut ValidationReport) {
+		let time = record.time;
 		let symbol = self.symbol_mapper.translate(&record.symbol);
 		let key = OhlcKey {
 			symbol: symbol.clone(),
@@ -205,13 +390,38 @@ impl CsvParser {
 			volume: record.volume,
 			open_interest: record.open_interest
 		};
-		ohlc_map.insert(key, value);
+		Self::insert_record(ohlc_map, key, value, report);
+	}
+
+	fn insert_record(ohlc_map: &mut OhlcTreeMap, key: OhlcKey, record: RawOhlcRecord, report: &mut ValidationReport) {
+		if let Some(previous) = ohlc_map.get(&key) {
+			if Self::is_conflicting(previous, &record) {
+				report.add_issue(IssueKind::ConflictingDuplicate, || format!("{} {}: multiple records with different values", record.symbol, record.time));
+			}
+		}
+		ohlc_map.insert(key, record);
+	}
+
+	fn is_conflicting(previous: &RawOhlcRecord, record: &RawOhlcRecord) -> bool {
+		previous.open != record.open ||
+			previous.high != record.high ||
+			previous.low != record.low ||
+			previous.close != record.close ||
+			previous.volume != record.volume ||
+			previous.open_interest != record.open_interest
+	}
+
+	fn get_archive_path(&self, symbol: &String) -> PathBuf {
+		let file_name = if self.configuration.memory_mapped {
+			get_mapped_archive_file_name(symbol)
+		} else {
+			get_archive_file_name(symbol)
+		};
+		Path::new(&self.configuration.output_directory).join(file_name)
 	}
 
-	fn get_archive_path(&self, time_frame_directory: &PathBuf) -> PathBuf {
-		let symbol = Self::get_last_token(time_frame_directory);
-		let exchange_symbol = self.symbol_mapper.translate(&symbol);
-		let file_name = get_archive_file_name(&exchange_symbol);
-		Path::new(&self.output_directory).join(file_name)
+	fn get_state_path(&self, symbol: &String) -> PathBuf {
+		let file_name = format!("{symbol}.sources.json");
+		Path::new(&self.configuration.output_directory).join(file_name)
 	}
 }
\ No newline at end of file
@@ -0,0 +1,187 @@
+use std::path::Path;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{Duration, NaiveDateTime, NaiveTime, Timelike};
+use serde::Deserialize;
+use unq_common::ohlc::{BarSpecification, BarType, RawOhlcRecord};
+use unq_common::read_csv;
+use crate::input::{get_file_symbol, parse_time};
+
+const MINUTES_PER_DAY: f64 = 1440.0;
+
+// Trade read from a tick file
+pub struct Tick {
+	pub symbol: String,
+	pub time: NaiveDateTime,
+	pub price: f64,
+	pub size: u32
+}
+
+pub type TickResult = std::result::Result<Tick, String>;
+
+/*
+Tick files are .csv files with the columns symbol, time, price, size, bid and ask, e.g.:
+
+symbol,time,price,size,bid,ask
+ESH24,2024-01-02 09:30:00.125,4750.25,3,4750.00,4750.25
+
+The symbol column is optional, the symbol is derived from the file name if it is missing.
+Rows without a price or size are quote updates and don't contribute to any bars. The bid and ask columns are not used otherwise.
+*/
+#[derive(Deserialize)]
+struct CsvTick {
+	symbol: Option<String>,
+	time: String,
+	price: Option<f64>,
+	size: Option<u32>
+}
+
+// Bar that is still receiving ticks
+struct PendingBar {
+	record: RawOhlcRecord,
+	// Sum of price times size
+	value: f64,
+	// The threshold has been reached, the bar is completed by the next tick with a later timestamp
+	complete: bool
+}
+
+pub fn read_ticks(path: &Path, on_tick: &mut dyn FnMut(TickResult)) -> Result<()> {
+	let time_formats = [
+		"%Y-%m-%d %H:%M:%S%.f".to_string(),
+		"%Y-%m-%d %H:%M:%S".to_string(),
+		"%Y-%m-%d %H:%M".to_string()
+	];
+	let file_symbol = get_file_symbol(path)?;
+	read_csv::<CsvTick>(path.to_path_buf(), |tick| {
+		let symbol = tick.symbol.unwrap_or_else(|| file_symbol.clone());
+		let Some(time) = parse_time(&tick.time, &time_formats) else {
+			on_tick(Err(format!("{symbol}: unable to parse time \"{}\"", tick.time)));
+			return;
+		};
+		let (Some(price), Some(size)) = (tick.price, tick.size) else {
+			return;
+		};
+		let tick = Tick {
+			symbol,
+			time,
+			price,
+			size
+		};
+		on_tick(Ok(tick));
+	})
+}
+
+// Parses a comma-separated list of bar specifications such as "time=5, volume=1000, dollar=2500000, range=2.5"
+pub fn parse_bar_specifications(specifications: &str) -> Result<Vec<BarSpecification>> {
+	specifications
+		.split(',')
+		.map(|x| {
+			let Some((bar_type_string, size_string)) = x.split_once('=') else {
+				bail!("Invalid bar specification \"{x}\"");
+			};
+			let bar_type = match bar_type_string.trim() {
+				"time" => BarType::Time,
+				"volume" => BarType::Volume,
+				"dollar" => BarType::Dollar,
+				"range" => BarType::Range,
+				bar_type => bail!("Unknown bar type \"{bar_type}\"")
+			};
+			let size = size_string
+				.trim()
+				.parse::<f64>()
+				.with_context(|| anyhow!("Invalid bar size \"{size_string}\""))?;
+			if size <= 0.0 {
+				bail!("Bar size must be positive");
+			}
+			if bar_type == BarType::Time && (size.fract() != 0.0 || size > MINUTES_PER_DAY) {
+				bail!("The size of time bars must be a whole number of minutes no greater than one day");
+			}
+			let specification = BarSpecification {
+				bar_type,
+				size
+			};
+			Ok(specification)
+		})
+		.collect()
+}
+
+/*
+Aggregates ticks sorted by symbol and time into bars, separately for each contract.
+Time bars are labeled with the start of their interval, like the intraday records from .csv files.
+All other bars are labeled with the time of their last tick, i.e. the time at which they were completed.
+Ticks with the same timestamp always end up in the same bar so that the timestamps of a contract's bars are unique.
+The last bar of each contract is only kept if it is a time bar, incomplete bars of the other types are discarded.
+*/
+pub fn aggregate_ticks(ticks: &[Tick], specification: &BarSpecification) -> Vec<RawOhlcRecord> {
+	let mut bars = Vec::new();
+	let mut pending: Option<PendingBar> = None;
+	for tick in ticks {
+		let time = match specification.bar_type {
+			BarType::Time => get_interval_start(tick.time, specification.size as i64),
+			_ => tick.time
+		};
+		let completed = pending
+			.as_ref()
+			.is_some_and(|bar|
+				bar.record.symbol != tick.symbol ||
+				match specification.bar_type {
+					BarType::Time => bar.record.time != time,
+					_ => bar.complete && bar.record.time < time
+				});
+		if completed {
+			if let Some(bar) = pending.take() {
+				if bar.complete || specification.bar_type == BarType::Time {
+					bars.push(bar.record);
+				}
+			}
+		}
+		let bar = pending.get_or_insert_with(|| PendingBar::new(tick, time));
+		bar.add(tick, time);
+		bar.complete = match specification.bar_type {
+			BarType::Time => false,
+			BarType::Volume => bar.record.volume as f64 >= specification.size,
+			BarType::Dollar => bar.value >= specification.size,
+			BarType::Range => bar.record.high - bar.record.low >= specification.size
+		};
+	}
+	if let Some(bar) = pending {
+		if bar.complete || specification.bar_type == BarType::Time {
+			bars.push(bar.record);
+		}
+	}
+	bars
+}
+
+fn get_interval_start(time: NaiveDateTime, minutes: i64) -> NaiveDateTime {
+	let minute_of_day = (time.hour() * 60 + time.minute()) as i64;
+	let start = minute_of_day - minute_of_day % minutes;
+	time.date().and_time(NaiveTime::MIN) + Duration::minutes(start)
+}
+
+impl PendingBar {
+	fn new(tick: &Tick, time: NaiveDateTime) -> PendingBar {
+		let record = RawOhlcRecord {
+			symbol: tick.symbol.clone(),
+			time,
+			open: tick.price,
+			high: tick.price,
+			low: tick.price,
+			close: tick.price,
+			volume: 0,
+			open_interest: None
+		};
+		PendingBar {
+			record,
+			value: 0.0,
+			complete: false
+		}
+	}
+
+	fn add(&mut self, tick: &Tick, time: NaiveDateTime) {
+		self.record.time = time;
+		self.record.high = self.record.high.max(tick.price);
+		self.record.low = self.record.low.min(tick.price);
+		self.record.close = tick.price;
+		self.record.volume = self.record.volume.saturating_add(tick.size);
+		self.value += tick.price * tick.size as f64;
+	}
+}
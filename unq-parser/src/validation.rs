@@ -0,0 +1,425 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{Datelike, NaiveDate, Weekday};
+use configparser::ini::Ini;
+use serde::Serialize;
+use unq_common::ohlc::RawOhlcRecord;
+use unq_common::{get_ini_optional, PathDisplay};
+use crate::ini_file::get_ini_sections;
+
+pub const VALIDATION_SECTION: &str = "validation";
+// Number of issues of each kind that are included in the report verbatim
+const MAX_EXAMPLES: usize = 5;
+const HOLIDAY_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+	// High below low or open/close outside of the range
+	InvalidRange,
+	NonPositivePrice,
+	// The same symbol and timestamp occurs multiple times with different values
+	ConflictingDuplicate,
+	// Run of missing trading days in daily data, i.e. weekdays that aren't listed as holidays of the exchange calendar
+	CalendarGap,
+	// Zero volume or volume far above the median of the contract
+	VolumeAnomaly,
+	// Zero open interest or records lacking open interest in a contract that otherwise provides it
+	OpenInterestAnomaly,
+	// Close-to-close return beyond the configured number of standard deviations
+	PriceSpike,
+	// Rows dropped due to unparseable timestamps
+	DroppedRow
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+	Ok,
+	Warning,
+	Error
+}
+
+#[derive(Clone, Copy)]
+struct Threshold {
+	// Minimum number of issues of one kind that trigger a warning or fail the symbol
+	warn: Option<usize>,
+	fail: Option<usize>
+}
+
+/*
+Settings from the [validation] section of unq-parser.ini, e.g.:
+
+[validation]
+report_directory = reports
+spike_sigma = 10
+max_gap_days = 0
+volume_ratio = 50
+holidays = 2024-01-01, 2024-12-25
+cme_holidays = 2024-01-01, 2024-01-15, 2024-02-19, 2024-03-29, 2024-05-27, 2024-06-19, 2024-07-04, 2024-09-02, 2024-11-28, 2024-12-25
+warn_threshold = 1
+fail_threshold = 100
+invalid_range_fail = 1
+non_positive_price_warn = 10
+
+The global warn_threshold and fail_threshold can be overridden for each kind of issue with "<issue>_warn" and "<issue>_fail",
+using the snake case names of IssueKind (invalid_range, calendar_gap etc.).
+By default every issue triggers a warning but none of them fail the symbol.
+Symbols that fail validation are not written to the output directory.
+
+The exchange calendar used by the calendar_gap check consists of the weekdays minus the holidays, which are specified as
+comma-separated dates. The list in [validation] applies to all tickers unless their section selects a named list with
+"calendar" or specifies its own "holidays":
+
+[es]
+calendar = cme
+
+[6e]
+holidays = 2024-01-01, 2024-12-25
+
+The holidays need to cover the entire history of the data. Without any holidays the exchange calendar is approximated
+by weekdays and max_gap_days defaults to 3 so that missing holidays aren't reported, otherwise it defaults to 0.
+*/
+pub struct ValidationConfiguration {
+	// JSON reports are written to "<symbol>.validation.json" in this directory, if specified
+	report_directory: Option<PathBuf>,
+	spike_sigma: f64,
+	// Maximum number of consecutive trading days without any records, None for the default that depends on the holidays
+	max_gap_days: Option<usize>,
+	volume_ratio: f64,
+	thresholds: HashMap<IssueKind, Threshold>,
+	// Holidays from the [validation] section, used for tickers without a calendar of their own
+	holidays: HashSet<NaiveDate>,
+	// Holidays of tickers that select a named calendar or specify their own, indexed by the uppercase root
+	ticker_holidays: HashMap<String, HashSet<NaiveDate>>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationReport {
+	symbol: String,
+	daily_records: usize,
+	intraday_records: usize,
+	tick_records: usize,
+	dropped_rows: usize,
+	severity: Severity,
+	issues: Vec<IssueSummary>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct IssueSummary {
+	kind: IssueKind,
+	count: usize,
+	severity: Severity,
+	examples: Vec<String>
+}
+
+impl IssueKind {
+	const ALL: [IssueKind; 8] = [
+		IssueKind::InvalidRange,
+		IssueKind::NonPositivePrice,
+		IssueKind::ConflictingDuplicate,
+		IssueKind::CalendarGap,
+		IssueKind::VolumeAnomaly,
+		IssueKind::OpenInterestAnomaly,
+		IssueKind::PriceSpike,
+		IssueKind::DroppedRow
+	];
+
+	fn get_name(&self) -> &'static str {
+		match self {
+			IssueKind::InvalidRange => "invalid_range",
+			IssueKind::NonPositivePrice => "non_positive_price",
+			IssueKind::ConflictingDuplicate => "conflicting_duplicate",
+			IssueKind::CalendarGap => "calendar_gap",
+			IssueKind::VolumeAnomaly => "volume_anomaly",
+			IssueKind::OpenInterestAnomaly => "open_interest_anomaly",
+			IssueKind::PriceSpike => "price_spike",
+			IssueKind::DroppedRow => "dropped_row"
+		}
+	}
+}
+
+impl ValidationConfiguration {
+	pub fn from_ini(ini: &Ini) -> Result<ValidationConfiguration> {
+		let report_directory = ini.get(VALIDATION_SECTION, "report_directory")
+			.map(PathBuf::from);
+		let spike_sigma = get_ini_optional(ini, VALIDATION_SECTION, "spike_sigma")?.unwrap_or(10.0);
+		let max_gap_days = get_ini_optional(ini, VALIDATION_SECTION, "max_gap_days")?;
+		let volume_ratio = get_ini_optional(ini, VALIDATION_SECTION, "volume_ratio")?.unwrap_or(50.0);
+		let default_threshold = Threshold {
+			warn: get_ini_optional(ini, VALIDATION_SECTION, "warn_threshold")?.or(Some(1)),
+			fail: get_ini_optional(ini, VALIDATION_SECTION, "fail_threshold")?
+		};
+		let mut thresholds = HashMap::new();
+		for kind in IssueKind::ALL {
+			let name = kind.get_name();
+			let threshold = Threshold {
+				warn: get_ini_optional(ini, VALIDATION_SECTION, &format!("{name}_warn"))?.or(default_threshold.warn),
+				fail: get_ini_optional(ini, VALIDATION_SECTION, &format!("{name}_fail"))?.or(default_threshold.fail)
+			};
+			thresholds.insert(kind, threshold);
+		}
+		let holidays = match ini.get(VALIDATION_SECTION, "holidays") {
+			Some(dates) => Self::parse_holidays(&dates, VALIDATION_SECTION)?,
+			None => HashSet::new()
+		};
+		let mut ticker_holidays = HashMap::new();
+		for section in get_ini_sections(ini)?.keys() {
+			let section_holidays = match (ini.get(section, "holidays"), ini.get(section, "calendar")) {
+				(Some(dates), _) => Self::parse_holidays(&dates, section)?,
+				(None, Some(calendar)) => {
+					let dates = ini.get(VALIDATION_SECTION, &format!("{calendar}_holidays"))
+						.with_context(|| anyhow!("Unknown calendar \"{calendar}\" in section \"{section}\""))?;
+					Self::parse_holidays(&dates, VALIDATION_SECTION)?
+				},
+				(None, None) => continue
+			};
+			ticker_holidays.insert(section.to_uppercase(), section_holidays);
+		}
+		let configuration = ValidationConfiguration {
+			report_directory,
+			spike_sigma,
+			max_gap_days,
+			volume_ratio,
+			thresholds,
+			holidays,
+			ticker_holidays
+		};
+		Ok(configuration)
+	}
+
+	fn get_holidays(&self, root: &str) -> &HashSet<NaiveDate> {
+		self.ticker_holidays
+			.get(&root.to_uppercase())
+			.unwrap_or(&self.holidays)
+	}
+
+	fn parse_holidays(dates: &str, section: &str) -> Result<HashSet<NaiveDate>> {
+		dates
+			.split(',')
+			.map(|x| x.trim())
+			.filter(|x| !x.is_empty())
+			.map(|x| NaiveDate::parse_from_str(x, HOLIDAY_FORMAT)
+				.with_context(|| anyhow!("Invalid holiday \"{x}\" in section \"{section}\"")))
+			.collect()
+	}
+}
+
+impl ValidationReport {
+	pub fn new(symbol: &str) -> ValidationReport {
+		ValidationReport {
+			symbol: symbol.to_string(),
+			daily_records: 0,
+			intraday_records: 0,
+			tick_records: 0,
+			dropped_rows: 0,
+			severity: Severity::Ok,
+			issues: Vec::new()
+		}
+	}
+
+	pub fn add_issue(&mut self, kind: IssueKind, message: impl FnOnce() -> String) {
+		if kind == IssueKind::DroppedRow {
+			self.dropped_rows += 1;
+		}
+		let summary = match self.issues.iter_mut().find(|x| x.kind == kind) {
+			Some(summary) => summary,
+			None => {
+				let summary = IssueSummary {
+					kind,
+					count: 0,
+					severity: Severity::Ok,
+					examples: Vec::new()
+				};
+				self.issues.push(summary);
+				self.issues.last_mut().unwrap()
+			}
+		};
+		summary.count += 1;
+		if summary.examples.len() < MAX_EXAMPLES {
+			summary.examples.push(message());
+		}
+	}
+
+	pub fn set_tick_records(&mut self, tick_records: usize) {
+		self.tick_records = tick_records;
+	}
+
+	// The root of the ticker selects the holidays of the calendar gap check
+	pub fn check_records(&mut self, records: &[RawOhlcRecord], daily: bool, root: &str, configuration: &ValidationConfiguration) {
+		if daily {
+			self.daily_records = records.len();
+			self.check_calendar(records, root, configuration);
+		} else {
+			self.intraday_records += records.len();
+		}
+		for record in records {
+			self.check_record(record);
+		}
+		for contract_records in Self::get_contracts(records).values() {
+			self.check_volume(contract_records, configuration);
+			self.check_open_interest(contract_records);
+			self.check_spikes(contract_records, configuration);
+		}
+	}
+
+	/*
+	Determines the severity of each kind of issue, prints a summary and writes the JSON report if configured.
+	Fails if any of the fail thresholds has been reached.
+	*/
+	pub fn evaluate(&mut self, configuration: &ValidationConfiguration) -> Result<()> {
+		for summary in self.issues.iter_mut() {
+			let threshold = configuration.thresholds[&summary.kind];
+			summary.severity = if threshold.fail.is_some_and(|x| summary.count >= x) {
+				Severity::Error
+			} else if threshold.warn.is_some_and(|x| summary.count >= x) {
+				Severity::Warning
+			} else {
+				Severity::Ok
+			};
+		}
+		self.severity = if self.issues.iter().any(|x| x.severity == Severity::Error) {
+			Severity::Error
+		} else if self.issues.iter().any(|x| x.severity == Severity::Warning) {
+			Severity::Warning
+		} else {
+			Severity::Ok
+		};
+		for summary in self.issues.iter().filter(|x| x.severity != Severity::Ok) {
+			let label = if summary.severity == Severity::Error { "Error" } else { "Warning" };
+			println!("{label}: {} issue(s) of type {} in {}, e.g. {}", summary.count, summary.kind.get_name(), self.symbol, summary.examples.join("; "));
+		}
+		if let Some(report_directory) = &configuration.report_directory {
+			self.write(report_directory)?;
+		}
+		if self.severity == Severity::Error {
+			bail!("Data for {} failed validation", self.symbol);
+		}
+		Ok(())
+	}
+
+	fn write(&self, report_directory: &PathBuf) -> Result<()> {
+		fs::create_dir_all(report_directory)
+			.with_context(|| anyhow!("Failed to create report directory \"{}\"", report_directory.to_string()))?;
+		let file_name = format!("{}.validation.json", self.symbol);
+		let path = Path::new(report_directory).join(file_name);
+		let data = serde_json::to_vec_pretty(self)?;
+		fs::write(&path, data)
+			.with_context(|| anyhow!("Failed to write validation report to \"{}\"", path.to_string()))?;
+		Ok(())
+	}
+
+	fn check_record(&mut self, record: &RawOhlcRecord) {
+		let prices = [record.open, record.high, record.low, record.close];
+		if prices.iter().any(|x| *x <= 0.0) {
+			self.add_issue(IssueKind::NonPositivePrice, || format!("{} {}: {:?}", record.symbol, record.time, prices));
+		}
+		let in_range = |x: f64| x >= record.low && x <= record.high;
+		if record.high < record.low || !in_range(record.open) || !in_range(record.close) {
+			self.add_issue(IssueKind::InvalidRange, || format!(
+				"{} {}: open {}, high {}, low {}, close {}",
+				record.symbol,
+				record.time,
+				record.open,
+				record.high,
+				record.low,
+				record.close
+			));
+		}
+	}
+
+	fn check_calendar(&mut self, records: &[RawOhlcRecord], root: &str, configuration: &ValidationConfiguration) {
+		let holidays = configuration.get_holidays(root);
+		let max_gap_days = configuration.max_gap_days.unwrap_or(if holidays.is_empty() { 3 } else { 0 });
+		let mut dates: Vec<NaiveDate> = records
+			.iter()
+			.map(|x| x.time.date())
+			.collect();
+		dates.sort();
+		dates.dedup();
+		for window in dates.windows(2) {
+			let [previous, next] = window else {
+				continue;
+			};
+			let missing = previous
+				.iter_days()
+				.skip(1)
+				.take_while(|x| x < next)
+				.filter(|x| !matches!(x.weekday(), Weekday::Sat | Weekday::Sun) && !holidays.contains(x))
+				.count();
+			if missing > max_gap_days {
+				self.add_issue(IssueKind::CalendarGap, || format!("{missing} trading days missing between {previous} and {next}"));
+			}
+		}
+	}
+
+	fn check_volume(&mut self, records: &[&RawOhlcRecord], configuration: &ValidationConfiguration) {
+		let mut volumes: Vec<u32> = records
+			.iter()
+			.map(|x| x.volume)
+			.collect();
+		volumes.sort_unstable();
+		let median = volumes[volumes.len() / 2] as f64;
+		for record in records {
+			if record.volume == 0 {
+				self.add_issue(IssueKind::VolumeAnomaly, || format!("{} {}: zero volume", record.symbol, record.time));
+			} else if median > 0.0 && record.volume as f64 > configuration.volume_ratio * median {
+				self.add_issue(IssueKind::VolumeAnomaly, || format!("{} {}: volume {} exceeds median {median}", record.symbol, record.time, record.volume));
+			}
+		}
+	}
+
+	fn check_open_interest(&mut self, records: &[&RawOhlcRecord]) {
+		let has_open_interest = records.iter().any(|x| x.open_interest.is_some());
+		if !has_open_interest {
+			return;
+		}
+		for record in records {
+			match record.open_interest {
+				Some(0) => self.add_issue(IssueKind::OpenInterestAnomaly, || format!("{} {}: zero open interest", record.symbol, record.time)),
+				None => self.add_issue(IssueKind::OpenInterestAnomaly, || format!("{} {}: missing open interest", record.symbol, record.time)),
+				_ => {}
+			}
+		}
+	}
+
+	fn check_spikes(&mut self, records: &[&RawOhlcRecord], configuration: &ValidationConfiguration) {
+		let returns: Vec<(&RawOhlcRecord, f64)> = records
+			.windows(2)
+			.filter(|x| x[0].close > 0.0 && x[1].close > 0.0)
+			.map(|x| (x[1], (x[1].close / x[0].close).ln()))
+			.collect();
+		if returns.len() < 2 {
+			return;
+		}
+		let count = returns.len() as f64;
+		let mean = returns.iter().map(|(_, x)| x).sum::<f64>() / count;
+		let variance = returns.iter().map(|(_, x)| (x - mean).powi(2)).sum::<f64>() / (count - 1.0);
+		let sigma = variance.sqrt();
+		if sigma == 0.0 {
+			return;
+		}
+		for (record, log_return) in returns {
+			let deviation = (log_return - mean).abs() / sigma;
+			if deviation > configuration.spike_sigma {
+				self.add_issue(IssueKind::PriceSpike, || format!("{} {}: close {} deviates by {deviation:.1} sigma", record.symbol, record.time, record.close));
+			}
+		}
+	}
+
+	// Groups records by contract, preserving their chronological order
+	fn get_contracts(records: &[RawOhlcRecord]) -> BTreeMap<&str, Vec<&RawOhlcRecord>> {
+		let mut contracts: BTreeMap<&str, Vec<&RawOhlcRecord>> = BTreeMap::new();
+		for record in records {
+			contracts
+				.entry(record.symbol.as_str())
+				.or_default()
+				.push(record);
+		}
+		contracts
+	}
+}
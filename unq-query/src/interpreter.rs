@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use anyhow::{anyhow, bail, Context, Result};
+use unq_common::api::{GetCorrelationRequest, GetHistoryRequest, RunBacktestRequest};
+use unq_common::datetime::{RelativeDateTime, SpecialDateTime};
+use unq_common::ohlc::TimeFrame;
+use unq_common::strategy::StrategyParameter;
+use crate::parser::parse;
+use crate::syntax::{Statement, Value, MINUTES_PER_DAY};
+
+// Requests generated by the calls in a program, in the order in which they were made
+#[derive(Clone)]
+pub enum Command {
+	History(GetHistoryRequest),
+	Correlation(GetCorrelationRequest),
+	Backtest(RunBacktestRequest)
+}
+
+/*
+Evaluates programs the same way the scripting engine of the web UI does.
+Variables persist across multiple runs, which makes it possible to execute a notebook one cell at a time.
+*/
+pub struct Interpreter {
+	variables: HashMap<String, Value>
+}
+
+impl Interpreter {
+	pub fn new() -> Interpreter {
+		Interpreter {
+			variables: HashMap::new()
+		}
+	}
+
+	pub fn get_variables(&self) -> &HashMap<String, Value> {
+		&self.variables
+	}
+
+	// The entire program is evaluated before any of the requests are returned so that errors are detected early
+	pub fn run(&mut self, source: &str) -> Result<Vec<Command>> {
+		let statements = parse(source)?;
+		let mut commands = Vec::new();
+		for statement in statements {
+			match statement {
+				Statement::Assignment { variable, value } => {
+					let value = self.substitute_variable(value)?;
+					self.variables.insert(variable, value);
+				},
+				Statement::Call { name, arguments } => {
+					let arguments = arguments
+						.into_iter()
+						.map(|x| self.substitute_variable(x))
+						.collect::<Result<Vec<Value>>>()?;
+					let command = Self::call(name.as_str(), arguments)
+						.with_context(|| anyhow!("Call \"{name}\" failed"))?;
+					if let Some(command) = command {
+						commands.push(command);
+					}
+				}
+			}
+		}
+		Ok(commands)
+	}
+
+	fn substitute_variable(&self, value: Value) -> Result<Value> {
+		match value {
+			Value::Variable(name) => {
+				let Some(value) = self.variables.get(&name) else {
+					bail!("Unknown variable: {name}");
+				};
+				Ok(value.clone())
+			},
+			_ => Ok(value)
+		}
+	}
+
+	fn call(name: &str, arguments: Vec<Value>) -> Result<Option<Command>> {
+		match name {
+			"candle" => Self::plot(arguments, true),
+			"plot" => Self::plot(arguments, false),
+			"correlation" => Self::correlation(arguments),
+			"backtest" => Self::backtest(arguments),
+			"script" => Self::script(arguments),
+			// These calls only affect the state of the web UI
+			"clear" | "history" | "clearHistory" => {
+				validate_argument_count(&arguments, 0, 0)?;
+				Ok(None)
+			},
+			_ => bail!("Unknown call: {name}")
+		}
+	}
+
+	// plot symbols, from, to, time frame
+	fn plot(arguments: Vec<Value>, is_candlestick: bool) -> Result<Option<Command>> {
+		validate_argument_count(&arguments, 1, 4)?;
+		let symbols = if is_candlestick {
+			let Value::Symbol(symbol) = &arguments[0] else {
+				bail!("Invalid symbol argument type");
+			};
+			vec![symbol.name.clone()]
+		} else {
+			get_symbols(&arguments[0])?
+		};
+		let (from, to) = get_from_to(arguments.get(1), arguments.get(2), true)?;
+		let time_frame = match arguments.get(3) {
+			Some(Value::TimeFrame(time_frame)) if (1..=MINUTES_PER_DAY).contains(time_frame) => *time_frame,
+			None => MINUTES_PER_DAY,
+			_ => bail!("Invalid time frame specified")
+		};
+		let request = GetHistoryRequest {
+			symbols,
+			from,
+			to,
+			time_frame,
+			bars: None,
+			period: None,
+			alignment: None
+		};
+		Ok(Some(Command::History(request)))
+	}
+
+	// correlation symbols, from, to
+	fn correlation(arguments: Vec<Value>) -> Result<Option<Command>> {
+		validate_argument_count(&arguments, 1, 3)?;
+		let symbols = get_symbols(&arguments[0])?;
+		let (from, to) = get_from_to(arguments.get(1), arguments.get(2), true)?;
+		let request = GetCorrelationRequest {
+			symbols,
+			from,
+			to
+		};
+		Ok(Some(Command::Correlation(request)))
+	}
+
+	// backtest "strategy", symbols, from, to, parameters, "time frame"
+	fn backtest(arguments: Vec<Value>) -> Result<Option<Command>> {
+		validate_argument_count(&arguments, 4, 6)?;
+		let Value::String(strategy) = &arguments[0] else {
+			bail!("Invalid strategy argument type");
+		};
+		let symbols = get_symbols(&arguments[1])?;
+		let (from, to) = get_from_to(arguments.get(2), arguments.get(3), false)?;
+		let parameters = match arguments.get(4) {
+			Some(Value::Parameters(parameters)) => parameters.clone(),
+			None => Vec::new(),
+			_ => bail!("Invalid parameters argument type")
+		};
+		let time_frame = match arguments.get(5) {
+			Some(Value::String(time_frame)) if time_frame == "daily" => TimeFrame::Daily,
+			Some(Value::String(time_frame)) if time_frame == "intraday" => TimeFrame::Intraday,
+			Some(Value::String(time_frame)) if time_frame == "weekly" => TimeFrame::Weekly,
+			Some(Value::String(time_frame)) if time_frame == "monthly" => TimeFrame::Monthly,
+			None => TimeFrame::Daily,
+			_ => bail!("Invalid time frame argument type")
+		};
+		let request = RunBacktestRequest {
+			strategy: strategy.clone(),
+			symbols,
+			from,
+			to,
+			parameters,
+			time_frame,
+			benchmark: None,
+			minimum_acceptable_return: None
+		};
+		Ok(Some(Command::Backtest(request)))
+	}
+
+	// script "name", symbols, from, to, parameters, "time frame"
+	fn script(mut arguments: Vec<Value>) -> Result<Option<Command>> {
+		validate_argument_count(&arguments, 4, 6)?;
+		let Value::String(script) = arguments[0].clone() else {
+			bail!("Invalid script argument type");
+		};
+		let script_parameter = StrategyParameter {
+			name: "script".to_string(),
+			value: None,
+			limit: None,
+			increment: None,
+			values: None,
+			bool_value: None,
+			string_value: Some(script),
+			string_values: None
+		};
+		match arguments.get_mut(4) {
+			Some(Value::Parameters(parameters)) => parameters.push(script_parameter),
+			Some(_) => bail!("Invalid parameters argument type"),
+			None => arguments.push(Value::Parameters(vec![script_parameter]))
+		}
+		arguments[0] = Value::String("script".to_string());
+		Self::backtest(arguments)
+	}
+}
+
+impl Default for Interpreter {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+fn validate_argument_count(arguments: &[Value], min: usize, max: usize) -> Result<()> {
+	if arguments.len() < min || arguments.len() > max {
+		bail!("Invalid number of arguments");
+	}
+	Ok(())
+}
+
+fn get_symbols(argument: &Value) -> Result<Vec<String>> {
+	match argument {
+		Value::Symbol(symbol) => Ok(vec![symbol.name.clone()]),
+		Value::SymbolArray(symbols) => Ok(symbols
+			.iter()
+			.map(|x| x.name.clone())
+			.collect()),
+		_ => bail!("Invalid symbol argument type")
+	}
+}
+
+/*
+At least one of the two arguments must be an absolute point in time or a keyword, the other one may be an offset.
+Missing arguments default to "first" and "last" if use_defaults is set.
+*/
+fn get_from_to(from: Option<&Value>, to: Option<&Value>, use_defaults: bool) -> Result<(RelativeDateTime, RelativeDateTime)> {
+	let get_time = |argument: Option<&Value>, default: SpecialDateTime| -> Result<(RelativeDateTime, bool)> {
+		match argument {
+			Some(Value::DateTime(date)) => Ok((RelativeDateTime::from_date(*date), false)),
+			Some(Value::Keyword(keyword)) => Ok((RelativeDateTime::from_keyword(keyword.clone()), false)),
+			Some(Value::Offset(offset, offset_unit)) => Ok((RelativeDateTime::from_offset(*offset, offset_unit.clone()), true)),
+			None if use_defaults => Ok((RelativeDateTime::from_keyword(default), false)),
+			_ => bail!("Invalid from/to parameter types")
+		}
+	};
+	let (from, from_is_offset) = get_time(from, SpecialDateTime::First)?;
+	let (to, to_is_offset) = get_time(to, SpecialDateTime::Last)?;
+	if from_is_offset && to_is_offset {
+		bail!("Invalid from/to parameter types");
+	}
+	Ok((from, to))
+}
@@ -0,0 +1,8 @@
+/*
+Server-side implementation of the Unquantified prompt language defined in web/scripts/unquantified.ohm.
+Programs are parsed into statements which are then evaluated by the interpreter, producing the same
+request structures that the web UI sends to the server.
+*/
+pub mod syntax;
+pub mod parser;
+pub mod interpreter;
@@ -0,0 +1,677 @@
+use anyhow::{bail, Result};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use unq_common::datetime::{OffsetUnit, SpecialDateTime};
+use unq_common::strategy::StrategyParameter;
+use unq_common::web::WebF64;
+use crate::syntax::{Statement, Symbol, Value, MINUTES_PER_DAY};
+
+type Rule<T> = fn(&mut Parser) -> Option<T>;
+
+/*
+Recursive descent parser that mirrors the rules of unquantified.ohm.
+Like in Ohm, alternatives are ordered and backtrack on failure.
+Syntactic rules (capitalized in the grammar) skip spaces and tabs before each of their elements
+whereas lexical rules (lower case) don't skip anything.
+*/
+pub struct Parser {
+	input: Vec<char>,
+	position: usize,
+	// Rightmost position at which a rule failed, reported in parser errors
+	failure_position: usize
+}
+
+pub fn parse(source: &str) -> Result<Vec<Statement>> {
+	let mut parser = Parser {
+		input: source.chars().collect(),
+		position: 0,
+		failure_position: 0
+	};
+	parser.program()
+}
+
+impl Parser {
+	// Program = Statement*
+	fn program(&mut self) -> Result<Vec<Statement>> {
+		let mut statements = Vec::new();
+		while let Some(statement) = self.attempt(Self::statement) {
+			statements.push(statement);
+		}
+		self.skip_spaces();
+		if !self.is_end() {
+			let (line, column) = self.get_line_column(self.failure_position.max(self.position));
+			bail!("Failed to parse query (line {line}, column {column})");
+		}
+		Ok(statements)
+	}
+
+	// Statement = (Assignment | Call | SimpleCall) (eol | end)
+	fn statement(&mut self) -> Option<Statement> {
+		let rules: [Rule<Statement>; 3] = [
+			Self::assignment,
+			Self::call,
+			Self::simple_call
+		];
+		let statement = self.first_match(&rules)?;
+		self.skip_spaces();
+		if self.literal("\r\n") || self.literal("\n") || self.is_end() {
+			Some(statement)
+		} else {
+			None
+		}
+	}
+
+	// Assignment = variable "=" (Value | Parameters)
+	fn assignment(&mut self) -> Option<Statement> {
+		self.skip_spaces();
+		let variable = self.variable()?;
+		self.token("=")?;
+		// Value already includes Parameters
+		let value = self.value()?;
+		let assignment = Statement::Assignment {
+			variable,
+			value
+		};
+		Some(assignment)
+	}
+
+	// Call = identifier Value ("," Value)*
+	fn call(&mut self) -> Option<Statement> {
+		self.skip_spaces();
+		let name = self.identifier()?;
+		let mut arguments = vec![self.value()?];
+		while let Some(value) = self.attempt(|parser| {
+			parser.token(",")?;
+			parser.value()
+		}) {
+			arguments.push(value);
+		}
+		let call = Statement::Call {
+			name,
+			arguments
+		};
+		Some(call)
+	}
+
+	// SimpleCall = identifier
+	fn simple_call(&mut self) -> Option<Statement> {
+		self.skip_spaces();
+		let name = self.identifier()?;
+		let call = Statement::Call {
+			name,
+			arguments: Vec::new()
+		};
+		Some(call)
+	}
+
+	// Value = variable | dateTime | date | offset | timeFrame | symbol | numeric | keyword | stringValue | SymbolArray | Parameters
+	fn value(&mut self) -> Option<Value> {
+		self.skip_spaces();
+		let rules: [Rule<Value>; 11] = [
+			|parser| parser.variable().map(Value::Variable),
+			|parser| parser.date_time().map(Value::DateTime),
+			|parser| parser.date().map(|x| Value::DateTime(x.and_time(NaiveTime::MIN))),
+			|parser| parser.offset().map(|(offset, unit)| Value::Offset(offset, unit)),
+			|parser| parser.time_frame().map(Value::TimeFrame),
+			|parser| parser.symbol().map(|name| Value::Symbol(Symbol { name, separator: false })),
+			|parser| parser.numeric().map(Value::Numeric),
+			Self::keyword,
+			|parser| parser.string().map(Value::String),
+			|parser| parser.symbol_array().map(Value::SymbolArray),
+			|parser| parser.parameters().map(Value::Parameters)
+		];
+		self.first_match(&rules)
+	}
+
+	// Parameters = "{" whitespace* Parameter ("," whitespace* Parameter)* whitespace* "}"
+	fn parameters(&mut self) -> Option<Vec<StrategyParameter>> {
+		self.token("{")?;
+		self.skip_whitespace();
+		let mut parameters = vec![self.parameter()?];
+		while let Some(parameter) = self.attempt(|parser| {
+			parser.token(",")?;
+			parser.skip_whitespace();
+			parser.parameter()
+		}) {
+			parameters.push(parameter);
+		}
+		self.skip_whitespace();
+		self.token("}")?;
+		Some(parameters)
+	}
+
+	// Parameter = identifier ":" (ValueRangeParameter | MultiValueParameter | MultiStringParameter | stringParameter | bool)
+	fn parameter(&mut self) -> Option<StrategyParameter> {
+		self.skip_spaces();
+		let name = self.identifier()?;
+		self.token(":")?;
+		self.skip_spaces();
+		let rules: [Rule<StrategyParameter>; 5] = [
+			Self::value_range_parameter,
+			Self::multi_value_parameter,
+			Self::multi_string_parameter,
+			|parser| {
+				let string = parser.string()?;
+				let mut parameter = get_empty_parameter();
+				parameter.string_value = Some(string);
+				Some(parameter)
+			},
+			|parser| {
+				let bool_value = parser.bool()?;
+				let mut parameter = get_empty_parameter();
+				parameter.bool_value = Some(bool_value);
+				Some(parameter)
+			}
+		];
+		let mut parameter = self.first_match(&rules)?;
+		parameter.name = name;
+		Some(parameter)
+	}
+
+	// ValueRangeParameter = numeric ("to" numeric ("step" numeric)?)?
+	fn value_range_parameter(&mut self) -> Option<StrategyParameter> {
+		self.skip_spaces();
+		let value = self.numeric()?;
+		let mut parameter = get_empty_parameter();
+		parameter.value = Some(WebF64::new(value));
+		let range = self.attempt(|parser| {
+			parser.token("to")?;
+			parser.skip_spaces();
+			let limit = parser.numeric()?;
+			let increment = parser.attempt(|parser| {
+				parser.token("step")?;
+				parser.skip_spaces();
+				parser.numeric()
+			});
+			Some((limit, increment))
+		});
+		if let Some((limit, increment)) = range {
+			parameter.limit = Some(WebF64::new(limit));
+			parameter.increment = increment.map(WebF64::new);
+		}
+		Some(parameter)
+	}
+
+	// MultiValueParameter = "[" numeric ("," numeric)* "]"
+	fn multi_value_parameter(&mut self) -> Option<StrategyParameter> {
+		let values = self.array(|parser| parser.numeric().map(WebF64::new))?;
+		let mut parameter = get_empty_parameter();
+		parameter.values = Some(values);
+		Some(parameter)
+	}
+
+	// MultiStringParameter = "[" string ("," string)* "]"
+	fn multi_string_parameter(&mut self) -> Option<StrategyParameter> {
+		let string_values = self.array(Self::string)?;
+		let mut parameter = get_empty_parameter();
+		parameter.string_values = Some(string_values);
+		Some(parameter)
+	}
+
+	// SymbolArray = "[" symbol SeparatedSymbol* "]", SeparatedSymbol = ("," | "|") symbol
+	fn symbol_array(&mut self) -> Option<Vec<Symbol>> {
+		self.token("[")?;
+		self.skip_spaces();
+		let first = Symbol {
+			name: self.symbol()?,
+			separator: false
+		};
+		let mut symbols = vec![first];
+		while let Some(symbol) = self.attempt(|parser| {
+			parser.skip_spaces();
+			let separator = if parser.literal("|") {
+				true
+			} else if parser.literal(",") {
+				false
+			} else {
+				return None;
+			};
+			parser.skip_spaces();
+			let symbol = Symbol {
+				name: parser.symbol()?,
+				separator
+			};
+			Some(symbol)
+		}) {
+			symbols.push(symbol);
+		}
+		self.token("]")?;
+		Some(symbols)
+	}
+
+	// identifier = lower (alnum | "_")*
+	fn identifier(&mut self) -> Option<String> {
+		let start = self.position;
+		self.character(|x| x.is_lowercase())?;
+		while self.character(|x| x.is_alphanumeric() || x == '_').is_some() {}
+		Some(self.get_text(start))
+	}
+
+	// variable = "$" identifier
+	fn variable(&mut self) -> Option<String> {
+		self.expect("$")?;
+		self.identifier()
+	}
+
+	// numeric = (numericPrefix | "0") ("." digit+)?, numericPrefix = "-"? nonZeroDigit digit*
+	fn numeric(&mut self) -> Option<f64> {
+		let start = self.position;
+		let prefix = self.attempt(|parser| {
+			parser.literal("-");
+			parser.non_zero_digit()?;
+			parser.digits(0);
+			Some(())
+		});
+		if prefix.is_none() {
+			self.expect("0")?;
+		}
+		self.attempt(|parser| {
+			parser.expect(".")?;
+			parser.digits(1)
+		});
+		self.get_text(start).parse().ok()
+	}
+
+	// date = nonZeroDigit digit digit digit "-" digit digit "-" digit digit
+	fn date(&mut self) -> Option<NaiveDate> {
+		let start = self.position;
+		self.non_zero_digit()?;
+		self.position = start;
+		let year = self.fixed_digits(4)?;
+		self.expect("-")?;
+		let month = self.fixed_digits(2)?;
+		self.expect("-")?;
+		let day = self.fixed_digits(2)?;
+		NaiveDate::from_ymd_opt(year as i32, month, day)
+	}
+
+	// dateTime = date space+ digit digit ":" digit digit (":" digit digit)?
+	fn date_time(&mut self) -> Option<NaiveDateTime> {
+		let date = self.date()?;
+		self.character(is_space)?;
+		self.skip_spaces();
+		let hours = self.fixed_digits(2)?;
+		self.expect(":")?;
+		let minutes = self.fixed_digits(2)?;
+		let seconds = self.attempt(|parser| {
+			parser.expect(":")?;
+			parser.fixed_digits(2)
+		});
+		let time = NaiveTime::from_hms_opt(hours, minutes, seconds.unwrap_or(0))?;
+		Some(date.and_time(time))
+	}
+
+	// offset = ("+" | "-") nonZeroDigit digit* ("mo" | "m" | "h" | "d" | "w" | "y")
+	fn offset(&mut self) -> Option<(i16, OffsetUnit)> {
+		let start = self.position;
+		if !self.literal("+") {
+			self.expect("-")?;
+		}
+		self.non_zero_digit()?;
+		self.digits(0);
+		let offset = self.get_text(start).parse::<i16>().ok()?;
+		let units = [
+			("mo", OffsetUnit::Months),
+			("m", OffsetUnit::Minutes),
+			("h", OffsetUnit::Hours),
+			("d", OffsetUnit::Days),
+			("w", OffsetUnit::Weeks),
+			("y", OffsetUnit::Years)
+		];
+		let (_, unit) = units
+			.into_iter()
+			.find(|(text, _)| self.literal(text))?;
+		Some((offset, unit))
+	}
+
+	// timeFrame = nonZeroDigit digit* ("m" | "h")
+	fn time_frame(&mut self) -> Option<u16> {
+		let start = self.position;
+		self.non_zero_digit()?;
+		self.digits(0);
+		let time_frame = self.get_text(start).parse::<u16>().ok()?;
+		if self.literal("m") {
+			Some(time_frame)
+		} else if self.literal("h") {
+			time_frame.checked_mul(60)
+		} else {
+			None
+		}
+	}
+
+	// symbol = (upper | nonZeroDigit) (upper | nonZeroDigit)+
+	fn symbol(&mut self) -> Option<String> {
+		let start = self.position;
+		let is_symbol_character = |x: char| x.is_uppercase() || ('1'..='9').contains(&x);
+		self.character(is_symbol_character)?;
+		self.character(is_symbol_character)?;
+		while self.character(is_symbol_character).is_some() {}
+		Some(self.get_text(start))
+	}
+
+	// string = stringDelimiter (~stringDelimiter any)* stringDelimiter
+	fn string(&mut self) -> Option<String> {
+		self.expect("\"")?;
+		let start = self.position;
+		while self.character(|x| x != '"').is_some() {}
+		let string = self.get_text(start);
+		self.expect("\"")?;
+		Some(string)
+	}
+
+	// keyword = "true" | "false" | "first" | "last" | "now" | "daily" | "all"
+	fn keyword(&mut self) -> Option<Value> {
+		let keywords = [
+			("true", Value::Bool(true)),
+			("false", Value::Bool(false)),
+			("first", Value::Keyword(SpecialDateTime::First)),
+			("last", Value::Keyword(SpecialDateTime::Last)),
+			("now", Value::Keyword(SpecialDateTime::Now)),
+			("daily", Value::TimeFrame(MINUTES_PER_DAY)),
+			("all", Value::Symbol(Symbol { name: "all".to_string(), separator: false }))
+		];
+		keywords
+			.into_iter()
+			.find(|(text, _)| self.literal(text))
+			.map(|(_, value)| value)
+	}
+
+	// bool = "true" | "false"
+	fn bool(&mut self) -> Option<bool> {
+		if self.literal("true") {
+			Some(true)
+		} else if self.literal("false") {
+			Some(false)
+		} else {
+			None
+		}
+	}
+
+	// Runs a rule and restores the previous position if it fails
+	fn attempt<T>(&mut self, rule: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+		let position = self.position;
+		let output = rule(self);
+		if output.is_none() {
+			self.failure_position = self.failure_position.max(self.position);
+			self.position = position;
+		}
+		output
+	}
+
+	fn first_match<T>(&mut self, rules: &[Rule<T>]) -> Option<T> {
+		rules
+			.iter()
+			.find_map(|rule| self.attempt(rule))
+	}
+
+	// "[" element ("," element)* "]" within a syntactic rule
+	fn array<T>(&mut self, element: Rule<T>) -> Option<Vec<T>> {
+		self.token("[")?;
+		self.skip_spaces();
+		let mut elements = vec![element(self)?];
+		while let Some(value) = self.attempt(|parser| {
+			parser.token(",")?;
+			parser.skip_spaces();
+			element(parser)
+		}) {
+			elements.push(value);
+		}
+		self.token("]")?;
+		Some(elements)
+	}
+
+	// Matches a literal within a syntactic rule
+	fn token(&mut self, text: &str) -> Option<()> {
+		self.skip_spaces();
+		self.expect(text)
+	}
+
+	fn expect(&mut self, text: &str) -> Option<()> {
+		if self.literal(text) {
+			Some(())
+		} else {
+			None
+		}
+	}
+
+	fn literal(&mut self, text: &str) -> bool {
+		let end = self.position + text.chars().count();
+		let matches = end <= self.input.len() && self.input[self.position..end]
+			.iter()
+			.copied()
+			.eq(text.chars());
+		if matches {
+			self.position = end;
+		}
+		matches
+	}
+
+	fn character(&mut self, predicate: impl Fn(char) -> bool) -> Option<char> {
+		let character = *self.input.get(self.position)?;
+		if predicate(character) {
+			self.position += 1;
+			Some(character)
+		} else {
+			None
+		}
+	}
+
+	fn non_zero_digit(&mut self) -> Option<char> {
+		self.character(|x| ('1'..='9').contains(&x))
+	}
+
+	// Consumes as many digits as possible and fails if there are fewer than the minimum
+	fn digits(&mut self, minimum: usize) -> Option<()> {
+		let mut count = 0;
+		while self.character(|x| x.is_ascii_digit()).is_some() {
+			count += 1;
+		}
+		if count >= minimum {
+			Some(())
+		} else {
+			None
+		}
+	}
+
+	fn fixed_digits(&mut self, count: usize) -> Option<u32> {
+		let start = self.position;
+		for _ in 0..count {
+			self.character(|x| x.is_ascii_digit())?;
+		}
+		self.get_text(start).parse().ok()
+	}
+
+	// The space rule is overridden in the grammar so line breaks are significant
+	fn skip_spaces(&mut self) {
+		while self.character(is_space).is_some() {}
+	}
+
+	fn skip_whitespace(&mut self) {
+		while self.character(|x| is_space(x) || x == '\r' || x == '\n').is_some() {}
+	}
+
+	fn is_end(&self) -> bool {
+		self.position >= self.input.len()
+	}
+
+	fn get_text(&self, start: usize) -> String {
+		self.input[start..self.position].iter().collect()
+	}
+
+	fn get_line_column(&self, position: usize) -> (usize, usize) {
+		let preceding = &self.input[..position.min(self.input.len())];
+		let line = preceding
+			.iter()
+			.filter(|x| **x == '\n')
+			.count() + 1;
+		let column = preceding
+			.iter()
+			.rev()
+			.take_while(|x| **x != '\n')
+			.count() + 1;
+		(line, column)
+	}
+}
+
+fn is_space(character: char) -> bool {
+	character == ' ' || character == '\t'
+}
+
+fn get_empty_parameter() -> StrategyParameter {
+	StrategyParameter {
+		name: String::new(),
+		value: None,
+		limit: None,
+		increment: None,
+		values: None,
+		bool_value: None,
+		string_value: None,
+		string_values: None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use chrono::NaiveDate;
+	use unq_common::datetime::{OffsetUnit, SpecialDateTime};
+	use crate::syntax::{Statement, Value, MINUTES_PER_DAY};
+	use super::parse;
+
+	fn parse_value(source: &str) -> Value {
+		let statements = parse(&format!("$x = {source}")).unwrap();
+		match statements.as_slice() {
+			[Statement::Assignment { value, .. }] => value.clone(),
+			_ => panic!("Unexpected statements: {statements:?}")
+		}
+	}
+
+	fn get_error(source: &str) -> String {
+		parse(source).unwrap_err().to_string()
+	}
+
+	#[test]
+	fn offsets() {
+		let offsets = [
+			("+5m", 5, OffsetUnit::Minutes),
+			("-2h", -2, OffsetUnit::Hours),
+			("+10d", 10, OffsetUnit::Days),
+			("-1w", -1, OffsetUnit::Weeks),
+			("+3mo", 3, OffsetUnit::Months),
+			("-20y", -20, OffsetUnit::Years)
+		];
+		for (source, expected_offset, expected_unit) in offsets {
+			match parse_value(source) {
+				Value::Offset(offset, unit) => {
+					assert_eq!(offset, expected_offset, "{source}");
+					assert_eq!(unit, expected_unit, "{source}");
+				},
+				value => panic!("Unexpected value for {source}: {value:?}")
+			}
+		}
+		// The grammar requires a sign and a non-zero leading digit
+		assert!(matches!(parse_value("5m"), Value::TimeFrame(5)));
+		assert!(parse("$x = +0d").is_err());
+		assert!(parse("$x = +5s").is_err());
+	}
+
+	#[test]
+	fn keywords() {
+		assert!(matches!(parse_value("true"), Value::Bool(true)));
+		assert!(matches!(parse_value("false"), Value::Bool(false)));
+		assert!(matches!(parse_value("first"), Value::Keyword(SpecialDateTime::First)));
+		assert!(matches!(parse_value("last"), Value::Keyword(SpecialDateTime::Last)));
+		assert!(matches!(parse_value("now"), Value::Keyword(SpecialDateTime::Now)));
+		assert!(matches!(parse_value("daily"), Value::TimeFrame(MINUTES_PER_DAY)));
+		assert!(matches!(parse_value("all"), Value::Symbol(symbol) if symbol.name == "all"));
+	}
+
+	#[test]
+	fn values() {
+		assert!(matches!(parse_value("$y"), Value::Variable(name) if name == "y"));
+		assert!(matches!(parse_value("4h"), Value::TimeFrame(240)));
+		assert!(matches!(parse_value("NQ"), Value::Symbol(symbol) if symbol.name == "NQ"));
+		assert!(matches!(parse_value("-1.25"), Value::Numeric(x) if x == -1.25));
+		assert!(matches!(parse_value("0.5"), Value::Numeric(x) if x == 0.5));
+		assert!(matches!(parse_value("\"text\""), Value::String(x) if x == "text"));
+		let date = NaiveDate::from_ymd_opt(2020, 4, 1).unwrap();
+		let midnight = date.and_hms_opt(0, 0, 0).unwrap();
+		let afternoon = date.and_hms_opt(15, 30, 10).unwrap();
+		assert!(matches!(parse_value("2020-04-01"), Value::DateTime(x) if x == midnight));
+		assert!(matches!(parse_value("2020-04-01 15:30:10"), Value::DateTime(x) if x == afternoon));
+	}
+
+	#[test]
+	fn symbol_arrays() {
+		let Value::SymbolArray(symbols) = parse_value("[ES, NQ | ZN,ZB]") else {
+			panic!("Expected a symbol array");
+		};
+		let names: Vec<&str> = symbols
+			.iter()
+			.map(|symbol| symbol.name.as_str())
+			.collect();
+		let separators: Vec<bool> = symbols
+			.iter()
+			.map(|symbol| symbol.separator)
+			.collect();
+		assert_eq!(names, ["ES", "NQ", "ZN", "ZB"]);
+		assert_eq!(separators, [false, false, true, false]);
+		assert!(parse("$x = []").is_err());
+		assert!(parse("$x = [ES; NQ]").is_err());
+	}
+
+	#[test]
+	fn parameters() {
+		let source = "$x = {\n\ta: 1 to 10 step 0.5,\n\tb: 2 to 4,\n\tc: 3,\n\td: [1, 2.5, -3],\n\te: [\"x\", \"y\"],\n\tf: \"z\",\n\tg: true\n}";
+		let statements = parse(source).unwrap();
+		let [Statement::Assignment { value: Value::Parameters(parameters), .. }] = statements.as_slice() else {
+			panic!("Unexpected statements: {statements:?}");
+		};
+		let names: Vec<&str> = parameters
+			.iter()
+			.map(|parameter| parameter.name.as_str())
+			.collect();
+		assert_eq!(names, ["a", "b", "c", "d", "e", "f", "g"]);
+		let get = |x: &Option<unq_common::web::WebF64>| x.as_ref().map(|x| x.get());
+		assert_eq!(get(&parameters[0].value), Some(1.0));
+		assert_eq!(get(&parameters[0].limit), Some(10.0));
+		assert_eq!(get(&parameters[0].increment), Some(0.5));
+		assert_eq!(get(&parameters[1].limit), Some(4.0));
+		assert_eq!(get(&parameters[1].increment), None);
+		assert_eq!(get(&parameters[2].value), Some(3.0));
+		assert_eq!(get(&parameters[2].limit), None);
+		let values: Option<Vec<f64>> = parameters[3].values
+			.as_ref()
+			.map(|values| values.iter().map(|x| x.get()).collect());
+		assert_eq!(values, Some(vec![1.0, 2.5, -3.0]));
+		assert_eq!(parameters[4].string_values, Some(vec!["x".to_string(), "y".to_string()]));
+		assert_eq!(parameters[5].string_value, Some("z".to_string()));
+		assert_eq!(parameters[6].bool_value, Some(true));
+		assert!(parse("$x = {}").is_err());
+		assert!(parse("$x = {a: 1 to}").is_err());
+	}
+
+	#[test]
+	fn statements() {
+		let statements = parse("$s = ES\r\nhistory $s, 2020-04-01, last, 1h\ncorrelation\n").unwrap();
+		assert_eq!(statements.len(), 3);
+		assert!(matches!(&statements[1], Statement::Call { name, arguments } if name == "history" && arguments.len() == 4));
+		assert!(matches!(&statements[2], Statement::Call { name, arguments } if name == "correlation" && arguments.is_empty()));
+		assert!(parse("").unwrap().is_empty());
+	}
+
+	#[test]
+	fn comments() {
+		// Like the grammar, the language has no comment syntax so comments are rejected rather than skipped
+		assert_eq!(get_error("// comment"), "Failed to parse query (line 1, column 1)");
+		assert_eq!(get_error("$x = ES # comment"), "Failed to parse query (line 1, column 9)");
+		assert_eq!(get_error("$x = ES\n/* comment */\n$y = NQ"), "Failed to parse query (line 2, column 1)");
+	}
+
+	#[test]
+	fn error_positions() {
+		// The rightmost failure position is reported, as with getRightmostFailurePosition in Ohm
+		assert_eq!(get_error("$x = ES\n$y = [ES, nq]"), "Failed to parse query (line 2, column 11)");
+		assert_eq!(get_error("history ES,"), "Failed to parse query (line 1, column 12)");
+		assert_eq!(get_error("$x = {a: 1 to 5 step}"), "Failed to parse query (line 1, column 21)");
+		assert_eq!(get_error("$x = 2020-13-01"), "Failed to parse query (line 1, column 16)");
+	}
+}
@@ -0,0 +1,63 @@
+use chrono::NaiveDateTime;
+use unq_common::datetime::{OffsetUnit, SpecialDateTime};
+use unq_common::strategy::StrategyParameter;
+
+pub const MINUTES_PER_DAY: u16 = 1440;
+
+#[derive(Clone, Debug)]
+pub enum Statement {
+	// $variable = value
+	Assignment {
+		variable: String,
+		value: Value
+	},
+	// Calls without arguments are represented by an empty vector
+	Call {
+		name: String,
+		arguments: Vec<Value>
+	}
+}
+
+#[derive(Clone, Debug)]
+pub enum Value {
+	// Name of the variable without the "$" prefix
+	Variable(String),
+	DateTime(NaiveDateTime),
+	Offset(i16, OffsetUnit),
+	// Minutes, the "daily" keyword evaluates to 1440
+	TimeFrame(u16),
+	// A symbol like "ES" or the keyword "all"
+	Symbol(Symbol),
+	Numeric(f64),
+	Bool(bool),
+	// The keywords "first", "last" and "now"
+	Keyword(SpecialDateTime),
+	String(String),
+	SymbolArray(Vec<Symbol>),
+	Parameters(Vec<StrategyParameter>)
+}
+
+#[derive(Clone, Debug)]
+pub struct Symbol {
+	pub name: String,
+	// Set if the symbol was preceded by "|" rather than "," in an array, used to group correlation matrices
+	pub separator: bool
+}
+
+impl Value {
+	pub fn get_type_name(&self) -> &str {
+		match self {
+			Value::Variable(_) => "variable",
+			Value::DateTime(_) => "date",
+			Value::Offset(_, _) => "offset",
+			Value::TimeFrame(_) => "time frame",
+			Value::Symbol(_) => "symbol",
+			Value::Numeric(_) => "numeric",
+			Value::Bool(_) => "bool",
+			Value::Keyword(_) => "keyword",
+			Value::String(_) => "string",
+			Value::SymbolArray(_) => "symbol array",
+			Value::Parameters(_) => "parameters"
+		}
+	}
+}
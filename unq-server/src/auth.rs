@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, bail, Context, Result};
+use axum::extract::{Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::Next;
+use axum::response::IntoResponse;
+use axum::Json;
+use chrono::Local;
+use configparser::ini::Ini;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use unq_common::api::Response;
+use unq_common::{get_hash, get_ini_optional, get_ini_string};
+
+const AUTH_SECTION: &str = "auth";
+// Each API key is defined in its own section, e.g. [key.alice]
+const KEY_SECTION_PREFIX: &str = "key.";
+const API_KEY_HEADER: &str = "x-api-key";
+// EventSource doesn't support custom headers so /jobs/stream also accepts the key in the query string
+const API_KEY_PARAMETER: &str = "key";
+const STREAM_PATH: &str = "/jobs/stream";
+// Clients poll the status of jobs frequently so these requests aren't recorded in the audit log
+const STATUS_PATH: &str = "/jobs/status";
+const ANONYMOUS_USER: &str = "anonymous";
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/*
+Settings from the [auth] section and the [key.*] sections of unq-server.ini, e.g.:
+
+[auth]
+audit_log = results/audit.log
+requests_per_minute = 60
+max_backtests = 1
+
+[key.alice]
+key_hash = 2bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b
+max_backtests = 4
+admin = true
+
+Each key section requires either the plain text "key" or its hexadecimal SHA-256 digest "key_hash".
+The limits in [auth] are the defaults for keys that don't specify their own, missing limits mean no limit at all.
+Authentication is only enforced if at least one key has been defined. Otherwise all requests are attributed
+to an anonymous user and the limits apply to all clients combined.
+Administrative endpoints such as /admin/reload require "admin = true", the anonymous user is always an administrator.
+Jobs and stored results may only be accessed by the user who created them and by administrators.
+*/
+pub struct AuthConfiguration {
+	pub audit_log: Option<String>,
+	keys: Vec<KeyConfiguration>,
+	anonymous: KeyConfiguration
+}
+
+struct KeyConfiguration {
+	name: String,
+	key_hash: String,
+	// Maximum number of requests within a window of one minute
+	requests_per_minute: Option<u32>,
+	// Maximum number of backtests and backtest jobs that may be running or queued at the same time
+	max_backtests: Option<usize>,
+	admin: bool
+}
+
+pub struct Authenticator {
+	// Users indexed by the SHA-256 digest of their key so that keys never need to be compared directly
+	users: HashMap<String, Arc<User>>,
+	anonymous: Option<Arc<User>>,
+	audit_log: Option<AuditLog>
+}
+
+pub struct User {
+	pub name: String,
+	admin: bool,
+	requests_per_minute: Option<u32>,
+	max_backtests: Option<usize>,
+	rate_window: Mutex<RateWindow>,
+	backtests: Option<Arc<Semaphore>>
+}
+
+struct RateWindow {
+	start: Instant,
+	requests: u32
+}
+
+// Append-only log with one tab-separated line per entry: time, user and message
+struct AuditLog {
+	file: Mutex<File>
+}
+
+impl AuthConfiguration {
+	pub fn from_ini(config: &Ini) -> Result<AuthConfiguration> {
+		let default_requests_per_minute = get_ini_optional(config, AUTH_SECTION, "requests_per_minute")?;
+		let default_max_backtests = get_ini_optional(config, AUTH_SECTION, "max_backtests")?;
+		let mut keys = Vec::new();
+		for section in config.sections() {
+			let Some(name) = section.strip_prefix(KEY_SECTION_PREFIX) else {
+				continue;
+			};
+			let key_hash = match (config.get(&section, "key"), config.get(&section, "key_hash")) {
+				(Some(key), None) => get_hash(key.as_bytes()),
+				(None, Some(key_hash)) => key_hash.to_lowercase(),
+				_ => bail!("Section \"{section}\" must contain either \"key\" or \"key_hash\"")
+			};
+			let requests_per_minute = get_ini_optional(config, &section, "requests_per_minute")?
+				.or(default_requests_per_minute);
+			let max_backtests = get_ini_optional(config, &section, "max_backtests")?
+				.or(default_max_backtests);
+			let admin = get_ini_optional(config, &section, "admin")?.unwrap_or(false);
+			let key = KeyConfiguration {
+				name: name.to_string(),
+				key_hash,
+				requests_per_minute,
+				max_backtests,
+				admin
+			};
+			keys.push(key);
+		}
+		let audit_log = match config.get(AUTH_SECTION, "audit_log") {
+			Some(_) => Some(get_ini_string(config, AUTH_SECTION, "audit_log")?),
+			None => None
+		};
+		let anonymous = KeyConfiguration {
+			name: ANONYMOUS_USER.to_string(),
+			key_hash: String::new(),
+			requests_per_minute: default_requests_per_minute,
+			max_backtests: default_max_backtests,
+			admin: true
+		};
+		let configuration = AuthConfiguration {
+			audit_log,
+			keys,
+			anonymous
+		};
+		Ok(configuration)
+	}
+}
+
+impl Authenticator {
+	pub fn new(configuration: AuthConfiguration) -> Result<Authenticator> {
+		let mut users = HashMap::new();
+		for key in configuration.keys.iter() {
+			let user = Arc::new(User::new(key)?);
+			if users.insert(key.key_hash.clone(), user).is_some() {
+				bail!("Key of \"{}\" is already in use by another user", key.name);
+			}
+		}
+		let anonymous = if users.is_empty() {
+			Some(Arc::new(User::new(&configuration.anonymous)?))
+		} else {
+			None
+		};
+		let audit_log = match configuration.audit_log {
+			Some(path) => Some(AuditLog::new(&path)?),
+			None => None
+		};
+		let authenticator = Authenticator {
+			users,
+			anonymous,
+			audit_log
+		};
+		Ok(authenticator)
+	}
+
+	pub fn audit(&self, user: &User, message: &str) {
+		if let Some(audit_log) = &self.audit_log {
+			audit_log.write(user.name.as_str(), message);
+		}
+	}
+
+	fn get_user(&self, request: &Request) -> Option<Arc<User>> {
+		if let Some(anonymous) = &self.anonymous {
+			return Some(anonymous.clone());
+		}
+		let key = Self::get_key(request)?;
+		let key_hash = get_hash(key.as_bytes());
+		self.users.get(&key_hash).cloned()
+	}
+
+	/*
+	Keys are accepted as "Authorization: Bearer <key>" or in an X-API-Key header.
+	The query string is only checked for /jobs/stream so that keys don't end up in the access logs of other endpoints.
+	*/
+	fn get_key(request: &Request) -> Option<String> {
+		let get_header = |name| request
+			.headers()
+			.get(name)
+			.and_then(|x| x.to_str().ok());
+		let bearer_key = get_header(header::AUTHORIZATION.as_str())
+			.and_then(|x| x.strip_prefix("Bearer "));
+		if let Some(key) = bearer_key {
+			return Some(key.trim().to_string());
+		}
+		if let Some(key) = get_header(API_KEY_HEADER) {
+			return Some(key.to_string());
+		}
+		if request.uri().path() != STREAM_PATH {
+			return None;
+		}
+		let Query(parameters) = Query::<HashMap<String, String>>::try_from_uri(request.uri()).ok()?;
+		parameters.get(API_KEY_PARAMETER).cloned()
+	}
+}
+
+impl User {
+	fn new(key: &KeyConfiguration) -> Result<User> {
+		let backtests = match key.max_backtests {
+			Some(0) => bail!("The backtest quota of \"{}\" must be greater than zero", key.name),
+			Some(max_backtests) => Some(Arc::new(Semaphore::new(max_backtests))),
+			None => None
+		};
+		let rate_window = RateWindow {
+			start: Instant::now(),
+			requests: 0
+		};
+		let user = User {
+			name: key.name.clone(),
+			admin: key.admin,
+			requests_per_minute: key.requests_per_minute,
+			max_backtests: key.max_backtests,
+			rate_window: Mutex::new(rate_window),
+			backtests
+		};
+		Ok(user)
+	}
+
+	pub fn is_admin(&self) -> bool {
+		self.admin
+	}
+
+	// Results stored before owners were recorded don't have one and are only accessible to administrators
+	pub fn check_owner(&self, owner: Option<&str>) -> Result<()> {
+		if !self.admin && owner != Some(self.name.as_str()) {
+			bail!("Access denied, \"{}\" is neither the owner nor an administrator", self.name);
+		}
+		Ok(())
+	}
+
+	/*
+	Reserves one of the backtest slots of the user until the permit is dropped.
+	Requests exceeding the quota are rejected right away rather than being queued.
+	*/
+	pub fn acquire_backtest(&self) -> Result<Option<OwnedSemaphorePermit>> {
+		let (Some(backtests), Some(max_backtests)) = (&self.backtests, self.max_backtests) else {
+			return Ok(None);
+		};
+		let permit = backtests.clone().try_acquire_owned()
+			.map_err(|_| anyhow!("Backtest quota exceeded, \"{}\" may only run {max_backtests} backtest(s) at a time", self.name))?;
+		Ok(Some(permit))
+	}
+
+	// Returns the time remaining until the next request is permitted if the rate limit has been reached
+	fn check_rate_limit(&self) -> Result<Option<Duration>> {
+		let Some(requests_per_minute) = self.requests_per_minute else {
+			return Ok(None);
+		};
+		let Ok(mut window) = self.rate_window.lock() else {
+			bail!("Failed to acquire rate limit lock");
+		};
+		let elapsed = window.start.elapsed();
+		if elapsed >= RATE_LIMIT_WINDOW {
+			window.start = Instant::now();
+			window.requests = 0;
+		} else if window.requests >= requests_per_minute {
+			return Ok(Some(RATE_LIMIT_WINDOW - elapsed));
+		}
+		window.requests += 1;
+		Ok(None)
+	}
+}
+
+impl AuditLog {
+	fn new(path: &str) -> Result<AuditLog> {
+		let path_buf = PathBuf::from(path);
+		if let Some(directory) = path_buf.parent() {
+			if !directory.as_os_str().is_empty() {
+				fs::create_dir_all(directory)
+					.with_context(|| anyhow!("Failed to create directory for audit log \"{path}\""))?;
+			}
+		}
+		let file = OpenOptions::new()
+			.create(true)
+			.append(true)
+			.open(&path_buf)
+			.with_context(|| anyhow!("Failed to open audit log \"{path}\""))?;
+		let audit_log = AuditLog {
+			file: Mutex::new(file)
+		};
+		Ok(audit_log)
+	}
+
+	fn write(&self, user: &str, message: &str) {
+		let time = Local::now().format("%Y-%m-%d %H:%M:%S");
+		let line = format!("{time}\t{user}\t{message}\n");
+		// Failing to write the audit log must not affect the request itself
+		if let Ok(mut file) = self.file.lock() {
+			if let Err(error) = file.write_all(line.as_bytes()) {
+				eprintln!("Failed to write to audit log: {error}");
+			}
+		}
+	}
+}
+
+/*
+Middleware applied to all API routes.
+It identifies the user, enforces the rate limit and records the outcome of each request in the audit log.
+The user is made available to the handlers as an extension so that they can enforce the backtest quota.
+*/
+pub async fn authenticate(State(authenticator): State<Arc<Authenticator>>, mut request: Request, next: Next) -> axum::response::Response {
+	let Some(user) = authenticator.get_user(&request) else {
+		return get_error_response(StatusCode::UNAUTHORIZED, "Missing or invalid API key");
+	};
+	match user.check_rate_limit() {
+		Ok(None) => {},
+		Ok(Some(retry_after)) => {
+			authenticator.audit(&user, &format!("{} {} rejected by rate limit", request.method(), request.uri().path()));
+			let message = format!("Rate limit of {} requests per minute exceeded", user.requests_per_minute.unwrap_or_default());
+			let mut response = get_error_response(StatusCode::TOO_MANY_REQUESTS, message.as_str());
+			response.headers_mut().insert(header::RETRY_AFTER, (retry_after.as_secs() + 1).into());
+			return response;
+		},
+		Err(error) => return get_error_response(StatusCode::INTERNAL_SERVER_ERROR, error.to_string().as_str())
+	}
+	let method = request.method().clone();
+	let path = request.uri().path().to_string();
+	request.extensions_mut().insert(user.clone());
+	let response = next.run(request).await;
+	if path != STATUS_PATH {
+		authenticator.audit(&user, &format!("{method} {path} {}", response.status().as_u16()));
+	}
+	response
+}
+
+fn get_error_response(status: StatusCode, message: &str) -> axum::response::Response {
+	let response: Response<()> = Response {
+		result: None,
+		error: Some(message.to_string())
+	};
+	(status, Json(response)).into_response()
+}
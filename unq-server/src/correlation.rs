@@ -3,17 +3,10 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use chrono::NaiveDateTime;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use serde::Serialize;
 use anyhow::{bail, Context, Result};
+use unq_common::api::CorrelationData;
 use unq_common::ohlc::{OhlcArchive, OhlcRecord};
-
-#[derive(Serialize)]
-pub struct CorrelationData {
-	pub symbols: Vec<String>,
-	pub from: NaiveDateTime,
-	pub to: NaiveDateTime,
-	pub correlation: Vec<Vec<f64>>,
-}
+use unq_common::web::WebF64;
 
 pub fn get_correlation_matrix(symbols: Vec<String>, request_from: NaiveDateTime, request_to: NaiveDateTime, archives: &Vec<Arc<OhlcArchive>>) -> Result<CorrelationData> {
 	// Determine smallest overlapping time range across all OHLC records
@@ -51,11 +44,18 @@ pub fn get_correlation_matrix(symbols: Vec<String>, request_from: NaiveDateTime,
 		matrix[i][j] = coefficient;
 		matrix[j][i] = coefficient;
 	}
+	let correlation = matrix
+		.into_iter()
+		.map(|row| row
+			.into_iter()
+			.map(WebF64::exact)
+			.collect())
+		.collect();
 	let output = CorrelationData {
 		symbols,
 		from,
 		to,
-		correlation: matrix
+		correlation
 	};
 	Ok(output)
 }
@@ -0,0 +1,337 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use anyhow::{anyhow, bail, Context, Result};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use serde::Serialize;
+use tokio::runtime::Handle;
+use tokio::sync::{broadcast, OwnedSemaphorePermit, Semaphore};
+use unq_common::api::{JobStatus, JobStatusResult};
+use unq_common::backtest::{BacktestEvent, BacktestObserver, BacktestSeries, DailyStats, SimplifiedBacktestResult};
+use unq_strategy::runner::SweepObserver;
+use crate::auth::User;
+
+// Number of finished jobs that are kept around so that clients can still retrieve their results
+const FINISHED_JOB_LIMIT: usize = 100;
+// Number of messages buffered for each subscriber before slow subscribers start missing messages
+const MESSAGE_CAPACITY: usize = 4096;
+
+/*
+Queue for backtests that are executed in the background.
+The number of jobs running at the same time is limited by a semaphore and all of the parallel work
+is performed in a dedicated rayon thread pool so that large parameter sweeps can't starve the server.
+*/
+pub struct JobManager {
+	jobs: Mutex<BTreeMap<u64, Arc<Job>>>,
+	next_id: Mutex<u64>,
+	semaphore: Arc<Semaphore>,
+	thread_pool: ThreadPool
+}
+
+pub struct Job {
+	pub id: u64,
+	// Name of the user who submitted the job
+	pub owner: String,
+	cancelled: AtomicBool,
+	state: Mutex<JobState>,
+	sender: broadcast::Sender<JobMessage>
+}
+
+/*
+Live updates of a job that are published to subscribers of the stream endpoint.
+The index refers to the position of the backtest in the list of expanded parameter combinations.
+*/
+#[derive(Serialize, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum JobMessage {
+	Status {
+		status: JobStatus,
+		completed: usize,
+		total: usize
+	},
+	Result {
+		index: usize,
+		result: SimplifiedBacktestResult
+	},
+	Event {
+		index: usize,
+		event: BacktestEvent
+	},
+	DailyStats {
+		index: usize,
+		daily_stats: DailyStats
+	}
+}
+
+// Forwards the updates of an individual backtest to the subscribers of a job
+struct JobObserver {
+	index: usize,
+	sender: broadcast::Sender<JobMessage>
+}
+
+struct JobState {
+	status: JobStatus,
+	// Number of expanded parameter combinations that have been processed so far, including invalid ones
+	completed: usize,
+	total: usize,
+	results: Vec<SimplifiedBacktestResult>,
+	series: Option<BacktestSeries>,
+	error: Option<String>
+}
+
+impl JobManager {
+	// A thread count of 0 makes rayon use one thread per logical core
+	pub fn new(max_jobs: usize, threads: usize) -> Result<JobManager> {
+		if max_jobs == 0 {
+			bail!("The maximum number of concurrent jobs must be greater than zero");
+		}
+		let thread_pool = ThreadPoolBuilder::new()
+			.num_threads(threads)
+			.build()
+			.with_context(|| "Failed to create thread pool for jobs")?;
+		let manager = JobManager {
+			jobs: Mutex::new(BTreeMap::new()),
+			next_id: Mutex::new(1),
+			semaphore: Arc::new(Semaphore::new(max_jobs)),
+			thread_pool
+		};
+		Ok(manager)
+	}
+
+	pub fn create(&self, owner: &User) -> Result<Arc<Job>> {
+		let id = {
+			let Ok(mut next_id) = self.next_id.lock() else {
+				bail!("Failed to acquire job lock");
+			};
+			let id = *next_id;
+			*next_id += 1;
+			id
+		};
+		let job = Arc::new(Job::new(id, owner.name.clone()));
+		let mut jobs = self.lock_jobs()?;
+		Self::remove_finished_jobs(&mut jobs);
+		jobs.insert(id, job.clone());
+		Ok(job)
+	}
+
+	// Only the owner of a job and administrators may access it
+	pub fn get(&self, id: u64, user: &User) -> Result<Arc<Job>> {
+		let jobs = self.lock_jobs()?;
+		let job = jobs.get(&id)
+			.with_context(|| anyhow!("Unable to find a job with ID {id}"))?;
+		user.check_owner(Some(job.owner.as_str()))?;
+		Ok(job.clone())
+	}
+
+	// Waits until there is a free slot for another job
+	pub async fn acquire(&self) -> Result<OwnedSemaphorePermit> {
+		let permit = self.semaphore.clone().acquire_owned().await?;
+		Ok(permit)
+	}
+
+	/*
+	Runs a synchronous backtest in the job thread pool once a slot is available so that it counts towards the job limit.
+	Must be called from a blocking task, i.e. outside of asynchronous code.
+	*/
+	pub fn run<T: Send>(&self, operation: impl FnOnce() -> Result<T> + Send) -> Result<T> {
+		let _permit = Handle::current().block_on(self.acquire())?;
+		self.install(operation)
+	}
+
+	// Runs parallel work in the job thread pool rather than the global one
+	pub fn install<T: Send>(&self, operation: impl FnOnce() -> T + Send) -> T {
+		self.thread_pool.install(operation)
+	}
+
+	fn lock_jobs(&self) -> Result<MutexGuard<'_, BTreeMap<u64, Arc<Job>>>> {
+		let Ok(jobs) = self.jobs.lock() else {
+			bail!("Failed to acquire job lock");
+		};
+		Ok(jobs)
+	}
+
+	fn remove_finished_jobs(jobs: &mut BTreeMap<u64, Arc<Job>>) {
+		let finished_ids: Vec<u64> = jobs
+			.iter()
+			.filter(|(_, job)| job.is_finished())
+			.map(|(id, _)| *id)
+			.collect();
+		// IDs are assigned in ascending order so the oldest jobs come first
+		if finished_ids.len() >= FINISHED_JOB_LIMIT {
+			let excess = finished_ids.len() + 1 - FINISHED_JOB_LIMIT;
+			for id in finished_ids.iter().take(excess) {
+				jobs.remove(id);
+			}
+		}
+	}
+}
+
+impl Job {
+	fn new(id: u64, owner: String) -> Job {
+		let state = JobState {
+			status: JobStatus::Queued,
+			completed: 0,
+			total: 0,
+			results: Vec::new(),
+			series: None,
+			error: None
+		};
+		let (sender, _) = broadcast::channel(MESSAGE_CAPACITY);
+		Job {
+			id,
+			owner,
+			cancelled: AtomicBool::new(false),
+			state: Mutex::new(state),
+			sender
+		}
+	}
+
+	pub fn subscribe(&self) -> broadcast::Receiver<JobMessage> {
+		self.sender.subscribe()
+	}
+
+	// Returns a status message describing the current progress of the job
+	pub fn get_status_message(&self) -> Option<JobMessage> {
+		let Ok(state) = self.state.lock() else {
+			return None;
+		};
+		let message = JobMessage::Status {
+			status: state.status.clone(),
+			completed: state.completed,
+			total: state.total
+		};
+		Some(message)
+	}
+
+	pub fn cancel(&self) {
+		self.cancelled.store(true, Ordering::Relaxed);
+	}
+
+	pub fn finish(&self, result: Result<BacktestSeries>) {
+		let cancelled = self.is_cancelled();
+		self.update(|state| {
+			match result {
+				Ok(series) => {
+					state.status = JobStatus::Completed;
+					state.series = Some(series);
+				},
+				Err(_) if cancelled => {
+					state.status = JobStatus::Cancelled;
+				},
+				Err(error) => {
+					state.status = JobStatus::Failed;
+					state.error = Some(error.to_string());
+				}
+			}
+		});
+		self.publish_status();
+	}
+
+	pub fn get_status(&self, offset: usize) -> Result<JobStatusResult> {
+		let Ok(state) = self.state.lock() else {
+			bail!("Failed to acquire job state lock");
+		};
+		let results = state.results
+			.iter()
+			.skip(offset)
+			.cloned()
+			.collect();
+		let status = JobStatusResult {
+			id: self.id,
+			status: state.status.clone(),
+			completed: state.completed,
+			total: state.total,
+			results,
+			series: state.series.clone(),
+			error: state.error.clone()
+		};
+		Ok(status)
+	}
+
+	pub fn is_finished(&self) -> bool {
+		self.get_status_message()
+			.is_some_and(|message| message.is_final())
+	}
+
+	fn update(&self, update: impl FnOnce(&mut JobState)) {
+		if let Ok(mut state) = self.state.lock() {
+			update(&mut state);
+		}
+	}
+
+	fn publish(&self, message: JobMessage) {
+		// Sending only fails if there are no subscribers, which is fine
+		let _ = self.sender.send(message);
+	}
+
+	fn publish_status(&self) {
+		if let Some(message) = self.get_status_message() {
+			self.publish(message);
+		}
+	}
+}
+
+impl JobMessage {
+	pub fn is_final(&self) -> bool {
+		match self {
+			JobMessage::Status { status, .. } => *status != JobStatus::Queued && *status != JobStatus::Running,
+			_ => false
+		}
+	}
+}
+
+impl SweepObserver for Job {
+	fn start(&self, total: usize) {
+		self.update(|state| {
+			state.status = JobStatus::Running;
+			state.total = total;
+		});
+		self.publish_status();
+	}
+
+	fn is_cancelled(&self) -> bool {
+		self.cancelled.load(Ordering::Relaxed)
+	}
+
+	fn get_backtest_observer(&self, index: usize) -> Arc<dyn BacktestObserver> {
+		let observer = JobObserver {
+			index,
+			sender: self.sender.clone()
+		};
+		Arc::new(observer)
+	}
+
+	fn add_result(&self, index: usize, result: Option<SimplifiedBacktestResult>) {
+		self.update(|state| {
+			state.completed += 1;
+			if let Some(result) = &result {
+				state.results.push(result.clone());
+			}
+		});
+		if let Some(result) = result {
+			self.publish(JobMessage::Result {
+				index,
+				result
+			});
+		}
+		self.publish_status();
+	}
+}
+
+impl BacktestObserver for JobObserver {
+	fn on_event(&self, event: &BacktestEvent) {
+		let message = JobMessage::Event {
+			index: self.index,
+			event: event.clone()
+		};
+		let _ = self.sender.send(message);
+	}
+
+	fn on_daily_stats(&self, daily_stats: &DailyStats) {
+		let message = JobMessage::DailyStats {
+			index: self.index,
+			daily_stats: daily_stats.clone()
+		};
+		let _ = self.sender.send(message);
+	}
+}
@@ -1,22 +1,38 @@
-use std::{collections::HashMap, net::SocketAddr, sync::Arc};
-use axum::{response::IntoResponse, extract::{Json, State}, routing::post, Router};
+use std::{collections::HashMap, convert::Infallible, net::SocketAddr, sync::{Arc, Mutex, RwLock}};
+use std::path::{Path, PathBuf};
+use axum::{response::IntoResponse, extract::{Extension, Json, Query, State}, middleware, routing::{get, post}, Router};
+use axum::http::{header, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures_util::stream::{self, Stream};
+use notify::RecommendedWatcher;
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use tokio::net::TcpListener;
 use tower_http::services::ServeDir;
 use anyhow::{Result, anyhow, Error, Context, bail};
-use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 use stopwatch::Stopwatch;
 use tokio::task;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::task::JoinError;
-use unq_common::backtest::{Backtest, BacktestConfiguration, BacktestResult, BacktestSeries};
+use utoipa::{IntoParams, Modify, OpenApi};
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityRequirement, SecurityScheme};
+use unq_common::api::{BacktestDiff, BacktestTable, CancelJobRequest, CorrelationData, DiffResultsRequest, ExportBacktestRequest, ExportCorrelationRequest, ExportHistoryRequest, GetCorrelationRequest, GetHistoryRequest, GetJobStatusRequest, JobStatusResult, LoadResultRequest, OhlcRecordWeb, QueryRequest, QueryResult, ReloadResult, ReplayBacktestRequest, ReplayBacktestResult, Response, RunBacktestRequest, StoredBacktest, StoredBacktestSummary, SubmitJobResult};
+use unq_common::backtest::{BacktestConfiguration, BacktestSeries};
+use unq_common::export::{ExportFormat, Table};
 use unq_common::manager::AssetManager;
-use unq_common::ohlc::{OhlcArchive, OhlcMap, OhlcRecord, TimeFrame};
-use unq_common::strategy::{StrategyParameter, StrategyParameterError, StrategyParameters};
-use unq_common::web::WebF64;
-use unq_strategy::{expand_parameters, get_strategy};
-use crate::correlation::{get_correlation_matrix, CorrelationData};
-use crate::datetime::RelativeDateTime;
+use unq_common::manifest::BacktestManifest;
+use unq_common::ohlc::{BarSpecification, OhlcArchive, OhlcMap, TimeFrame};
+use unq_common::resample::{Alignment, ResamplePeriod, Resampler};
+use unq_common::web::{with_precision, Precision, WebF64};
+use unq_query::interpreter::{Command, Interpreter};
+use unq_strategy::runner::{run_backtests, SweepObserver};
+use crate::auth::{authenticate, AuthConfiguration, Authenticator, User};
+use crate::correlation::get_correlation_matrix;
+use crate::job::{Job, JobManager};
+use crate::storage::ResultStorage;
+use crate::watcher::watch_directories;
 
 const MINUTES_PER_DAY: u16 = 1440;
 
@@ -25,96 +41,142 @@ pub struct ServerConfiguration {
 	pub ticker_directory: String,
 	pub csv_directory: String,
 	pub assets_path: String,
-	pub script_directory: String
+	pub script_directory: String,
+	pub result_directory: String,
+	// Maximum number of backtest jobs that may run at the same time
+	pub max_jobs: usize,
+	// Number of threads used to run backtests, 0 for one thread per logical core
+	pub job_threads: usize,
+	// Automatically reload archives, time series and asset definitions when they are modified
+	pub watch_assets: bool,
+	// Load archives on first use and keep them in memory up to this many bytes rather than loading all of them at startup
+	pub archive_memory_limit: Option<usize>
 }
 
 struct ServerState {
 	server_configuration: ServerConfiguration,
-	asset_manager: Arc<AssetManager>,
-	backtest_configuration: BacktestConfiguration
-}
-
-#[derive(Serialize)]
-struct Response<T> {
-	result: Option<T>,
-	error: Option<String>
-}
-
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct GetHistoryRequest {
-	symbols: Vec<String>,
-	from: RelativeDateTime,
-	to: RelativeDateTime,
-	// Minutes, 1440 for daily data
-	time_frame: u16
-}
-
-#[derive(Deserialize)]
-struct GetCorrelationRequest {
-	symbols: Vec<String>,
-	from: RelativeDateTime,
-	to: RelativeDateTime
-}
-
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct RunBacktestRequest {
-	strategy: String,
-	symbols: Vec<String>,
-	from: RelativeDateTime,
-	to: RelativeDateTime,
-	parameters: Vec<StrategyParameter>,
-	time_frame: TimeFrame
-}
-
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
-struct OhlcRecordWeb {
-	pub symbol: String,
-	pub time: NaiveDateTime,
-	pub open: WebF64,
-	pub high: WebF64,
-	pub low: WebF64,
-	pub close: WebF64,
-	pub volume: u32,
-	pub open_interest: Option<u32>
-}
-
-impl OhlcRecordWeb {
-	pub fn new(record: &OhlcRecord) -> OhlcRecordWeb {
-		OhlcRecordWeb {
-			symbol: record.symbol.clone(),
-			time: record.time,
-			open: WebF64::new(record.open),
-			high: WebF64::new(record.high),
-			low: WebF64::new(record.low),
-			close: WebF64::new(record.close),
-			volume: record.volume,
-			open_interest: record.open_interest
-		}
+	/*
+	The asset manager is replaced as a whole when reloading assets.
+	Requests clone the Arc before they start working so backtests that are still running keep using the old data.
+	*/
+	asset_manager: RwLock<Arc<AssetManager>>,
+	// Prevents multiple reloads from loading the same archives at the same time
+	reload_lock: Mutex<()>,
+	backtest_configuration: BacktestConfiguration,
+	storage: ResultStorage,
+	jobs: JobManager,
+	authenticator: Arc<Authenticator>
+}
+
+#[derive(Deserialize, IntoParams)]
+struct StreamJobRequest {
+	id: u64,
+	#[param(value_type = Option<String>)]
+	precision: Option<Precision>
+}
+
+// Optional query string parameters of endpoints returning WebF64 values, e.g. /backtest?precision=full
+#[derive(Deserialize, IntoParams)]
+struct ResponseOptions {
+	// "compact", "full" or a number of significant digits between 1 and 17
+	#[param(value_type = Option<String>)]
+	precision: Option<Precision>
+}
+
+// OpenAPI 3 document of all JSON endpoints, served at /openapi.json
+#[derive(OpenApi)]
+#[openapi(
+	info(title = "Unquantified"),
+	modifiers(&SecurityAddon),
+	paths(
+		get_history,
+		get_correlation,
+		run_backtest,
+		replay_backtest,
+		submit_job,
+		get_job_status,
+		cancel_job,
+		stream_job,
+		list_results,
+		load_result,
+		diff_results,
+		run_query,
+		export_history,
+		export_correlation,
+		export_backtest,
+		reload
+	)
+)]
+struct ApiDoc;
+
+// Keys are only required if the configuration file defines at least one [key.*] section
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+	fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+		let scheme = HttpBuilder::new()
+			.scheme(HttpAuthScheme::Bearer)
+			.build();
+		openapi.components
+			.get_or_insert_with(Default::default)
+			.add_security_scheme("apiKey", SecurityScheme::Http(scheme));
+		openapi.security = Some(vec![SecurityRequirement::new("apiKey", Vec::<String>::new())]);
+	}
+}
+
+impl ResponseOptions {
+	fn get_precision(&self) -> Precision {
+		self.precision.unwrap_or_default()
 	}
 }
 
-pub async fn run(server_configuration: ServerConfiguration, backtest_configuration: BacktestConfiguration) -> Result<()> {
+pub async fn run(server_configuration: ServerConfiguration, backtest_configuration: BacktestConfiguration, auth_configuration: AuthConfiguration) -> Result<()> {
 	println!("Loading assets");
 	let stopwatch = Stopwatch::start_new();
-	let asset_manager = AssetManager::new(&server_configuration.ticker_directory, &server_configuration.csv_directory, &server_configuration.assets_path)?;
+	let asset_manager = server_configuration.get_asset_manager()?;
 	let asset_manager_arc = Arc::new(asset_manager);
 	println!("Loaded assets in {} ms", stopwatch.elapsed_ms());
+	let storage = ResultStorage::new(&server_configuration.result_directory)?;
+	let jobs = JobManager::new(server_configuration.max_jobs, server_configuration.job_threads)?;
+	let authenticator = Arc::new(Authenticator::new(auth_configuration)?);
 	let address = server_configuration.address.clone();
 	println!("Running server on {}", &address);
 	let server_state = ServerState {
 		server_configuration,
-		asset_manager: asset_manager_arc,
-		backtest_configuration
+		asset_manager: RwLock::new(asset_manager_arc),
+		reload_lock: Mutex::new(()),
+		backtest_configuration,
+		storage,
+		jobs,
+		authenticator: authenticator.clone()
 	};
 	let state_arc = Arc::new(server_state);
+	let _watcher = if state_arc.server_configuration.watch_assets {
+		Some(watch_assets(state_arc.clone())?)
+	} else {
+		None
+	};
 	let serve_dir = ServeDir::new("web");
 	let app = Router::new()
 		.route("/history", post(get_history))
 		.route("/correlation", post(get_correlation))
 		.route("/backtest", post(run_backtest))
+		.route("/backtest/replay", post(replay_backtest))
+		.route("/jobs/submit", post(submit_job))
+		.route("/jobs/status", post(get_job_status))
+		.route("/jobs/cancel", post(cancel_job))
+		.route("/jobs/stream", get(stream_job))
+		.route("/results/list", post(list_results))
+		.route("/results/load", post(load_result))
+		.route("/results/diff", post(diff_results))
+		.route("/query", post(run_query))
+		.route("/export/history", post(export_history))
+		.route("/export/correlation", post(export_correlation))
+		.route("/export/backtest", post(export_backtest))
+		.route("/admin/reload", post(reload))
+		.route("/openapi.json", get(get_openapi))
+		// Static files of the web UI don't require authentication
+		.route_layer(middleware::from_fn_with_state(authenticator, authenticate))
 		.with_state(state_arc)
 		.fallback_service(serve_dir);
 	let listener = TcpListener::bind(address).await
@@ -124,7 +186,72 @@ pub async fn run(server_configuration: ServerConfiguration, backtest_configurati
 	Ok(())
 }
 
-async fn get_response<A, B>(state: Arc<ServerState>, request: A, get_data: Box<dyn FnOnce(A, Arc<ServerState>) -> Result<B> + Send>) -> impl IntoResponse
+impl ServerConfiguration {
+	fn get_asset_manager(&self) -> Result<AssetManager> {
+		AssetManager::new(&self.ticker_directory, &self.csv_directory, &self.assets_path, self.archive_memory_limit)
+	}
+}
+
+impl ServerState {
+	fn get_asset_manager(&self) -> Arc<AssetManager> {
+		match self.asset_manager.read() {
+			Ok(asset_manager) => asset_manager.clone(),
+			// A reload can't leave the asset manager in an inconsistent state since it's swapped in one go
+			Err(error) => error.into_inner().clone()
+		}
+	}
+
+	// Loads all archives, time series and asset definitions again and swaps in the new asset manager
+	fn reload_assets(&self) -> Result<ReloadResult> {
+		let Ok(_guard) = self.reload_lock.lock() else {
+			bail!("Failed to acquire reload lock");
+		};
+		let stopwatch = Stopwatch::start_new();
+		let configuration = &self.server_configuration;
+		let asset_manager = configuration.get_asset_manager()?;
+		let previous = self.get_asset_manager();
+		let result = ReloadResult::new(&previous, &asset_manager);
+		let Ok(mut asset_manager_lock) = self.asset_manager.write() else {
+			bail!("Failed to acquire asset manager lock");
+		};
+		*asset_manager_lock = Arc::new(asset_manager);
+		println!(
+			"Reloaded assets in {} ms: {} added, {} removed, {} modified",
+			stopwatch.elapsed_ms(),
+			result.added.len(),
+			result.removed.len(),
+			result.modified.len()
+		);
+		Ok(result)
+	}
+}
+
+fn watch_assets(state: Arc<ServerState>) -> Result<RecommendedWatcher> {
+	let configuration = &state.server_configuration;
+	let assets_directory = Path::new(&configuration.assets_path)
+		.parent()
+		.filter(|x| !x.as_os_str().is_empty())
+		.unwrap_or(Path::new("."))
+		.to_path_buf();
+	let mut directories = vec![
+		PathBuf::from(&configuration.ticker_directory),
+		PathBuf::from(&configuration.csv_directory),
+		assets_directory
+	];
+	directories.sort();
+	directories.dedup();
+	let state_clone = state.clone();
+	let watcher = watch_directories(&directories, move || {
+		// Keep the previous asset manager if the files are still in the middle of being written
+		if let Err(error) = state_clone.reload_assets() {
+			eprintln!("Failed to reload assets: {error:#}");
+		}
+	})?;
+	println!("Watching assets for changes");
+	Ok(watcher)
+}
+
+async fn get_response<A, B>(state: Arc<ServerState>, request: A, precision: Precision, get_data: Box<dyn FnOnce(A, Arc<ServerState>) -> Result<B> + Send>) -> impl IntoResponse
 where
 	A: Send + 'static,
 	B: Send + Serialize + 'static
@@ -145,37 +272,343 @@ where
 		.await
 		.map(|task_result| task_result.map_or_else(get_error, get_response))
 		.unwrap_or_else(|error: JoinError| get_error(anyhow!(error)));
-	Json(response)
+	get_json_response(&response, precision)
+}
+
+// Json can't be used because the precision of WebF64 values has to be applied during serialization
+fn get_json_response<T: Serialize>(response: &T, precision: Precision) -> axum::response::Response {
+	match with_precision(precision, || serde_json::to_vec(response)) {
+		Ok(json) => ([(header::CONTENT_TYPE, "application/json")], json).into_response(),
+		Err(error) => (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()).into_response()
+	}
+}
+
+// Like get_response, but the table is returned as a file rather than being wrapped in JSON
+async fn get_file_response<A>(state: Arc<ServerState>, request: A, format: ExportFormat, name: &str, get_table: Box<dyn FnOnce(A, Arc<ServerState>) -> Result<Table> + Send>) -> axum::response::Response
+where
+	A: Send + 'static
+{
+	let result = task::spawn_blocking(move || get_table(request, state)?.to_bytes(format))
+		.await
+		.unwrap_or_else(|error: JoinError| Err(anyhow!(error)));
+	match result {
+		Ok(bytes) => {
+			let content_disposition = format!("attachment; filename=\"{name}.{}\"", format.get_extension());
+			let headers = [
+				(header::CONTENT_TYPE, format.get_content_type().to_string()),
+				(header::CONTENT_DISPOSITION, content_disposition)
+			];
+			(headers, bytes).into_response()
+		},
+		Err(error) => {
+			let response: Response<()> = Response {
+				result: None,
+				error: Some(error.to_string())
+			};
+			Json(response).into_response()
+		}
+	}
 }
 
+#[utoipa::path(post, path = "/history", params(ResponseOptions), request_body = GetHistoryRequest, responses((status = 200, body = Response<HashMap<String, Vec<OhlcRecordWeb>>>)))]
 async fn get_history(
 	State(state): State<Arc<ServerState>>,
+	Query(options): Query<ResponseOptions>,
 	Json(request): Json<GetHistoryRequest>
 ) -> impl IntoResponse {
-	get_response(state, request, Box::new(|request, state| {
-		get_history_data(request, state.asset_manager.clone())
+	get_response(state, request, options.get_precision(), Box::new(|request, state| {
+		get_history_data(request, state.get_asset_manager())
 	})).await
 }
 
+#[utoipa::path(post, path = "/correlation", params(ResponseOptions), request_body = GetCorrelationRequest, responses((status = 200, body = Response<CorrelationData>)))]
 async fn get_correlation(
 	State(state): State<Arc<ServerState>>,
+	Query(options): Query<ResponseOptions>,
 	Json(request): Json<GetCorrelationRequest>
 ) -> impl IntoResponse {
-	get_response(state, request, Box::new(|request, state| {
-		get_correlation_data(request, state.asset_manager.clone())
+	get_response(state, request, options.get_precision(), Box::new(|request, state| {
+		get_correlation_data(request, state.get_asset_manager())
 	})).await
 }
 
+#[utoipa::path(post, path = "/backtest", params(ResponseOptions), request_body = RunBacktestRequest, responses((status = 200, body = Response<BacktestSeries>)))]
 async fn run_backtest(
 	State(state): State<Arc<ServerState>>,
+	Extension(user): Extension<Arc<User>>,
+	Query(options): Query<ResponseOptions>,
 	Json(request): Json<RunBacktestRequest>
 ) -> impl IntoResponse {
-	get_response(state, request, Box::new(|request, state| {
-		get_backtest_result(request, state.asset_manager.clone(), &state.server_configuration, &state.backtest_configuration)
+	get_response(state, request, options.get_precision(), Box::new(move |request, state| {
+		let _permit = user.acquire_backtest()?;
+		run_and_store_backtest(request, &state, None, &user)
 	})).await
 }
 
+#[utoipa::path(post, path = "/backtest/replay", params(ResponseOptions), request_body = ReplayBacktestRequest, responses((status = 200, body = Response<ReplayBacktestResult>)))]
+async fn replay_backtest(
+	State(state): State<Arc<ServerState>>,
+	Extension(user): Extension<Arc<User>>,
+	Query(options): Query<ResponseOptions>,
+	Json(request): Json<ReplayBacktestRequest>
+) -> impl IntoResponse {
+	get_response(state, request, options.get_precision(), Box::new(move |request, state| {
+		let _permit = user.acquire_backtest()?;
+		get_replay_result(request, state, &user)
+	})).await
+}
+
+#[utoipa::path(post, path = "/jobs/submit", request_body = RunBacktestRequest, responses((status = 200, body = Response<SubmitJobResult>)))]
+async fn submit_job(
+	State(state): State<Arc<ServerState>>,
+	Extension(user): Extension<Arc<User>>,
+	Json(request): Json<RunBacktestRequest>
+) -> impl IntoResponse {
+	// Queued jobs count towards the backtest quota of the user, too
+	let job = user.acquire_backtest()
+		.and_then(|permit| Ok((state.jobs.create(&user)?, permit)));
+	let response = match job {
+		Ok((job, permit)) => {
+			let id = job.id;
+			tokio::spawn(run_job(job, request, state, user, permit));
+			Response {
+				result: Some(SubmitJobResult { id }),
+				error: None
+			}
+		},
+		Err(error) => Response {
+			result: None,
+			error: Some(error.to_string())
+		}
+	};
+	Json(response)
+}
+
+async fn run_job(job: Arc<Job>, request: RunBacktestRequest, state: Arc<ServerState>, user: Arc<User>, _user_permit: Option<OwnedSemaphorePermit>) {
+	// Jobs remain queued until one of the slots becomes available
+	let _permit = match state.jobs.acquire().await {
+		Ok(permit) => permit,
+		Err(error) => {
+			job.finish(Err(error));
+			return;
+		}
+	};
+	let job_clone = job.clone();
+	let result = task::spawn_blocking(move || -> Result<BacktestSeries> {
+		if job_clone.is_cancelled() {
+			bail!("Job has been cancelled");
+		}
+		run_and_store_backtest(request, &state, Some(&job_clone), &user)
+	}).await;
+	let series = result.unwrap_or_else(|error: JoinError| Err(anyhow!(error)));
+	job.finish(series);
+}
+
+#[utoipa::path(post, path = "/query", params(ResponseOptions), request_body = QueryRequest, responses((status = 200, body = Response<Vec<QueryResult>>)))]
+async fn run_query(
+	State(state): State<Arc<ServerState>>,
+	Extension(user): Extension<Arc<User>>,
+	Query(options): Query<ResponseOptions>,
+	Json(request): Json<QueryRequest>
+) -> impl IntoResponse {
+	get_response(state, request, options.get_precision(), Box::new(move |request, state| {
+		get_query_results(request, state, &user)
+	})).await
+}
+
+#[utoipa::path(post, path = "/export/history", request_body = ExportHistoryRequest, responses((status = 200, description = "CSV or Parquet file, errors are returned as JSON", content((String = "text/csv"), (Vec<u8> = "application/vnd.apache.parquet"), (Response<Value> = "application/json")))))]
+async fn export_history(
+	State(state): State<Arc<ServerState>>,
+	Json(request): Json<ExportHistoryRequest>
+) -> impl IntoResponse {
+	let format = request.format;
+	get_file_response(state, request, format, "history", Box::new(|request, state| {
+		let data = get_history_data(request.request, state.get_asset_manager())?;
+		get_history_table(data)
+	})).await
+}
+
+#[utoipa::path(post, path = "/export/correlation", request_body = ExportCorrelationRequest, responses((status = 200, description = "CSV or Parquet file, errors are returned as JSON", content((String = "text/csv"), (Vec<u8> = "application/vnd.apache.parquet"), (Response<Value> = "application/json")))))]
+async fn export_correlation(
+	State(state): State<Arc<ServerState>>,
+	Json(request): Json<ExportCorrelationRequest>
+) -> impl IntoResponse {
+	let format = request.format;
+	get_file_response(state, request, format, "correlation", Box::new(|request, state| {
+		get_correlation_data(request.request, state.get_asset_manager())?.get_table()
+	})).await
+}
+
+#[utoipa::path(post, path = "/export/backtest", request_body = ExportBacktestRequest, responses((status = 200, description = "CSV or Parquet file, errors are returned as JSON", content((String = "text/csv"), (Vec<u8> = "application/vnd.apache.parquet"), (Response<Value> = "application/json")))))]
+async fn export_backtest(
+	State(state): State<Arc<ServerState>>,
+	Extension(user): Extension<Arc<User>>,
+	Json(request): Json<ExportBacktestRequest>
+) -> impl IntoResponse {
+	let format = request.format;
+	let name = match request.table {
+		BacktestTable::EquityCurve => "equity-curve",
+		BacktestTable::Trades => "trades",
+		BacktestTable::Sweep => "sweep"
+	};
+	get_file_response(state, request, format, name, Box::new(move |request, state| {
+		get_backtest_table(request, state, &user)
+	})).await
+}
+
+#[utoipa::path(post, path = "/jobs/status", params(ResponseOptions), request_body = GetJobStatusRequest, responses((status = 200, body = Response<JobStatusResult>)))]
+async fn get_job_status(
+	State(state): State<Arc<ServerState>>,
+	Extension(user): Extension<Arc<User>>,
+	Query(options): Query<ResponseOptions>,
+	Json(request): Json<GetJobStatusRequest>
+) -> impl IntoResponse {
+	get_response(state, request, options.get_precision(), Box::new(move |request, state| -> Result<JobStatusResult> {
+		let job = state.jobs.get(request.id, &user)?;
+		job.get_status(request.offset.unwrap_or(0))
+	})).await
+}
+
+/*
+Server-sent events endpoint for live updates of a job, e.g. GET /jobs/stream?id=1.
+The first message always describes the current status of the job and the stream ends once the job has finished.
+Messages that are sent before the client subscribed can be retrieved via /jobs/status.
+*/
+#[utoipa::path(get, path = "/jobs/stream", params(StreamJobRequest), responses((status = 200, description = "Server-sent events with one JSON message per event", content_type = "text/event-stream", body = String)))]
+async fn stream_job(
+	State(state): State<Arc<ServerState>>,
+	Extension(user): Extension<Arc<User>>,
+	Query(request): Query<StreamJobRequest>
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, impl IntoResponse> {
+	let job = match state.jobs.get(request.id, &user) {
+		Ok(job) => job,
+		Err(error) => {
+			let response: Response<()> = Response {
+				result: None,
+				error: Some(error.to_string())
+			};
+			return Err(Json(response));
+		}
+	};
+	let precision = request.precision.unwrap_or_default();
+	let receiver = job.subscribe();
+	let initial_message = job.get_status_message();
+	let stream = stream::unfold((receiver, initial_message, false), move |(mut receiver, initial_message, done)| async move {
+		if done {
+			return None;
+		}
+		let message = match initial_message {
+			Some(message) => Ok(message),
+			None => match receiver.recv().await {
+				Ok(message) => Ok(message),
+				// Let the client know that it was too slow and missed some of the messages
+				Err(RecvError::Lagged(count)) => Err(count),
+				Err(RecvError::Closed) => return None
+			}
+		};
+		let (event, done) = match message {
+			Ok(message) => {
+				let done = message.is_final();
+				let event = with_precision(precision, || Event::default().json_data(&message))
+					.unwrap_or_else(|error| Event::default().event("error").data(error.to_string()));
+				(event, done)
+			},
+			Err(count) => (Event::default().event("lagged").data(count.to_string()), false)
+		};
+		Some((Ok(event), (receiver, None, done)))
+	});
+	Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+#[utoipa::path(post, path = "/jobs/cancel", request_body = CancelJobRequest, responses((status = 200, description = "The result is always null", body = Response<Value>)))]
+async fn cancel_job(
+	State(state): State<Arc<ServerState>>,
+	Extension(user): Extension<Arc<User>>,
+	Json(request): Json<CancelJobRequest>
+) -> impl IntoResponse {
+	get_response(state, request, Precision::Compact, Box::new(move |request, state| -> Result<()> {
+		let job = state.jobs.get(request.id, &user)?;
+		job.cancel();
+		Ok(())
+	})).await
+}
+
+#[utoipa::path(post, path = "/results/list", params(ResponseOptions), responses((status = 200, body = Response<Vec<StoredBacktestSummary>>)))]
+async fn list_results(
+	State(state): State<Arc<ServerState>>,
+	Extension(user): Extension<Arc<User>>,
+	Query(options): Query<ResponseOptions>
+) -> impl IntoResponse {
+	get_response(state, (), options.get_precision(), Box::new(move |_, state| -> Result<Vec<StoredBacktestSummary>> {
+		// Users only get to see their own results
+		let summaries = state.storage.list()?
+			.into_iter()
+			.filter(|x| user.check_owner(x.owner.as_deref()).is_ok())
+			.collect();
+		Ok(summaries)
+	})).await
+}
+
+#[utoipa::path(post, path = "/results/load", params(ResponseOptions), request_body = LoadResultRequest, responses((status = 200, body = Response<StoredBacktest>)))]
+async fn load_result(
+	State(state): State<Arc<ServerState>>,
+	Extension(user): Extension<Arc<User>>,
+	Query(options): Query<ResponseOptions>,
+	Json(request): Json<LoadResultRequest>
+) -> impl IntoResponse {
+	get_response(state, request, options.get_precision(), Box::new(move |request, state| -> Result<StoredBacktest> {
+		load_stored_backtest(request.id, &state, &user)
+	})).await
+}
+
+#[utoipa::path(post, path = "/results/diff", params(ResponseOptions), request_body = DiffResultsRequest, responses((status = 200, body = Response<BacktestDiff>)))]
+async fn diff_results(
+	State(state): State<Arc<ServerState>>,
+	Extension(user): Extension<Arc<User>>,
+	Query(options): Query<ResponseOptions>,
+	Json(request): Json<DiffResultsRequest>
+) -> impl IntoResponse {
+	get_response(state, request, options.get_precision(), Box::new(move |request, state| -> Result<BacktestDiff> {
+		let backtest1 = load_stored_backtest(request.id1, &state, &user)?;
+		let backtest2 = load_stored_backtest(request.id2, &state, &user)?;
+		ResultStorage::diff(&backtest1, &backtest2)
+	})).await
+}
+
+#[utoipa::path(post, path = "/admin/reload", responses((status = 200, body = Response<ReloadResult>)))]
+async fn reload(
+	State(state): State<Arc<ServerState>>,
+	Extension(user): Extension<Arc<User>>
+) -> impl IntoResponse {
+	get_response(state, user, Precision::Compact, Box::new(|user, state| {
+		if !user.is_admin() {
+			bail!("Reloading assets requires an administrator key");
+		}
+		let result = state.reload_assets()?;
+		let message = format!(
+			"Reloaded assets, added: [{}], removed: [{}], modified: [{}], assets changed: {}",
+			result.added.join(", "),
+			result.removed.join(", "),
+			result.modified.join(", "),
+			result.assets_changed
+		);
+		state.authenticator.audit(&user, message.as_str());
+		Ok(result)
+	})).await
+}
+
+async fn get_openapi() -> impl IntoResponse {
+	Json(ApiDoc::openapi())
+}
+
 fn get_history_data(request: GetHistoryRequest, asset_manager: Arc<AssetManager>) -> Result<HashMap<String, Vec<OhlcRecordWeb>>> {
+	let period = match request.period {
+		Some(period) if period.count == 0 => bail!("Invalid period"),
+		Some(period) => Some(period),
+		None if request.time_frame < MINUTES_PER_DAY => Some(ResamplePeriod::minutes(request.time_frame)),
+		None => None
+	};
+	let alignment = request.alignment.unwrap_or_default();
 	let time_frame = if request.time_frame >= MINUTES_PER_DAY {
 		TimeFrame::Daily
 	} else {
@@ -187,7 +620,11 @@ fn get_history_data(request: GetHistoryRequest, asset_manager: Arc<AssetManager>
 	let to_resolved = request.to.resolve(&request.from, &time_frame, &archives)?;
 	let result: Result<Vec<Vec<OhlcRecordWeb>>> = archives
 		.iter()
-		.map(|archive| get_ohlc_records(&from_resolved, &to_resolved, request.time_frame, archive))
+		.zip(resolved_symbols.iter())
+		.map(|(archive, symbol)| match &request.bars {
+			Some(specification) => get_bar_records(&from_resolved, &to_resolved, specification, symbol, archive),
+			None => get_ohlc_records(&from_resolved, &to_resolved, period, alignment, archive)
+		})
 		.collect();
 	match result {
 		Ok(ticker_records) => {
@@ -214,59 +651,46 @@ fn get_correlation_data(request: GetCorrelationRequest, asset_manager: Arc<Asset
 	get_correlation_matrix(resolved_symbols, from, to, &archives)
 }
 
-fn get_ohlc_records(from: &NaiveDateTime, to: &NaiveDateTime, time_frame: u16, archive: &OhlcArchive) -> Result<Vec<OhlcRecordWeb>> {
-	if time_frame >= MINUTES_PER_DAY {
+// Daily records are returned as they are unless a period is specified
+fn get_ohlc_records(from: &NaiveDateTime, to: &NaiveDateTime, period: Option<ResamplePeriod>, alignment: Alignment, archive: &OhlcArchive) -> Result<Vec<OhlcRecordWeb>> {
+	let Some(period) = period else {
 		return Ok(get_unprocessed_records(from, to, archive.daily.get_adjusted_fallback()));
-	} else if time_frame == archive.intraday_time_frame {
-		return Ok(get_unprocessed_records(from, to, archive.intraday.get_adjusted_fallback()));
-	} else if time_frame < archive.intraday_time_frame {
-		bail!("Requested time frame too small for intraday data in archive");
-	} else if time_frame % archive.intraday_time_frame != 0 {
-		let message = format!("Requested time frame must be a multiple of {}", archive.intraday_time_frame);
-		bail!(message);
-	}
-	let chunk_size = (time_frame / archive.intraday_time_frame) as usize;
-	archive.intraday
-		.get_adjusted_fallback()
-		.range(from..to)
-		.map(|(_, record)| record)
-		.collect::<Vec<_>>()
-		.chunks(chunk_size)
-		.filter(|x| x.len() == chunk_size)
-		.map(merge_ohlc_records)
-		.collect()
+	};
+	let (source_time_frame, source) = match period.get_minutes() {
+		Some(minutes) => {
+			let Some(time_frame) = u16::try_from(minutes).ok().filter(|x| *x < MINUTES_PER_DAY) else {
+				bail!("Intraday periods must be shorter than a day");
+			};
+			let Some((source_time_frame, data)) = archive.get_intraday_source(time_frame, from) else {
+				let resolutions: Vec<String> = archive.get_intraday_resolutions()
+					.iter()
+					.map(|(resolution, _)| resolution.to_string())
+					.collect();
+				if resolutions.is_empty() {
+					bail!("Archive contains no intraday data");
+				}
+				bail!("Requested time frame must be a multiple of one of the available resolutions ({})", resolutions.join(", "));
+			};
+			if source_time_frame == time_frame {
+				return Ok(get_unprocessed_records(from, to, data.get_adjusted_fallback()));
+			}
+			(source_time_frame, data.get_adjusted_fallback())
+		},
+		None => (MINUTES_PER_DAY, archive.daily.get_adjusted_fallback())
+	};
+	let records = Resampler::new(period, alignment, source_time_frame)
+		.resample(source.range(from..to).map(|(_, record)| record))
+		.iter()
+		.map(OhlcRecordWeb::new)
+		.collect();
+	Ok(records)
 }
 
-fn merge_ohlc_records(data: &[&OhlcRecord]) -> Result<OhlcRecordWeb> {
-	let first = data.first().unwrap();
-	let last = data.last().unwrap();
-	let symbol = first.symbol.clone();
-	let time = first.time;
-	let open = first.open;
-	let high = data
-		.iter()
-		.max_by(|x, y| x.high.partial_cmp(&y.high).unwrap())
-		.unwrap()
-		.high;
-	let low = data
-		.iter()
-		.min_by(|x, y| x.low.partial_cmp(&y.low).unwrap())
-		.unwrap()
-		.low;
-	let close = last.close;
-	let volume = data.iter().map(|x| x.volume).sum();
-	let open_interest = data.iter().map(|x| x.open_interest).sum();
-	let record = OhlcRecordWeb {
-		symbol,
-		time,
-		open: WebF64::new(open),
-		high: WebF64::new(high),
-		low: WebF64::new(low),
-		close: WebF64::new(close),
-		volume,
-		open_interest
+fn get_bar_records(from: &NaiveDateTime, to: &NaiveDateTime, specification: &BarSpecification, symbol: &str, archive: &OhlcArchive) -> Result<Vec<OhlcRecordWeb>> {
+	let Some(data) = archive.get_bars(specification) else {
+		bail!("The archive of {symbol} contains no bars of type {:?} with size {}", specification.bar_type, specification.size);
 	};
-	Ok(record)
+	Ok(get_unprocessed_records(from, to, data.get_adjusted_fallback()))
 }
 
 fn get_unprocessed_records(from: &NaiveDateTime, to: &NaiveDateTime, source: &OhlcMap) -> Vec<OhlcRecordWeb>
@@ -277,61 +701,139 @@ fn get_unprocessed_records(from: &NaiveDateTime, to: &NaiveDateTime, source: &Oh
 		.collect()
 }
 
-fn get_backtest_result(request: RunBacktestRequest, asset_manager: Arc<AssetManager>, server_configuration: &ServerConfiguration, backtest_configuration: &BacktestConfiguration) -> Result<BacktestSeries> {
-	let stopwatch = Stopwatch::start_new();
-	let archives = get_ticker_archives(&request.symbols, asset_manager.clone())?;
-	let from = request.from.resolve(&request.to, &request.time_frame, &archives)?;
-	let to = request.to.resolve(&request.from, &request.time_frame, &archives)?;
-	let parameters = StrategyParameters::from_vec(request.parameters);
-	// Expand range parameters/multi-value parameters and execute backtests in parallel
-	// This isn't very memory-efficient but might be faster than using a mutex for now
-	let expanded_parameters = expand_parameters(&parameters)?;
-	let results = expanded_parameters.par_iter().map(|parameters| -> Result<(&StrategyParameters, BacktestResult)> {
-		let backtest = Backtest::new(from, to, request.time_frame.clone(), backtest_configuration.clone(), asset_manager.clone())?;
-		let strategy_result = get_strategy(&request.strategy, &request.symbols, &server_configuration.script_directory, parameters, backtest.clone());
-		let mut strategy = match strategy_result {
-			Ok(strategy) => strategy,
-			Err(error) => bail!(StrategyParameterError::new(error.to_string()))
-		};
-		let mut done = false;
-		while !done {
-			strategy.next()?;
-			done = backtest.borrow_mut().next()?;
-		}
-		let result;
-		result = backtest.borrow_mut().get_result()?;
-		Ok((parameters, result))
-	}).collect::<Vec<Result<(&StrategyParameters, BacktestResult)>>>();
-	let ok_results: Vec<(&StrategyParameters, BacktestResult)> = results.iter().filter_map(|x| x.as_ref().ok()).cloned().collect();
-	if ok_results.is_empty() {
-		if results.is_empty() {
-			bail!("Parameter expansion failed");
-		} else {
-			if let Some(first_error) = results.first() {
-				if let Err(error) = first_error {
-					bail!(error.to_string());
-				} else {
-					bail!("Unable to extract error");
-				}
-			} else {
-				bail!("Unable to retrieve first backtest result");
+fn get_backtest_result(request: RunBacktestRequest, asset_manager: Arc<AssetManager>, server_configuration: &ServerConfiguration, backtest_configuration: &BacktestConfiguration, job: Option<&Job>) -> Result<BacktestSeries> {
+	let specification = request.get_specification(backtest_configuration, &asset_manager)?;
+	let observer = job.map(|job| job as &dyn SweepObserver);
+	run_backtests(specification, asset_manager, &server_configuration.script_directory, observer)
+}
+
+// Runs the backtests in the job thread pool and stores the result
+fn run_and_store_backtest(request: RunBacktestRequest, state: &ServerState, job: Option<&Job>, user: &User) -> Result<BacktestSeries> {
+	let get_series = || get_backtest_result(request, state.get_asset_manager(), &state.server_configuration, &state.backtest_configuration, job);
+	let series = match job {
+		// Jobs already acquired their slot while they were queued
+		Some(_) => state.jobs.install(get_series)?,
+		None => state.jobs.run(get_series)?
+	};
+	store_series(series, state, user)
+}
+
+// Stores the series and records the ID and specification of the run in the audit log
+fn store_series(mut series: BacktestSeries, state: &ServerState, user: &User) -> Result<BacktestSeries> {
+	let id = state.storage.store(&series, user.name.as_str())?;
+	series.set_id(id);
+	let specification = &series.get_manifest().specification;
+	let message = format!(
+		"Stored backtest {id}: {} on {} from {} to {} ({})",
+		specification.strategy,
+		specification.symbols.join(", "),
+		specification.from,
+		specification.to,
+		specification.parameters
+	);
+	state.authenticator.audit(user, message.as_str());
+	Ok(series)
+}
+
+// Loads a stored backtest, provided that the user is permitted to access it
+fn load_stored_backtest(id: u64, state: &ServerState, user: &User) -> Result<StoredBacktest> {
+	let stored_backtest = state.storage.load(id)?;
+	user.check_owner(stored_backtest.owner.as_deref())?;
+	Ok(stored_backtest)
+}
+
+fn get_backtest_table(request: ExportBacktestRequest, state: Arc<ServerState>, user: &User) -> Result<Table> {
+	let series = match (request.id, request.request) {
+		(Some(id), None) => load_stored_backtest(id, &state, user)?.series,
+		(None, Some(backtest_request)) => {
+			// Inline requests are stored and audited like regular backtests
+			let _permit = user.acquire_backtest()?;
+			run_and_store_backtest(backtest_request, &state, None, user)?
+		},
+		_ => bail!("Either the ID of a stored backtest or a backtest request must be specified")
+	};
+	match request.table {
+		BacktestTable::EquityCurve => series.get_best_result().get_equity_curve_table(),
+		BacktestTable::Trades => series.get_best_result().get_trades_table(),
+		BacktestTable::Sweep => series.get_sweep_table()
+	}
+}
+
+// Long format with one row per record, sorted by symbol and time
+fn get_history_table(data: HashMap<String, Vec<OhlcRecordWeb>>) -> Result<Table> {
+	let mut records: Vec<&OhlcRecordWeb> = data
+		.values()
+		.flatten()
+		.collect();
+	records.sort_by(|x, y| x.symbol.cmp(&y.symbol).then(x.time.cmp(&y.time)));
+	let get_floats = |get_value: fn(&OhlcRecordWeb) -> &WebF64| records
+		.iter()
+		.map(|x| get_value(x).get())
+		.collect();
+	let mut table = Table::new(records.len());
+	table.add_text("symbol", records.iter().map(|x| x.symbol.clone()).collect())?;
+	table.add_time("time", records.iter().map(|x| x.time).collect())?;
+	table.add_float("open", get_floats(|x| &x.open))?;
+	table.add_float("high", get_floats(|x| &x.high))?;
+	table.add_float("low", get_floats(|x| &x.low))?;
+	table.add_float("close", get_floats(|x| &x.close))?;
+	table.add_integer("volume", records.iter().map(|x| Some(x.volume as i64)).collect())?;
+	table.add_integer("openInterest", records.iter().map(|x| x.open_interest.map(|x| x as i64)).collect())?;
+	Ok(table)
+}
+
+fn get_query_results(request: QueryRequest, state: Arc<ServerState>, user: &User) -> Result<Vec<QueryResult>> {
+	let mut interpreter = Interpreter::new();
+	let commands = interpreter.run(request.query.as_str())?;
+	commands
+		.into_iter()
+		.map(|command| match command {
+			Command::History(request) => {
+				let data = get_history_data(request, state.get_asset_manager())?;
+				Ok(QueryResult::History { data })
+			},
+			Command::Correlation(request) => {
+				let data = get_correlation_data(request, state.get_asset_manager())?;
+				Ok(QueryResult::Correlation { data })
+			},
+			Command::Backtest(request) => {
+				let _permit = user.acquire_backtest()?;
+				let series = run_and_store_backtest(request, &state, None, user)?;
+				Ok(QueryResult::Backtest { series: Box::new(series) })
 			}
+		})
+		.collect()
+}
+
+fn get_replay_result(request: ReplayBacktestRequest, state: Arc<ServerState>, user: &User) -> Result<ReplayBacktestResult> {
+	let (manifest, is_stored) = match (request.id, request.manifest) {
+		(Some(id), None) => (load_stored_backtest(id, &state, user)?.series.get_manifest().clone(), true),
+		(None, Some(manifest)) => (manifest, false),
+		_ => bail!("Either the ID of a stored backtest or a manifest must be specified")
+	};
+	let stored_configuration = &manifest.specification.configuration;
+	let current_configuration = state.backtest_configuration.with_overrides(&stored_configuration.benchmark, stored_configuration.minimum_acceptable_return);
+	let current_hash = BacktestManifest::get_configuration_hash(&current_configuration)?;
+	if !is_stored {
+		// Manifests sent by clients may only deviate from the server configuration in the benchmark and minimum acceptable return
+		if BacktestManifest::get_configuration_hash(stored_configuration)? != manifest.configuration_hash {
+			bail!("The configuration of the manifest does not match its configuration hash");
+		}
+		if current_hash != manifest.configuration_hash {
+			bail!("The configuration of the manifest differs from the configuration of the server");
 		}
 	}
-	// Ignore strategy parameter errors caused by invalid combinations generated by the parameter expansion
-	for x in results.iter().filter_map(|x| x.as_ref().err()) {
-		let Some(_) = x.downcast_ref::<StrategyParameterError>() else {
-			// Bail in case of non-strategy parameter errors, though
-			bail!(x.to_string());
-		};
+	// The replay of a stored backtest always uses the stored configuration, even if the configuration file has been modified since
+	let specification = manifest.specification.clone();
+	let series = state.jobs.run(|| run_backtests(specification, state.get_asset_manager(), &state.server_configuration.script_directory, None))?;
+	let mut warnings = manifest.get_mismatches(series.get_manifest());
+	if current_hash != manifest.configuration_hash {
+		warnings.push("The server configuration has been modified, the stored configuration was used instead".to_string());
 	}
-	// Select best result by Sortino ratio and discard the others
-	let best_result = ok_results
-		.iter()
-		.map(|(_, result)| result)
-		.max()
-		.cloned()
-		.with_context(|| "Failed to expand strategy parameters")?;
-	let series = BacktestSeries::new(parameters, best_result, &ok_results, stopwatch);
-	Ok(series)
-}
\ No newline at end of file
+	let series = store_series(series, &state, user)?;
+	let result = ReplayBacktestResult {
+		series,
+		warnings
+	};
+	Ok(result)
+}
@@ -0,0 +1,274 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::Local;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use unq_common::api::{ArchiveDifference, BacktestDiff, Metric, MetricDifference, StoredBacktest, StoredBacktestSummary, ValueDifference};
+use unq_common::backtest::BacktestSeries;
+use unq_common::manager::ArchiveSnapshot;
+use unq_common::manifest::{BacktestManifest, BacktestSpecification};
+use unq_common::web::{with_storage_format, WebF64};
+use unq_common::PathDisplay;
+
+const RUN_FILE: &str = "run.json";
+const SUMMARY_FILE: &str = "summary.json";
+const TEMPORARY_EXTENSION: &str = "tmp";
+
+/*
+File-based storage for backtest results.
+Each run is stored in its own directory named after the numeric ID of the run:
+- run.json: the full StoredBacktest, including the equity curves and events
+- summary.json: a StoredBacktestSummary so that listing runs doesn't require parsing all of the results
+Runs are first written to a temporary directory which is then renamed to make the operation atomic.
+*/
+pub struct ResultStorage {
+	directory: PathBuf,
+	next_id: Mutex<u64>
+}
+
+impl ResultStorage {
+	pub fn new(directory: &String) -> Result<ResultStorage> {
+		let directory = PathBuf::from(directory);
+		fs::create_dir_all(&directory)
+			.with_context(|| anyhow!("Failed to create result directory \"{}\"", directory.to_string()))?;
+		let max_id = Self::get_ids(&directory)?
+			.into_iter()
+			.max()
+			.unwrap_or(0);
+		let storage = ResultStorage {
+			directory,
+			next_id: Mutex::new(max_id + 1)
+		};
+		Ok(storage)
+	}
+
+	// Stores the result of a backtest and returns the ID it has been assigned
+	pub fn store(&self, series: &BacktestSeries, owner: &str) -> Result<u64> {
+		let id = {
+			let Ok(mut next_id) = self.next_id.lock() else {
+				bail!("Failed to acquire result storage lock");
+			};
+			let id = *next_id;
+			*next_id += 1;
+			id
+		};
+		let mut series = series.clone();
+		series.set_id(id);
+		let stored_backtest = StoredBacktest {
+			id,
+			time: Local::now().naive_local(),
+			owner: Some(owner.to_string()),
+			series
+		};
+		let summary = get_summary(&stored_backtest);
+		let temporary_directory = self.directory.join(format!("{id}.{TEMPORARY_EXTENSION}"));
+		fs::create_dir_all(&temporary_directory)?;
+		Self::write_json(&temporary_directory.join(RUN_FILE), &stored_backtest)?;
+		Self::write_json(&temporary_directory.join(SUMMARY_FILE), &summary)?;
+		fs::rename(&temporary_directory, self.get_run_directory(id))
+			.with_context(|| anyhow!("Failed to store backtest result {id}"))?;
+		Ok(id)
+	}
+
+	pub fn list(&self) -> Result<Vec<StoredBacktestSummary>> {
+		let mut ids = Self::get_ids(&self.directory)?;
+		ids.sort();
+		ids
+			.iter()
+			.map(|id| {
+				let path = self.get_run_directory(*id).join(SUMMARY_FILE);
+				Self::read_json(&path)
+			})
+			.collect()
+	}
+
+	pub fn load(&self, id: u64) -> Result<StoredBacktest> {
+		let path = self.get_run_directory(id).join(RUN_FILE);
+		if !path.exists() {
+			bail!("Unable to find a backtest result with ID {id}");
+		}
+		Self::read_json(&path)
+	}
+
+	pub fn diff(backtest1: &StoredBacktest, backtest2: &StoredBacktest) -> Result<BacktestDiff> {
+		let manifest1 = backtest1.series.get_manifest();
+		let manifest2 = backtest2.series.get_manifest();
+		let parameters = Self::get_parameter_differences(manifest1, manifest2)?;
+		let script_changed = manifest1.script_hash != manifest2.script_hash;
+		let assets_changed = manifest1.assets_hash != manifest2.assets_hash;
+		let archives = Self::get_archive_differences(&manifest1.archives, &manifest2.archives);
+		let metrics1 = backtest1.series.get_best_result().get_metrics();
+		let metrics2 = backtest2.series.get_best_result().get_metrics();
+		let metrics = metrics1
+			.iter()
+			.zip(metrics2.iter())
+			.map(|((name, value1), (_, value2))| MetricDifference {
+				name: name.to_string(),
+				value1: WebF64::precise(*value1),
+				value2: WebF64::precise(*value2),
+				delta: WebF64::precise(value2 - value1)
+			})
+			.collect();
+		let diff = BacktestDiff {
+			id1: backtest1.id,
+			id2: backtest2.id,
+			parameters,
+			script_changed,
+			assets_changed,
+			archives,
+			metrics
+		};
+		Ok(diff)
+	}
+
+	fn get_run_directory(&self, id: u64) -> PathBuf {
+		self.directory.join(id.to_string())
+	}
+
+	fn get_ids(directory: &PathBuf) -> Result<Vec<u64>> {
+		// Incomplete runs in temporary directories are ignored since their names can't be parsed as integers
+		let entries = fs::read_dir(directory)
+			.with_context(|| anyhow!("Failed to read list of results from \"{}\"", directory.to_string()))?;
+		let ids = entries
+			.filter_map(|x| x.ok())
+			.map(|x| x.path())
+			.filter(|x| x.is_dir())
+			.filter_map(|x| x
+				.file_name()
+				.and_then(|x| x.to_str())
+				.and_then(|x| x.parse::<u64>().ok()))
+			.collect();
+		Ok(ids)
+	}
+
+	fn write_json<T: Serialize>(path: &PathBuf, value: &T) -> Result<()> {
+		// Results are stored without rounding and include the precision of each value so that they can be restored losslessly
+		let json = with_storage_format(|| serde_json::to_string(value))?;
+		fs::write(path, json)
+			.with_context(|| anyhow!("Failed to write \"{}\"", path.to_string()))?;
+		Ok(())
+	}
+
+	fn read_json<T: DeserializeOwned>(path: &PathBuf) -> Result<T> {
+		let json = fs::read_to_string(path)
+			.with_context(|| anyhow!("Failed to read \"{}\"", path.to_string()))?;
+		let value = serde_json::from_str(json.as_str())
+			.with_context(|| anyhow!("Failed to deserialize \"{}\"", path.to_string()))?;
+		Ok(value)
+	}
+
+	fn get_parameter_differences(manifest1: &BacktestManifest, manifest2: &BacktestManifest) -> Result<Vec<ValueDifference>> {
+		let values1 = Self::get_specification_values(&manifest1.specification)?;
+		let values2 = Self::get_specification_values(&manifest2.specification)?;
+		let keys: BTreeSet<&String> = values1
+			.iter()
+			.chain(values2.iter())
+			.map(|(key, _)| key)
+			.collect();
+		let get_value = |values: &Vec<(String, Value)>, key: &String| values
+			.iter()
+			.find(|(x, _)| x == key)
+			.map(|(_, value)| value.clone())
+			.unwrap_or(Value::Null);
+		let differences = keys
+			.into_iter()
+			.filter_map(|key| {
+				let value1 = get_value(&values1, key);
+				let value2 = get_value(&values2, key);
+				if value1 != value2 {
+					let difference = ValueDifference {
+						key: key.clone(),
+						value1,
+						value2
+					};
+					Some(difference)
+				} else {
+					None
+				}
+			})
+			.collect();
+		Ok(differences)
+	}
+
+	fn get_specification_values(specification: &BacktestSpecification) -> Result<Vec<(String, Value)>> {
+		// Flatten the specification so that strategy parameters are compared by name rather than by position
+		let Value::Object(object) = serde_json::to_value(specification)? else {
+			bail!("Unexpected specification serialization");
+		};
+		let mut values = Vec::new();
+		for (key, value) in object {
+			match (key.as_str(), value) {
+				("parameters", _) => {},
+				("configuration", Value::Object(configuration)) => {
+					for (configuration_key, configuration_value) in configuration {
+						values.push((format!("configuration.{configuration_key}"), configuration_value));
+					}
+				},
+				(_, value) => values.push((key, value))
+			}
+		}
+		for parameter in specification.parameters.iter() {
+			let key = format!("parameters.{}", parameter.name);
+			let value = serde_json::to_value(parameter)?;
+			values.push((key, value));
+		}
+		Ok(values)
+	}
+
+	fn get_archive_differences(archives1: &[ArchiveSnapshot], archives2: &[ArchiveSnapshot]) -> Vec<ArchiveDifference> {
+		let symbols: BTreeSet<&String> = archives1
+			.iter()
+			.chain(archives2.iter())
+			.map(|x| &x.symbol)
+			.collect();
+		let get_hash = |archives: &[ArchiveSnapshot], symbol: &String| archives
+			.iter()
+			.find(|x| x.symbol == *symbol)
+			.map(|x| x.hash.clone());
+		symbols
+			.into_iter()
+			.filter_map(|symbol| {
+				let hash1 = get_hash(archives1, symbol);
+				let hash2 = get_hash(archives2, symbol);
+				if hash1 != hash2 {
+					let difference = ArchiveDifference {
+						symbol: symbol.clone(),
+						hash1,
+						hash2
+					};
+					Some(difference)
+				} else {
+					None
+				}
+			})
+			.collect()
+	}
+}
+
+fn get_summary(stored_backtest: &StoredBacktest) -> StoredBacktestSummary {
+	let manifest = stored_backtest.series.get_manifest();
+	let metrics = stored_backtest.series
+		.get_best_result()
+		.get_metrics()
+		.into_iter()
+		.map(|(name, value)| Metric {
+			name: name.to_string(),
+			value: WebF64::precise(value)
+		})
+		.collect();
+	StoredBacktestSummary {
+		id: stored_backtest.id,
+		time: stored_backtest.time,
+		owner: stored_backtest.owner.clone(),
+		strategy: manifest.specification.strategy.clone(),
+		symbols: manifest.specification.symbols.clone(),
+		from: manifest.specification.from,
+		to: manifest.specification.to,
+		script_hash: manifest.script_hash.clone(),
+		metrics
+	}
+}
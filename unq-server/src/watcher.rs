@@ -0,0 +1,53 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use anyhow::{anyhow, Context, Result};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use unq_common::{ARCHIVE_EXTENSION, MAPPED_ARCHIVE_EXTENSION};
+
+// Archives are written over the course of several seconds so changes are only reported once the files have settled
+const DEBOUNCE_DELAY: Duration = Duration::from_secs(2);
+// Archives, time series and asset definitions
+const WATCHED_EXTENSIONS: [&str; 3] = [ARCHIVE_EXTENSION, MAPPED_ARCHIVE_EXTENSION, "csv"];
+
+/*
+Watches the directories for changes to archives, time series and asset definitions and invokes the callback
+on a dedicated thread once there haven't been any further changes for a while.
+The directories are only watched for as long as the returned watcher is kept alive.
+*/
+pub fn watch_directories(directories: &[PathBuf], on_change: impl Fn() + Send + 'static) -> Result<RecommendedWatcher> {
+	let (sender, receiver) = mpsc::channel();
+	let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+		if let Ok(event) = event {
+			if is_relevant(&event) {
+				// The receiver only disappears once the watcher is being dropped
+				let _ = sender.send(());
+			}
+		}
+	})?;
+	for directory in directories {
+		watcher.watch(directory, RecursiveMode::NonRecursive)
+			.with_context(|| anyhow!("Failed to watch directory \"{}\"", directory.display()))?;
+	}
+	thread::spawn(move || {
+		while receiver.recv().is_ok() {
+			while receiver.recv_timeout(DEBOUNCE_DELAY).is_ok() {}
+			on_change();
+		}
+	});
+	Ok(watcher)
+}
+
+fn is_relevant(event: &Event) -> bool {
+	let is_change = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_));
+	is_change && event.paths
+		.iter()
+		.any(|path| has_watched_extension(path))
+}
+
+fn has_watched_extension(path: &Path) -> bool {
+	path.extension()
+		.and_then(|x| x.to_str())
+		.is_some_and(|x| WATCHED_EXTENSIONS.contains(&x))
+}
@@ -6,6 +6,7 @@ use chrono::Datelike;
 use rhai::{Dynamic, EvalAltResult, ImmutableString};
 use unq_common::backtest::Backtest;
 use unq_common::ohlc::OhlcRecord;
+use unq_common::resample::ResamplePeriod;
 use crate::id::IndicatorId;
 use crate::indicator::adx::AverageDirectionalIndex;
 use crate::indicator::atr::AverageTrueRange;
@@ -22,20 +23,26 @@ use crate::indicator::rsi::RelativeStrengthIndicator;
 use crate::indicator::simple::SimpleMovingAverage;
 use crate::strategy::script::{ScriptStrategy, TradeSignal};
 use crate::technical::{ChannelExitMode, Indicator};
+use crate::timeframe::HigherTimeFrame;
 
 pub type ApiResult<T> = Result<T, Box<EvalAltResult>>;
 
 pub struct ApiIndicator {
 	symbol: String,
 	id: IndicatorId,
+	// Period of the records the indicator is calculated from, None for the time frame of the backtest
+	period: Option<ResamplePeriod>,
+	time_frame: Option<HigherTimeFrame>,
 	indicator: Box<dyn Indicator>
 }
 
 impl ApiIndicator {
-	fn new(symbol: String, id: IndicatorId, indicator: Box<dyn Indicator>) -> Self {
+	fn new(symbol: String, id: IndicatorId, period: Option<ResamplePeriod>, time_frame: Option<HigherTimeFrame>, indicator: Box<dyn Indicator>) -> Self {
 		Self {
 			symbol,
 			id,
+			period,
+			time_frame,
 			indicator
 		}
 	}
@@ -106,9 +113,13 @@ impl ApiContext {
 	}
 
 	pub fn update_indicators(&mut self, symbol: &String, record: &OhlcRecord) {
+		let now = *self.backtest.borrow().get_time();
 		for api_indicator in self.indicators.iter_mut() {
 			if api_indicator.symbol == *symbol {
-				api_indicator.indicator.next(record);
+				match api_indicator.time_frame.as_mut() {
+					Some(time_frame) => time_frame.update(&now, &mut api_indicator.indicator),
+					None => api_indicator.indicator.next(record)
+				}
 			}
 		}
 	}
@@ -192,7 +203,7 @@ impl ApiContext {
 		}
 	}
 
-	pub fn close_lagged(&mut self, period: i64) -> ApiResult<Dynamic> {
+	pub fn close_lagged(&mut self, period: i64, time_frame: Option<ResamplePeriod>) -> ApiResult<Dynamic> {
 		Self::validate_period(period)?;
 		let indicator_id = MomentumIndicator::get_id(period as usize);
 		let get_indicator = move || -> ApiResult<Box<dyn Indicator>> {
@@ -201,10 +212,10 @@ impl ApiContext {
 			let indicator_box = Box::new(indicator);
 			Ok(indicator_box)
 		};
-		self.execute_indicator(indicator_id, Box::new(get_indicator))
+		self.execute_indicator(indicator_id, time_frame, Box::new(get_indicator))
 	}
 
-	pub fn simple_moving_average(&mut self, period: i64) -> ApiResult<Dynamic> {
+	pub fn simple_moving_average(&mut self, period: i64, time_frame: Option<ResamplePeriod>) -> ApiResult<Dynamic> {
 		Self::validate_period(period)?;
 		let indicator_id = SimpleMovingAverage::get_id(period as usize);
 		let get_indicator = move || -> ApiResult<Box<dyn Indicator>> {
@@ -213,10 +224,10 @@ impl ApiContext {
 			let indicator_box = Box::new(indicator);
 			Ok(indicator_box)
 		};
-		self.execute_indicator(indicator_id, Box::new(get_indicator))
+		self.execute_indicator(indicator_id, time_frame, Box::new(get_indicator))
 	}
 
-	pub fn linear_moving_average(&mut self, period: i64) -> ApiResult<Dynamic> {
+	pub fn linear_moving_average(&mut self, period: i64, time_frame: Option<ResamplePeriod>) -> ApiResult<Dynamic> {
 		Self::validate_period(period)?;
 		let indicator_id = LinearMovingAverage::get_id(period as usize);
 		let get_indicator = move || -> ApiResult<Box<dyn Indicator>> {
@@ -225,10 +236,10 @@ impl ApiContext {
 			let indicator_box = Box::new(indicator);
 			Ok(indicator_box)
 		};
-		self.execute_indicator(indicator_id, Box::new(get_indicator))
+		self.execute_indicator(indicator_id, time_frame, Box::new(get_indicator))
 	}
 
-	pub fn exponential_moving_average(&mut self, period: i64) -> ApiResult<Dynamic> {
+	pub fn exponential_moving_average(&mut self, period: i64, time_frame: Option<ResamplePeriod>) -> ApiResult<Dynamic> {
 		Self::validate_period(period)?;
 		let indicator_id = ExponentialMovingAverage::get_id(period as usize);
 		let get_indicator = move || -> ApiResult<Box<dyn Indicator>> {
@@ -237,10 +248,10 @@ impl ApiContext {
 			let indicator_box = Box::new(indicator);
 			Ok(indicator_box)
 		};
-		self.execute_indicator(indicator_id, Box::new(get_indicator))
+		self.execute_indicator(indicator_id, time_frame, Box::new(get_indicator))
 	}
 
-	pub fn relative_strength_indicator(&mut self, period: i64) -> ApiResult<Dynamic> {
+	pub fn relative_strength_indicator(&mut self, period: i64, time_frame: Option<ResamplePeriod>) -> ApiResult<Dynamic> {
 		Self::validate_period(period)?;
 		let indicator_id = RelativeStrengthIndicator::get_id(period as usize);
 		let get_indicator = move || -> ApiResult<Box<dyn Indicator>> {
@@ -249,10 +260,10 @@ impl ApiContext {
 			let indicator_box = Box::new(indicator);
 			Ok(indicator_box)
 		};
-		self.execute_indicator(indicator_id, Box::new(get_indicator))
+		self.execute_indicator(indicator_id, time_frame, Box::new(get_indicator))
 	}
 
-	pub fn moving_average_convergence(&mut self, signal_period: i64, fast_period: i64, slow_period: i64) -> ApiResult<Dynamic> {
+	pub fn moving_average_convergence(&mut self, signal_period: i64, fast_period: i64, slow_period: i64, time_frame: Option<ResamplePeriod>) -> ApiResult<Dynamic> {
 		Self::validate_periods(signal_period, fast_period, slow_period)?;
 		let indicator_id = MovingAverageConvergence::get_id(signal_period as usize, fast_period as usize, slow_period as usize);
 		let get_indicator = move || -> ApiResult<Box<dyn Indicator>> {
@@ -261,10 +272,10 @@ impl ApiContext {
 			let indicator_box = Box::new(indicator);
 			Ok(indicator_box)
 		};
-		self.execute_indicator(indicator_id, Box::new(get_indicator))
+		self.execute_indicator(indicator_id, time_frame, Box::new(get_indicator))
 	}
 
-	pub fn percentage_price_oscillator(&mut self, signal_period: i64, fast_period: i64, slow_period: i64) -> ApiResult<Dynamic> {
+	pub fn percentage_price_oscillator(&mut self, signal_period: i64, fast_period: i64, slow_period: i64, time_frame: Option<ResamplePeriod>) -> ApiResult<Dynamic> {
 		Self::validate_periods(signal_period, fast_period, slow_period)?;
 		let indicator_id = PercentagePriceOscillator::get_id(signal_period as usize, fast_period as usize, slow_period as usize);
 		let get_indicator = move || -> ApiResult<Box<dyn Indicator>> {
@@ -273,10 +284,10 @@ impl ApiContext {
 			let indicator_box = Box::new(indicator);
 			Ok(indicator_box)
 		};
-		self.execute_indicator(indicator_id, Box::new(get_indicator))
+		self.execute_indicator(indicator_id, time_frame, Box::new(get_indicator))
 	}
 
-	pub fn bollinger_band(&mut self, period: i64, multiplier: f64) -> ApiResult<Dynamic> {
+	pub fn bollinger_band(&mut self, period: i64, multiplier: f64, time_frame: Option<ResamplePeriod>) -> ApiResult<Dynamic> {
 		Self::validate_period(period)?;
 		Self::validate_multiplier(multiplier)?;
 		let indicator_id = BollingerBands::get_id(period as usize, multiplier);
@@ -286,10 +297,10 @@ impl ApiContext {
 			let indicator_box = Box::new(indicator);
 			Ok(indicator_box)
 		};
-		self.execute_indicator(indicator_id, Box::new(get_indicator))
+		self.execute_indicator(indicator_id, time_frame, Box::new(get_indicator))
 	}
 
-	pub fn keltner_channel(&mut self, period: i64, multiplier: f64) -> ApiResult<Dynamic> {
+	pub fn keltner_channel(&mut self, period: i64, multiplier: f64, time_frame: Option<ResamplePeriod>) -> ApiResult<Dynamic> {
 		Self::validate_period(period)?;
 		Self::validate_multiplier(multiplier)?;
 		let indicator_id = KeltnerChannel::get_id(period as usize, multiplier);
@@ -299,10 +310,10 @@ impl ApiContext {
 			let indicator_box = Box::new(indicator);
 			Ok(indicator_box)
 		};
-		self.execute_indicator(indicator_id, Box::new(get_indicator))
+		self.execute_indicator(indicator_id, time_frame, Box::new(get_indicator))
 	}
 
-	pub fn donchian_channel(&mut self, period: i64) -> ApiResult<Dynamic> {
+	pub fn donchian_channel(&mut self, period: i64, time_frame: Option<ResamplePeriod>) -> ApiResult<Dynamic> {
 		Self::validate_period(period)?;
 		let indicator_id = DonchianChannel::get_id(period as usize);
 		let get_indicator = move || -> ApiResult<Box<dyn Indicator>> {
@@ -311,10 +322,10 @@ impl ApiContext {
 			let indicator_box = Box::new(indicator);
 			Ok(indicator_box)
 		};
-		self.execute_indicator(indicator_id, Box::new(get_indicator))
+		self.execute_indicator(indicator_id, time_frame, Box::new(get_indicator))
 	}
 
-	pub fn average_directional_index(&mut self, period: i64) -> ApiResult<Dynamic> {
+	pub fn average_directional_index(&mut self, period: i64, time_frame: Option<ResamplePeriod>) -> ApiResult<Dynamic> {
 		Self::validate_period(period)?;
 		let indicator_id = AverageDirectionalIndex::get_id(period as usize);
 		let get_indicator = move || -> ApiResult<Box<dyn Indicator>> {
@@ -323,10 +334,10 @@ impl ApiContext {
 			let indicator_box = Box::new(indicator);
 			Ok(indicator_box)
 		};
-		self.execute_indicator(indicator_id, Box::new(get_indicator))
+		self.execute_indicator(indicator_id, time_frame, Box::new(get_indicator))
 	}
 
-	pub fn average_true_range(&mut self, period: i64) -> ApiResult<Dynamic> {
+	pub fn average_true_range(&mut self, period: i64, time_frame: Option<ResamplePeriod>) -> ApiResult<Dynamic> {
 		Self::validate_period(period)?;
 		let indicator_id = AverageTrueRange::get_id(period as usize);
 		let get_indicator = move || -> ApiResult<Box<dyn Indicator>> {
@@ -335,10 +346,10 @@ impl ApiContext {
 			let indicator_box = Box::new(indicator);
 			Ok(indicator_box)
 		};
-		self.execute_indicator(indicator_id, Box::new(get_indicator))
+		self.execute_indicator(indicator_id, time_frame, Box::new(get_indicator))
 	}
 
-	pub fn rate_of_change(&mut self, period: i64) -> ApiResult<Dynamic> {
+	pub fn rate_of_change(&mut self, period: i64, time_frame: Option<ResamplePeriod>) -> ApiResult<Dynamic> {
 		Self::validate_period(period)?;
 		let indicator_id = RateOfChange::get_id(period as usize);
 		let get_indicator = move || -> ApiResult<Box<dyn Indicator>> {
@@ -347,7 +358,7 @@ impl ApiContext {
 			let indicator_box = Box::new(indicator);
 			Ok(indicator_box)
 		};
-		self.execute_indicator(indicator_id, Box::new(get_indicator))
+		self.execute_indicator(indicator_id, time_frame, Box::new(get_indicator))
 	}
 
 	fn validate_period(period: i64) -> ApiResult<()> {
@@ -387,20 +398,41 @@ impl ApiContext {
 
 	}
 
-	fn execute_indicator(&mut self, indicator_id: IndicatorId, get_indicator: Box<dyn Fn() -> ApiResult<Box<dyn Indicator>>>) -> ApiResult<Dynamic> {
-		match self.indicators.iter().find(|x| x.id == indicator_id) {
+	fn execute_indicator(&mut self, indicator_id: IndicatorId, period: Option<ResamplePeriod>, get_indicator: Box<dyn Fn() -> ApiResult<Box<dyn Indicator>>>) -> ApiResult<Dynamic> {
+		let existing_indicator = self.indicators
+			.iter()
+			.find(|x| x.id == indicator_id && x.period == period);
+		match existing_indicator {
 			Some(api_indicator) => {
 				let indicator_values = api_indicator.indicator.get_indicators();
 				Self::translate_indicator_values(indicator_values)
 			},
 			None => {
 				let mut indicator = get_indicator()?;
-				if let Some(initialization_bars) = indicator.needs_initialization() {
-					if let Ok(initialization_records) = self.backtest.borrow().get_records(&self.current_symbol, initialization_bars) {
-						indicator.initialize(&initialization_records);
+				let backtest = self.backtest.borrow();
+				let time_frame = match &period {
+					Some(period) => HigherTimeFrame::new(&self.current_symbol, period, &backtest)
+						.map_err(|error| -> Box<EvalAltResult> {
+							format!("Failed to load records for indicator: {error}").into()
+						})?,
+					None => None
+				};
+				let time_frame = match time_frame {
+					Some(mut time_frame) => {
+						time_frame.update(backtest.get_time(), &mut indicator);
+						Some(time_frame)
+					},
+					None => {
+						if let Some(initialization_bars) = indicator.needs_initialization() {
+							if let Ok(initialization_records) = backtest.get_records(&self.current_symbol, initialization_bars) {
+								indicator.initialize(&initialization_records);
+							}
+						}
+						None
 					}
-				}
-				let api_indicator = ApiIndicator::new(self.current_symbol.clone(), indicator_id, indicator);
+				};
+				drop(backtest);
+				let api_indicator = ApiIndicator::new(self.current_symbol.clone(), indicator_id, period, time_frame, indicator);
 				let indicator_values = api_indicator.indicator.get_indicators();
 				self.indicators.push(api_indicator);
 				Self::translate_indicator_values(indicator_values)
@@ -1,7 +1,9 @@
 pub mod id;
+pub mod runner;
 mod technical;
 mod buffer;
 mod api_context;
+mod timeframe;
 
 mod indicator {
 	pub mod momentum;
@@ -27,9 +29,10 @@ mod strategy {
 }
 
 use std::cell::RefCell;
+use std::fs;
 use std::iter;
 use std::rc::Rc;
-use anyhow::{Result, bail};
+use anyhow::{anyhow, bail, Context, Result};
 use unq_common::backtest::Backtest;
 use unq_common::strategy::{Strategy, StrategyParameter, StrategyParameterType, StrategyParameters};
 use crate::strategy::auto_indicator::AutoIndicatorStrategy;
@@ -63,6 +66,18 @@ pub fn get_strategy<'a>(name: &String, symbols: &Vec<String>, script_directory:
 	}
 }
 
+// Returns the source code of the script used by a strategy, None if the strategy isn't script-based
+pub fn get_script_source(name: &String, script_directory: &String, parameters: &StrategyParameters) -> Result<Option<String>> {
+	if name != ScriptStrategy::ID {
+		return Ok(None);
+	}
+	let script = ScriptStrategy::get_script_name(parameters)?;
+	let path = ScriptStrategy::get_script_path(&script, script_directory)?;
+	let source = fs::read_to_string(&path)
+		.with_context(|| anyhow!("Failed to read script \"{script}\""))?;
+	Ok(Some(source))
+}
+
 pub fn expand_parameters(parameters: &StrategyParameters) -> Result<Vec<StrategyParameters>> {
 	let parameters_output = StrategyParameters::new();
 	let output = RefCell::new(Vec::new());
@@ -0,0 +1,154 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+use anyhow::{bail, Context, Result};
+use rayon::prelude::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use stopwatch::Stopwatch;
+use unq_common::backtest::{Backtest, BacktestObserver, BacktestResult, BacktestSeries, SimplifiedBacktestResult, StrategyEvent};
+use unq_common::manager::AssetManager;
+use unq_common::manifest::{BacktestManifest, BacktestSpecification};
+use unq_common::strategy::{Strategy, StrategyParameterError, StrategyParameters};
+use crate::{expand_parameters, get_script_source, get_strategy};
+
+/*
+Tracks the progress of a parameter sweep, e.g. to report it to clients.
+The index refers to the position of the backtest in the list of expanded parameter combinations.
+*/
+pub trait SweepObserver: Sync {
+	fn start(&self, total: usize);
+	// Checked before each step of a backtest, the sweep is aborted with an error if it returns true
+	fn is_cancelled(&self) -> bool;
+	fn get_backtest_observer(&self, index: usize) -> Arc<dyn BacktestObserver>;
+	// Called for every processed parameter combination, result is None for combinations that failed
+	fn add_result(&self, index: usize, result: Option<SimplifiedBacktestResult>);
+}
+
+/*
+Runs all backtests of a specification and selects the best result.
+Range parameters and multi-value parameters are expanded and the resulting backtests are executed in parallel
+in the current rayon thread pool.
+*/
+pub fn run_backtests(specification: BacktestSpecification, asset_manager: Arc<AssetManager>, script_directory: &String, observer: Option<&dyn SweepObserver>) -> Result<BacktestSeries> {
+	let stopwatch = Stopwatch::start_new();
+	let script_source = get_script_source(&specification.strategy, script_directory, &specification.parameters)?;
+	let manifest = BacktestManifest::new(specification.clone(), script_source.as_ref(), &asset_manager)?;
+	let BacktestSpecification {
+		strategy,
+		symbols,
+		parameters,
+		from,
+		to,
+		time_frame,
+		configuration: backtest_configuration
+	} = specification;
+	// Expand range parameters/multi-value parameters and execute backtests in parallel
+	// This isn't very memory-efficient but might be faster than using a mutex for now
+	let expanded_parameters = expand_parameters(&parameters)?;
+	let is_cancelled = || observer.is_some_and(|observer| observer.is_cancelled());
+	if let Some(observer) = observer {
+		observer.start(expanded_parameters.len());
+	}
+	let results = expanded_parameters.par_iter().enumerate().map(|(index, parameters)| -> Result<(&StrategyParameters, BacktestResult)> {
+		let run_backtest = || -> Result<BacktestResult> {
+			let backtest = Backtest::new(from, to, time_frame.clone(), backtest_configuration.clone(), asset_manager.clone())?;
+			if let Some(observer) = observer {
+				backtest.borrow_mut().set_observer(observer.get_backtest_observer(index));
+			}
+			let strategy_result = get_strategy(&strategy, &symbols, script_directory, parameters, backtest.clone());
+			let mut strategy = match strategy_result {
+				Ok(strategy) => strategy,
+				Err(error) => bail!(StrategyParameterError::new(error.to_string()))
+			};
+			run_strategy(strategy.as_mut(), &backtest, &is_cancelled)?;
+			let result = backtest.borrow_mut().get_result()?;
+			Ok(result)
+		};
+		let result = run_backtest();
+		if let Some(observer) = observer {
+			// Publish partial results so that clients can display them before the entire sweep has finished
+			let simplified_result = result
+				.as_ref()
+				.ok()
+				.map(|result| result.simple(parameters.clone()));
+			observer.add_result(index, simplified_result);
+		}
+		Ok((parameters, result?))
+	}).collect::<Vec<Result<(&StrategyParameters, BacktestResult)>>>();
+	let ok_results: Vec<(&StrategyParameters, BacktestResult)> = results.iter().filter_map(|x| x.as_ref().ok()).cloned().collect();
+	if ok_results.is_empty() {
+		if results.is_empty() {
+			bail!("Parameter expansion failed");
+		} else {
+			if let Some(first_error) = results.first() {
+				if let Err(error) = first_error {
+					bail!(error.to_string());
+				} else {
+					bail!("Unable to extract error");
+				}
+			} else {
+				bail!("Unable to retrieve first backtest result");
+			}
+		}
+	}
+	// Ignore strategy parameter errors caused by invalid combinations generated by the parameter expansion
+	for x in results.iter().filter_map(|x| x.as_ref().err()) {
+		let Some(_) = x.downcast_ref::<StrategyParameterError>() else {
+			// Bail in case of non-strategy parameter errors, though
+			bail!(x.to_string());
+		};
+	}
+	// Select best result by Sortino ratio and discard the others
+	let best_result = ok_results
+		.iter()
+		.map(|(_, result)| result)
+		.max()
+		.cloned()
+		.with_context(|| "Failed to expand strategy parameters")?;
+	let series = BacktestSeries::new(manifest, parameters, best_result, &ok_results, stopwatch);
+	Ok(series)
+}
+
+/*
+Executes a strategy until the backtest terminates, calling the lifecycle hooks of the strategy along the way.
+Weekly, monthly and N-day time frames only execute "next" on the last trading day of each period.
+*/
+pub fn run_strategy(strategy: &mut dyn Strategy, backtest: &Rc<RefCell<Backtest>>, is_cancelled: &dyn Fn() -> bool) -> Result<()> {
+	strategy.on_start()?;
+	let mut done = false;
+	while !done {
+		if is_cancelled() {
+			bail!("Backtest has been cancelled");
+		}
+		let (is_rebalancing_time, is_session_close, is_month_end) = {
+			let backtest = backtest.borrow();
+			(backtest.is_rebalancing_time(), backtest.is_session_close(), backtest.is_month_end())
+		};
+		if is_rebalancing_time {
+			strategy.next()?;
+		}
+		if is_session_close {
+			strategy.on_session_close()?;
+		}
+		if is_month_end {
+			strategy.on_month_end()?;
+		}
+		dispatch_events(strategy, backtest)?;
+		done = backtest.borrow_mut().next()?;
+		dispatch_events(strategy, backtest)?;
+	}
+	strategy.on_finish()
+}
+
+fn dispatch_events(strategy: &mut dyn Strategy, backtest: &Rc<RefCell<Backtest>>) -> Result<()> {
+	let events = backtest.borrow_mut().take_strategy_events();
+	for event in events {
+		match event {
+			StrategyEvent::DayStart => strategy.on_day_start()?,
+			StrategyEvent::SessionStart => strategy.on_session_start()?,
+			StrategyEvent::Fill(fill) => strategy.on_fill(&fill)?,
+			StrategyEvent::Rollover(rollover) => strategy.on_rollover(&rollover)?,
+			StrategyEvent::MarginCall => strategy.on_margin_call()?
+		}
+	}
+	Ok(())
+}
@@ -6,6 +6,7 @@ use chrono::TimeDelta;
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 use unq_common::backtest::{Backtest, BacktestResult, EventType};
 use unq_common::strategy::{Strategy, StrategyParameters};
+use crate::runner::run_strategy;
 use crate::strategy::indicator::{IndicatorStrategy, SymbolIndicator};
 use crate::{get_symbol_contracts, SymbolContracts};
 use crate::indicator::bollinger::BollingerBands;
@@ -127,11 +128,7 @@ impl AutoIndicatorStrategy {
 				optimization_backtest.borrow_mut().disable_logging();
 				let strategy_indicators = vec![symbol_indicator.clone()];
 				let mut strategy = IndicatorStrategy::new(strategy_indicators, enable_long, enable_short, optimization_backtest.clone())?;
-				let mut done = false;
-				while !done {
-					strategy.next()?;
-					done = optimization_backtest.borrow_mut().next()?;
-				}
+				run_strategy(&mut strategy, &optimization_backtest, &|| false)?;
 				let result = optimization_backtest.borrow_mut().get_result()?;
 				let auto_indicator = AutoIndicator {
 					symbol_indicator: symbol_indicator.clone(),
@@ -285,7 +282,8 @@ impl AutoIndicatorStrategy {
 			let symbol_indicator = SymbolIndicator {
 				symbol: symbol.clone(),
 				contracts,
-				indicator: Box::new(indicator)
+				indicator: Box::new(indicator),
+				time_frame: None
 			};
 			indicators.push(symbol_indicator);
 		}
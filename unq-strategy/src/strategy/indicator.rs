@@ -15,11 +15,14 @@ use crate::indicator::ppo::PercentagePriceOscillator;
 use crate::indicator::rsi::RelativeStrengthIndicator;
 use crate::indicator::simple::SimpleMovingAverage;
 use crate::technical::*;
+use crate::timeframe::{parse_time_frame, HigherTimeFrame};
 
 pub struct SymbolIndicator {
 	pub symbol: String,
 	pub contracts: u32,
-	pub indicator: Box<dyn Indicator>
+	pub indicator: Box<dyn Indicator>,
+	// Completed records of a higher time frame the indicator is calculated from, e.g. daily records in an intraday backtest
+	pub time_frame: Option<HigherTimeFrame>
 }
 
 impl Clone for SymbolIndicator {
@@ -27,7 +30,8 @@ impl Clone for SymbolIndicator {
 		SymbolIndicator {
 			symbol: self.symbol.clone(),
 			contracts: self.contracts,
-			indicator: self.indicator.clone_box()
+			indicator: self.indicator.clone_box(),
+			time_frame: self.time_frame.clone()
 		}
 	}
 }
@@ -170,17 +174,29 @@ impl IndicatorStrategy {
 			},
 			other => bail!("Unknown indicator type \"{other}\"")
 		};
+		// Optional higher time frame such as "daily" or "weekly", see HigherTimeFrame
+		let period = match parameters.get_string("indicatorTimeFrame")? {
+			Some(name) => Some(parse_time_frame(&name)?),
+			None => None
+		};
 		let symbol_contracts = get_symbol_contracts(&symbols, parameters)?;
-		let indicators: Vec<SymbolIndicator> = symbol_contracts
+		let indicators: Result<Vec<SymbolIndicator>> = symbol_contracts
 			.into_iter()
 			.map(|(symbol, contracts)| {
-				SymbolIndicator {
+				let time_frame = match &period {
+					Some(period) => HigherTimeFrame::new(&symbol, period, &backtest.borrow())?,
+					None => None
+				};
+				let symbol_indicator = SymbolIndicator {
 					symbol,
 					contracts,
-					indicator: indicator.clone_box()
-				}
+					indicator: indicator.clone_box(),
+					time_frame
+				};
+				Ok(symbol_indicator)
 			})
 			.collect();
+		let indicators = indicators?;
 		let strategy = Self::new(indicators, enable_long, enable_short, backtest)?;
 		Ok(strategy)
 	}
@@ -275,7 +291,9 @@ impl Strategy for IndicatorStrategy {
 					// This symbol isn't available on the exchange yet, skip it
 					continue;
 				}
-				if let Some(initialization_bars) = indicator.needs_initialization() {
+				if let Some(time_frame) = indicator_data.time_frame.as_mut() {
+					time_frame.update(backtest.get_time(), indicator);
+				} else if let Some(initialization_bars) = indicator.needs_initialization() {
 					// It's the first time the indicator is being invoked
 					// Try to fill up its buffer with OHLC data from outside the from/to range to speed up signal generation
 					// This can actually make a big difference with big buffers (e.g. EMA)
@@ -1,12 +1,13 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use anyhow::{Result, bail, anyhow, Context};
 use regex::Regex;
-use rhai::{Dynamic, Engine, ImmutableString, Scope, AST};
-use unq_common::backtest::{Backtest, PositionSide};
+use rhai::{Dynamic, Engine, FuncArgs, ImmutableString, Map, Scope, AST};
+use unq_common::backtest::{Backtest, Fill, PositionSide, Rollover};
 use unq_common::ohlc::OhlcRecord;
+use unq_common::resample::{PeriodUnit, ResamplePeriod};
 use unq_common::strategy::{Strategy, StrategyParameter, StrategyParameterType, StrategyParameters};
 use crate::api_context::ApiContext;
 use crate::CONTRACTS_PARAMETER;
@@ -21,6 +22,20 @@ const TRADE_SIGNAL_SHORT: i64 = 1;
 const TRADE_SIGNAL_HOLD: i64 = 2;
 const TRADE_SIGNAL_CLOSE: i64 = 3;
 
+// Number of parameters expected for each function called by the strategy
+const SCRIPT_FUNCTIONS: [(&'static str, usize); 10] = [
+	("next", 0),
+	("on_start", 0),
+	("on_day_start", 0),
+	("on_session_start", 0),
+	("on_session_close", 0),
+	("on_month_end", 0),
+	("on_rollover", 1),
+	("on_fill", 1),
+	("on_margin_call", 0),
+	("on_finish", 0)
+];
+
 type ApiContextCell = Rc<RefCell<ApiContext>>;
 
 /*
@@ -84,16 +99,11 @@ impl<'a> ScriptStrategy<'a> {
 	pub const ID: &'static str = "script";
 
 	pub fn new(script: String, script_directory: &String, symbols: &Vec<String>, position_sizing: PositionSizing, contracts: Option<Vec<u32>>, margin_ratio: Option<f64>, parameters: HashMap<String, Dynamic>, backtest: Rc<RefCell<Backtest>>) -> Result<Self> {
-		// Basic restriction to prevent directory traversal attacks
-		let pattern = Regex::new("^[A-Za-z0-9 ]+$")?;
-		if !pattern.is_match(script.as_str()) {
-			bail!("Invalid characters in script path");
-		}
-		let file_name = format!("{script}.rhai");
-		let path = Path::new(script_directory).join(&file_name);
+		let path = Self::get_script_path(&script, script_directory)?;
 		let engine = Engine::new();
 		let script = engine.compile_file(path)
 			.map_err(|error| anyhow!("Failed to compile script: {error}"))?;
+		Self::validate_functions(&script)?;
 		let current_symbol = symbols.first()
 			.with_context(|| "No symbols specified")?
 			.clone();
@@ -133,9 +143,39 @@ impl<'a> ScriptStrategy<'a> {
 		Ok(strategy)
 	}
 
-	pub fn from_parameters(script_directory: &String, symbols: &Vec<String>, parameters: &StrategyParameters, backtest: Rc<RefCell<Backtest>>) -> Result<Self> {
+	// Rhai resolves functions by name and number of parameters so a hook with the wrong signature would never be called
+	fn validate_functions(script: &AST) -> Result<()> {
+		for (name, parameter_count) in SCRIPT_FUNCTIONS {
+			let mut overloads = script
+				.iter_functions()
+				.filter(|function| function.name == name)
+				.peekable();
+			let is_defined = overloads.peek().is_some();
+			if is_defined && !overloads.any(|function| function.params.len() == parameter_count) {
+				bail!("Function {name} must take {parameter_count} parameter(s)");
+			}
+		}
+		Ok(())
+	}
+
+	pub fn get_script_path(script: &String, script_directory: &String) -> Result<PathBuf> {
+		// Basic restriction to prevent directory traversal attacks
+		let pattern = Regex::new("^[A-Za-z0-9 ]+$")?;
+		if !pattern.is_match(script.as_str()) {
+			bail!("Invalid characters in script path");
+		}
+		let file_name = format!("{script}.rhai");
+		let path = Path::new(script_directory).join(&file_name);
+		Ok(path)
+	}
+
+	pub fn get_script_name(parameters: &StrategyParameters) -> Result<String> {
 		let script_parameter = parameters.get_string(SCRIPT_PARAMETER)?;
-		let script = script_parameter.with_context(|| "Script parameter has not been specified")?;
+		script_parameter.with_context(|| "Script parameter has not been specified")
+	}
+
+	pub fn from_parameters(script_directory: &String, symbols: &Vec<String>, parameters: &StrategyParameters, backtest: Rc<RefCell<Backtest>>) -> Result<Self> {
+		let script = Self::get_script_name(parameters)?;
 		let positions_parameter = parameters.get_string(POSITIONS_PARAMETER)?;
 		let contracts_parameter = parameters.get_values(CONTRACTS_PARAMETER)?;
 		let margin_ratio = parameters.get_value(MARGIN_RATIO_PARAMETER)?;
@@ -310,7 +350,7 @@ impl<'a> ScriptStrategy<'a> {
 	fn get_contract_counts(&self) -> HashMap<String, u32> {
 		// Count contracts per symbol using the remaining symbols
 		let positions = self.backtest.borrow().get_positions();
-		let mut contract_counts = HashMap::new();
+		let mut contract_counts: HashMap<String, u32> = HashMap::new();
 		for position in positions {
 			let position_symbol = position.asset.symbol;
 			let new_count = if let Some(count) = contract_counts.get(&position_symbol) {
@@ -418,58 +458,72 @@ impl<'a> ScriptStrategy<'a> {
 	}
 
 	fn register_indicators(&mut self) {
+		// Plain indicators use the time frame of the backtest, the suffixed variants such as "sma_daily" completed records of a higher time frame
+		let time_frames = [
+			("", None),
+			("_hourly", Some(ResamplePeriod::new(PeriodUnit::Hour, 1))),
+			("_daily", Some(ResamplePeriod::new(PeriodUnit::Day, 1))),
+			("_weekly", Some(ResamplePeriod::new(PeriodUnit::Week, 1))),
+			("_monthly", Some(ResamplePeriod::new(PeriodUnit::Month, 1)))
+		];
+		for (suffix, period) in time_frames {
+			self.register_time_frame_indicators(suffix, period);
+		}
+	}
+
+	fn register_time_frame_indicators(&mut self, suffix: &str, time_frame: Option<ResamplePeriod>) {
 		let engine = &mut self.engine;
 		let context = self.context.clone();
-		engine.register_fn("close", move |offset: i64| {
-			context.borrow_mut().close_lagged(offset)
+		engine.register_fn(format!("close{suffix}"), move |offset: i64| {
+			context.borrow_mut().close_lagged(offset, time_frame)
 		});
 		let context = self.context.clone();
-		engine.register_fn("sma", move |period: i64| {
-			context.borrow_mut().simple_moving_average(period)
+		engine.register_fn(format!("sma{suffix}"), move |period: i64| {
+			context.borrow_mut().simple_moving_average(period, time_frame)
 		});
 		let context = self.context.clone();
-		engine.register_fn("lma", move |period: i64| {
-			context.borrow_mut().linear_moving_average(period)
+		engine.register_fn(format!("lma{suffix}"), move |period: i64| {
+			context.borrow_mut().linear_moving_average(period, time_frame)
 		});
 		let context = self.context.clone();
-		engine.register_fn("ema", move |period: i64| {
-			context.borrow_mut().exponential_moving_average(period)
+		engine.register_fn(format!("ema{suffix}"), move |period: i64| {
+			context.borrow_mut().exponential_moving_average(period, time_frame)
 		});
 		let context = self.context.clone();
-		engine.register_fn("rsi", move |period: i64| {
-			context.borrow_mut().relative_strength_indicator(period)
+		engine.register_fn(format!("rsi{suffix}"), move |period: i64| {
+			context.borrow_mut().relative_strength_indicator(period, time_frame)
 		});
 		let context = self.context.clone();
-		engine.register_fn("macd", move |signal_period: i64, fast_period: i64, slow_period: i64| {
-			context.borrow_mut().moving_average_convergence(signal_period, fast_period, slow_period)
+		engine.register_fn(format!("macd{suffix}"), move |signal_period: i64, fast_period: i64, slow_period: i64| {
+			context.borrow_mut().moving_average_convergence(signal_period, fast_period, slow_period, time_frame)
 		});
 		let context = self.context.clone();
-		engine.register_fn("ppo", move |signal_period: i64, fast_period: i64, slow_period: i64| {
-			context.borrow_mut().percentage_price_oscillator(signal_period, fast_period, slow_period)
+		engine.register_fn(format!("ppo{suffix}"), move |signal_period: i64, fast_period: i64, slow_period: i64| {
+			context.borrow_mut().percentage_price_oscillator(signal_period, fast_period, slow_period, time_frame)
 		});
 		let context = self.context.clone();
-		engine.register_fn("bollinger", move |period: i64, multiplier: f64| {
-			context.borrow_mut().bollinger_band(period, multiplier)
+		engine.register_fn(format!("bollinger{suffix}"), move |period: i64, multiplier: f64| {
+			context.borrow_mut().bollinger_band(period, multiplier, time_frame)
 		});
 		let context = self.context.clone();
-		engine.register_fn("keltner", move |period: i64, multiplier: f64| {
-			context.borrow_mut().keltner_channel(period, multiplier)
+		engine.register_fn(format!("keltner{suffix}"), move |period: i64, multiplier: f64| {
+			context.borrow_mut().keltner_channel(period, multiplier, time_frame)
 		});
 		let context = self.context.clone();
-		engine.register_fn("donchian", move |period: i64| {
-			context.borrow_mut().donchian_channel(period)
+		engine.register_fn(format!("donchian{suffix}"), move |period: i64| {
+			context.borrow_mut().donchian_channel(period, time_frame)
 		});
 		let context = self.context.clone();
-		engine.register_fn("adx", move |period: i64| {
-			context.borrow_mut().average_directional_index(period)
+		engine.register_fn(format!("adx{suffix}"), move |period: i64| {
+			context.borrow_mut().average_directional_index(period, time_frame)
 		});
 		let context = self.context.clone();
-		engine.register_fn("atr", move |period: i64| {
-			context.borrow_mut().average_true_range(period)
+		engine.register_fn(format!("atr{suffix}"), move |period: i64| {
+			context.borrow_mut().average_true_range(period, time_frame)
 		});
 		let context = self.context.clone();
-		engine.register_fn("roc", move |period: i64| {
-			context.borrow_mut().rate_of_change(period)
+		engine.register_fn(format!("roc{suffix}"), move |period: i64| {
+			context.borrow_mut().rate_of_change(period, time_frame)
 		});
 	}
 
@@ -486,6 +540,41 @@ impl<'a> ScriptStrategy<'a> {
 		let mut context = self.context.borrow_mut();
 		context.update_indicators(symbol, record)
 	}
+
+	// Lifecycle functions such as "on_month_end" are optional, only "next" must be defined by the script
+	fn call_hook(&mut self, name: &str, arguments: impl FuncArgs) -> Result<()> {
+		let mut values: Vec<Dynamic> = Vec::new();
+		arguments.parse(&mut values);
+		let is_defined = self.script
+			.iter_functions()
+			.any(|function| function.name == name && function.params.len() == values.len());
+		if is_defined {
+			// The return values of hooks are ignored
+			let _ = self.engine.call_fn::<Dynamic>(&mut self.scope, &self.script, name, values)
+				.map_err(|error| anyhow!("Failed to execute {name} function: {error}"))?;
+		}
+		Ok(())
+	}
+
+	fn get_fill_map(fill: &Fill) -> Map {
+		let mut map = Map::new();
+		map.insert("position_id".into(), (fill.position_id as i64).into());
+		map.insert("symbol".into(), fill.symbol.clone().into());
+		map.insert("side".into(), fill.side.to_string().into());
+		map.insert("count".into(), (fill.count as i64).into());
+		map.insert("price".into(), fill.price.into());
+		map.insert("opening".into(), fill.opening.into());
+		map
+	}
+
+	fn get_rollover_map(rollover: &Rollover) -> Map {
+		let mut map = Map::new();
+		map.insert("previous_position_id".into(), (rollover.previous_position_id as i64).into());
+		map.insert("previous_symbol".into(), rollover.previous_symbol.clone().into());
+		map.insert("position_id".into(), (rollover.position_id as i64).into());
+		map.insert("symbol".into(), rollover.symbol.clone().into());
+		map
+	}
 }
 
 impl<'a> Strategy for ScriptStrategy<'a> {
@@ -509,4 +598,42 @@ impl<'a> Strategy for ScriptStrategy<'a> {
 		self.adjust_positions(&position_targets)?;
 		Ok(())
 	}
+
+	fn on_start(&mut self) -> Result<()> {
+		self.call_hook("on_start", ())
+	}
+
+	fn on_day_start(&mut self) -> Result<()> {
+		self.call_hook("on_day_start", ())
+	}
+
+	fn on_session_start(&mut self) -> Result<()> {
+		self.call_hook("on_session_start", ())
+	}
+
+	fn on_session_close(&mut self) -> Result<()> {
+		self.call_hook("on_session_close", ())
+	}
+
+	fn on_month_end(&mut self) -> Result<()> {
+		self.call_hook("on_month_end", ())
+	}
+
+	fn on_rollover(&mut self, rollover: &Rollover) -> Result<()> {
+		let map = Self::get_rollover_map(rollover);
+		self.call_hook("on_rollover", (map,))
+	}
+
+	fn on_fill(&mut self, fill: &Fill) -> Result<()> {
+		let map = Self::get_fill_map(fill);
+		self.call_hook("on_fill", (map,))
+	}
+
+	fn on_margin_call(&mut self) -> Result<()> {
+		self.call_hook("on_margin_call", ())
+	}
+
+	fn on_finish(&mut self) -> Result<()> {
+		self.call_hook("on_finish", ())
+	}
 }
\ No newline at end of file
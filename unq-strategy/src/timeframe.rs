@@ -0,0 +1,82 @@
+use anyhow::{bail, Result};
+use chrono::NaiveDateTime;
+use unq_common::backtest::Backtest;
+use unq_common::ohlc::{OhlcRecord, TimeFrame};
+use unq_common::resample::{Alignment, PeriodUnit, ResamplePeriod};
+use crate::technical::Indicator;
+
+/*
+Records of a different time frame than the one the backtest iterates over, e.g. daily bars in an intraday backtest.
+Indicators only ever receive completed records to prevent look-ahead bias. A record is completed at the end of its period,
+e.g. the daily record of a Monday is passed on once the backtest reaches Tuesday 00:00.
+*/
+#[derive(Clone)]
+pub struct HigherTimeFrame {
+	// Resampled records along with the time at which they are completed, in ascending order
+	records: Vec<(NaiveDateTime, OhlcRecord)>,
+	// Index of the first record that hasn't been passed on to the indicator yet, None before the first update
+	position: Option<usize>
+}
+
+impl HigherTimeFrame {
+	/*
+	Returns None if the period matches the time frame of the backtest, in which case the indicator should be updated with the
+	records of the backtest instead.
+	*/
+	pub fn new(symbol: &String, period: &ResamplePeriod, backtest: &Backtest) -> Result<Option<HigherTimeFrame>> {
+		if period.count == 0 {
+			bail!("Invalid time frame for indicator");
+		}
+		let time_frame = backtest.get_time_frame();
+		let is_daily = period.unit == PeriodUnit::Day && period.count == 1;
+		if (is_daily && *time_frame == TimeFrame::Daily) || time_frame.get_period() == Some(*period) {
+			return Ok(None);
+		}
+		let resampled_records = backtest.get_resampled_records(symbol, period)?;
+		let mut records = Vec::with_capacity(resampled_records.len());
+		let mut iterator = resampled_records.into_iter().peekable();
+		while let Some(record) = iterator.next() {
+			// Periods that depend on the data are only completed once the next one starts
+			let end = period.get_end(record.time, Alignment::Clock)
+				.or_else(|| iterator.peek().map(|x| x.time))
+				.unwrap_or(NaiveDateTime::MAX);
+			records.push((end, record));
+		}
+		let time_frame = HigherTimeFrame {
+			records,
+			position: None
+		};
+		Ok(Some(time_frame))
+	}
+
+	/*
+	Passes the records completed since the previous update to the indicator.
+	The first update fills up the buffer of the indicator with as many completed records as it needs.
+	*/
+	pub fn update(&mut self, now: &NaiveDateTime, indicator: &mut Box<dyn Indicator>) {
+		let completed = self.records.partition_point(|(end, _)| end <= now);
+		let start = match self.position {
+			Some(position) => position,
+			None => {
+				let bars = indicator.needs_initialization().unwrap_or(0);
+				completed.saturating_sub(bars)
+			}
+		};
+		for (_, record) in &self.records[start.min(completed)..completed] {
+			indicator.next(record);
+		}
+		self.position = Some(completed.max(start));
+	}
+}
+
+// Parses the names used by strategy parameters, e.g. "daily" or "weekly"
+pub fn parse_time_frame(name: &str) -> Result<ResamplePeriod> {
+	let unit = match name {
+		"hourly" => PeriodUnit::Hour,
+		"daily" => PeriodUnit::Day,
+		"weekly" => PeriodUnit::Week,
+		"monthly" => PeriodUnit::Month,
+		_ => bail!("Unknown indicator time frame \"{name}\"")
+	};
+	Ok(ResamplePeriod::new(unit, 1))
+}
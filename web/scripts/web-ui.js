@@ -123,11 +123,17 @@ export class WebUi {
 
 	invoke(url, request) {
 		const json = JSON.stringify(request);
+		const headers = {
+			"Content-Type": "application/json"
+		};
+		// Only required if the server has been configured with API keys
+		const apiKey = this.getLocalStorageData().apiKey;
+		if (apiKey != null) {
+			headers["Authorization"] = `Bearer ${apiKey}`;
+		}
 		const options = {
 			method: "POST",
-			headers: {
-				"Content-Type": "application/json"
-			},
+			headers: headers,
 			body: json
 		};
 		return new Promise((resolve, reject) => {